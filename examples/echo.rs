@@ -44,6 +44,9 @@ fn main() -> anyhow::Result<()> {
     })
     .expect("Failed to set ^C handler");
 
+    // The echo frame is reused, with its ID and data updated for each reply
+    let mut echo_frame = CanFrame::new(StandardId::ZERO, &[]).expect("Failed to create frame");
+
     while !QUIT.load(Ordering::Relaxed) {
         if let Ok(frame) = sock.read_frame_timeout(Duration::from_millis(100)) {
             println!("{}", frame_to_string(&frame));
@@ -51,7 +54,8 @@ fn main() -> anyhow::Result<()> {
             let new_id = frame.raw_id() + 0x01;
             let new_id = StandardId::new(new_id as u16).expect("Failed to create ID");
 
-            if let Some(echo_frame) = CanFrame::new(new_id, frame.data()) {
+            echo_frame.set_id(new_id);
+            if echo_frame.set_data(frame.data()).is_ok() {
                 sock.transmit(&echo_frame)
                     .expect("Failed to echo recieved frame");
             }
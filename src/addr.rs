@@ -14,7 +14,12 @@
 use libc::{sa_family_t, sockaddr, sockaddr_can, sockaddr_storage, socklen_t};
 use nix::net::if_::if_nametoindex;
 use socket2::SockAddr;
-use std::{fmt, io, mem, mem::size_of, os::raw::c_int};
+use std::{
+    ffi::CStr,
+    fmt, io, mem,
+    mem::size_of,
+    os::raw::{c_char, c_int},
+};
 
 pub use libc::{AF_CAN, CAN_RAW, PF_CAN};
 
@@ -47,6 +52,39 @@ impl CanAddr {
         Ok(Self::new(ifindex))
     }
 
+    /// Try to create an address from an interface name, also returning the
+    /// index that the name resolved to.
+    ///
+    /// This is the same as [`CanAddr::from_iface`], but saves a tool that
+    /// reports both the name and index of an interface from having to look
+    /// up the index again.
+    pub fn from_iface_named(ifname: &str) -> io::Result<(Self, u32)> {
+        let addr = Self::from_iface(ifname)?;
+        Ok((addr, addr.ifindex()))
+    }
+
+    /// Gets the interface index of the address.
+    /// An index of zero refers to all interfaces.
+    pub fn ifindex(&self) -> u32 {
+        self.0.can_ifindex as u32
+    }
+
+    /// Looks up the name of the interface for this address, like "can0".
+    ///
+    /// This is the reverse of [`CanAddr::from_iface`]. It fails if the
+    /// interface index does not refer to an existing interface, such as
+    /// when the interface has been removed since the address was captured,
+    /// or for an index of zero (all interfaces).
+    pub fn iface_name(&self) -> io::Result<String> {
+        let mut buf = [0 as c_char; libc::IF_NAMESIZE];
+        let ret = unsafe { libc::if_indextoname(self.ifindex(), buf.as_mut_ptr()) };
+        if ret.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+        Ok(name.to_string_lossy().into_owned())
+    }
+
     /// Gets the address of the structure as a `sockaddr_can` pointer.
     pub fn as_ptr(&self) -> *const sockaddr_can {
         &self.0
@@ -118,6 +156,29 @@ impl From<CanAddr> for SockAddr {
     }
 }
 
+impl TryFrom<SockAddr> for CanAddr {
+    type Error = io::Error;
+
+    /// Tries to convert a `socket2::SockAddr` into a CAN address.
+    ///
+    /// This fails if the address is not in the CAN address family. Note
+    /// that the kernel might return an address that is shorter than the
+    /// full `sockaddr_can` struct, in which case the remaining fields are
+    /// left as zero.
+    fn try_from(addr: SockAddr) -> io::Result<Self> {
+        if addr.family() != AF_CAN as sa_family_t {
+            return Err(io::Error::from(io::ErrorKind::InvalidInput));
+        }
+
+        let len = (addr.len() as usize).min(Self::len());
+        let sock_addr = unsafe { std::slice::from_raw_parts(addr.as_ptr() as *const u8, len) };
+
+        let mut can_addr = Self::default();
+        crate::as_bytes_mut(&mut can_addr.0)[..len].copy_from_slice(sock_addr);
+        Ok(can_addr)
+    }
+}
+
 impl AsRef<sockaddr_can> for CanAddr {
     fn as_ref(&self) -> &sockaddr_can {
         &self.0
@@ -140,13 +201,48 @@ mod tests {
         assert_eq!(size_of::<sockaddr_can>(), CanAddr::len());
     }
 
+    #[test]
+    fn test_from_iface_named() {
+        // Any interface resolves, even if it isn't CAN
+        let (addr, ifindex) = CanAddr::from_iface_named("lo").unwrap();
+        assert_ne!(0, ifindex);
+        assert_eq!(ifindex, addr.ifindex());
+        assert_eq!("lo", addr.iface_name().unwrap());
+
+        assert!(CanAddr::from_iface_named("an_invalid_name").is_err());
+    }
+
     #[test]
     fn test_addr_to_sock_addr() {
         let addr = CanAddr::new(IDX);
 
-        let (sock_addr, len) = addr.clone().into_storage();
+        let (sock_addr, len) = addr.into_storage();
 
         assert_eq!(CanAddr::len() as socklen_t, len);
         assert_eq!(as_bytes(&addr), &as_bytes(&sock_addr)[0..len as usize]);
     }
+
+    #[test]
+    fn test_sock_addr_to_addr() {
+        let addr = CanAddr::new(IDX);
+        assert_eq!(IDX, addr.ifindex());
+
+        let addr = CanAddr::try_from(addr.into_sock_addr()).unwrap();
+        assert_eq!(IDX, addr.ifindex());
+        assert_eq!(AF_CAN as sa_family_t, addr.as_ref().can_family);
+
+        let sock_addr = SockAddr::from(std::net::SocketAddr::from(([127, 0, 0, 1], 8080)));
+        assert!(CanAddr::try_from(sock_addr).is_err());
+    }
+
+    #[test]
+    fn test_iface_name() {
+        assert!(CanAddr::new(0).iface_name().is_err());
+        assert!(CanAddr::new(u32::MAX).iface_name().is_err());
+
+        // The loopback interface should be available on any Linux host.
+        if let Ok(addr) = CanAddr::from_iface("lo") {
+            assert_eq!("lo", addr.iface_name().unwrap());
+        }
+    }
 }
@@ -11,10 +11,13 @@
 
 //! Bindings to async-io for CANbus 2.0 and FD sockets using SocketCAN on Linux.
 
-use crate::{frame::AsPtr, CanAnyFrame, CanFrame, Socket, SocketOptions};
+use crate::{frame::AsPtr, CanAnyFrame, CanFrame, IoErrorKind, Socket, SocketOptions};
+use futures::{ready, stream::Stream};
 use std::{
     io,
-    os::unix::io::{AsRawFd, RawFd},
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    pin::Pin,
+    task::{Context, Poll},
 };
 
 #[cfg(any(feature = "async-io", feature = "async-std"))]
@@ -28,7 +31,48 @@ use smol::Async;
 
 /////////////////////////////////////////////////////////////////////////////
 
+/// Polls the socket for the next frame, for use by a `Stream`.
+///
+/// Transient errors, such as `WouldBlock` or `Interrupted`, are retried,
+/// while any other error is returned as an item. The stream only ends on a
+/// read of zero bytes, which a raw CAN socket never returns.
+fn poll_next_frame<S: Socket>(
+    sock: &Async<S>,
+    cx: &mut Context<'_>,
+) -> Poll<Option<io::Result<S::FrameType>>> {
+    loop {
+        match sock.get_ref().read_frame() {
+            Ok(frame) => return Poll::Ready(Some(Ok(frame))),
+            Err(err) if err.kind() == IoErrorKind::UnexpectedEof => return Poll::Ready(None),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    IoErrorKind::WouldBlock | IoErrorKind::Interrupted
+                ) =>
+            {
+                if let Err(err) = ready!(sock.poll_readable(cx)) {
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+            Err(err) => return Poll::Ready(Some(Err(err))),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
 /// An asynchronous CAN socket for use with `async-io`.
+///
+/// The socket can be used as a `Stream` of received frames. Transient
+/// conditions, like there being no frame available yet, are handled
+/// internally, while any other error is yielded as an item, leaving it
+/// to the caller to decide whether to stop. A raw CAN socket has no end of
+/// stream, so the stream doesn't end on its own; drop it to stop reading.
+///
+/// The socket options can be set through the `SocketOptions` trait, just
+/// as on a blocking socket. Note that these calls are blocking, even on an
+/// asynchronous socket, but setting an option doesn't wait on the bus, so
+/// they return right away.
 #[derive(Debug)]
 pub struct CanSocket(Async<crate::CanSocket>);
 
@@ -57,6 +101,14 @@ impl CanSocket {
 
 impl SocketOptions for CanSocket {}
 
+impl Stream for CanSocket {
+    type Item = io::Result<CanFrame>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        poll_next_frame(&self.0, cx)
+    }
+}
+
 impl TryFrom<crate::CanSocket> for CanSocket {
     type Error = io::Error;
 
@@ -71,9 +123,19 @@ impl AsRawFd for CanSocket {
     }
 }
 
+impl AsFd for CanSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.get_ref().as_fd()
+    }
+}
+
 /////////////////////////////////////////////////////////////////////////////
 
-/// An asynchronous CAN socket for use with `async-io`.
+/// An asynchronous CAN FD socket for use with `async-io`.
+///
+/// Like the classic socket, this can be used as a `Stream` of the
+/// received frames, and the socket options can be set through the
+/// `SocketOptions` trait.
 #[derive(Debug)]
 pub struct CanFdSocket(Async<crate::CanFdSocket>);
 
@@ -102,6 +164,14 @@ impl CanFdSocket {
 
 impl SocketOptions for CanFdSocket {}
 
+impl Stream for CanFdSocket {
+    type Item = io::Result<CanAnyFrame>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        poll_next_frame(&self.0, cx)
+    }
+}
+
 impl TryFrom<crate::CanFdSocket> for CanFdSocket {
     type Error = io::Error;
 
@@ -115,3 +185,9 @@ impl AsRawFd for CanFdSocket {
         self.0.as_raw_fd()
     }
 }
+
+impl AsFd for CanFdSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.get_ref().as_fd()
+    }
+}
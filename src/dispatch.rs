@@ -0,0 +1,257 @@
+// socketcan/src/dispatch.rs
+//
+// Implements routing of received CAN frames to handlers by ID.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Routing received frames to handlers by CAN ID.
+
+use crate::{Frame, IoErrorKind, IoResult, ShouldRetry, Socket};
+use libc::canid_t;
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// A frame handler registered with a [`Dispatcher`].
+type FrameHandler<'a, F> = Box<dyn FnMut(&F) + 'a>;
+
+/// Reads frames from a socket and routes them to handlers by CAN ID.
+///
+/// This wraps the read loop for signal-oriented code, such as GUI or
+/// telemetry apps, that would rather register a handler for each ID than
+/// match on the IDs of the frames. The handlers are looked up by the raw
+/// ID of each frame, without the EFF/RTR/ERR flags. Error frames, and
+/// frames with no handler for their ID, are passed to the handler set with
+/// `on_other()`, if any, or are discarded.
+///
+/// The frames are routed in the process, so every frame is still read from
+/// the socket. To keep the kernel from queueing frames that have no
+/// handler at all, set filters on the socket for the IDs of interest.
+///
+/// ```no_run
+/// use socketcan::{CanSocket, Dispatcher, EmbeddedFrame, Socket};
+///
+/// let sock = CanSocket::open("vcan0").unwrap();
+/// let mut speed = 0;
+///
+/// let mut dispatcher = Dispatcher::new(&sock);
+/// dispatcher
+///     .on_id(0x123, |frame| speed = frame.data()[0])
+///     .on_id(0x456, |frame| println!("Status: {:?}", frame.data()));
+/// dispatcher.run().unwrap();
+/// ```
+pub struct Dispatcher<'a, S: Socket> {
+    sock: &'a S,
+    handlers: HashMap<canid_t, FrameHandler<'a, S::FrameType>>,
+    other: Option<FrameHandler<'a, S::FrameType>>,
+}
+
+impl<'a, S> Dispatcher<'a, S>
+where
+    S: Socket,
+    S::FrameType: Frame,
+{
+    /// Creates a dispatcher for the socket, with no handlers.
+    pub fn new(sock: &'a S) -> Self {
+        Self {
+            sock,
+            handlers: HashMap::new(),
+            other: None,
+        }
+    }
+
+    /// Registers the handler for the frames with the raw CAN ID.
+    ///
+    /// This replaces any handler that was already registered for the ID.
+    pub fn on_id<F>(&mut self, id: canid_t, handler: F) -> &mut Self
+    where
+        F: FnMut(&S::FrameType) + 'a,
+    {
+        self.handlers.insert(id, Box::new(handler));
+        self
+    }
+
+    /// Registers the handler for error frames, and for all the frames that
+    /// have no handler for their ID.
+    pub fn on_other<F>(&mut self, handler: F) -> &mut Self
+    where
+        F: FnMut(&S::FrameType) + 'a,
+    {
+        self.other = Some(Box::new(handler));
+        self
+    }
+
+    /// Removes the handler for the raw CAN ID, returning whether there
+    /// was one.
+    pub fn remove_id(&mut self, id: canid_t) -> bool {
+        self.handlers.remove(&id).is_some()
+    }
+
+    /// Determines if there is a handler registered for the raw CAN ID.
+    pub fn has_id(&self, id: canid_t) -> bool {
+        self.handlers.contains_key(&id)
+    }
+
+    /// Gets a reference to the underlying socket.
+    pub fn get_ref(&self) -> &S {
+        self.sock
+    }
+
+    /// Passes a frame to its handler, returning whether it had one.
+    ///
+    /// This can route frames that were read from elsewhere, like a
+    /// capture.
+    pub fn route(&mut self, frame: &S::FrameType) -> bool {
+        let handler = match frame.is_error_frame() {
+            true => None,
+            false => self.handlers.get_mut(&frame.raw_id()),
+        };
+        match handler.or(self.other.as_mut()) {
+            Some(handler) => {
+                handler(frame);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Blocking read of a single frame from the socket, passing it to its
+    /// handler.
+    ///
+    /// This returns whether the frame had a handler, or any error from
+    /// the read.
+    pub fn dispatch(&mut self) -> IoResult<bool> {
+        let frame = self.sock.read_frame()?;
+        Ok(self.route(&frame))
+    }
+
+    /// Reads frames from the socket and passes them to their handlers,
+    /// until a read fails.
+    ///
+    /// Timeouts and interrupted reads are retried, and any other error
+    /// ends the loop and is returned. A raw CAN socket never reaches the
+    /// end of its stream, so use `run_for()` to regain control
+    /// periodically.
+    pub fn run(&mut self) -> IoResult<()> {
+        loop {
+            match self.dispatch() {
+                Ok(_) => (),
+                Err(e) if e.kind() == IoErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) if e.kind() == IoErrorKind::Interrupted => (),
+                Err(e) if e.should_retry() => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Reads frames from the socket and passes them to their handlers,
+    /// for a period of time.
+    ///
+    /// This returns once the time has elapsed, or with the first I/O
+    /// error, other than an interrupted read.
+    pub fn run_for(&mut self, duration: Duration) -> IoResult<()> {
+        let deadline = Instant::now() + duration;
+        loop {
+            match self.sock.read_frame_deadline(deadline) {
+                Ok(frame) => {
+                    self.route(&frame);
+                }
+                Err(e) if e.kind() == IoErrorKind::TimedOut => return Ok(()),
+                Err(e) if e.kind() == IoErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) if e.kind() == IoErrorKind::Interrupted => (),
+                Err(e) if e.should_retry() => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<S: Socket> fmt::Debug for Dispatcher<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ids: Vec<_> = self.handlers.keys().collect();
+        ids.sort();
+        f.debug_struct("Dispatcher")
+            .field("ids", &ids)
+            .field("other", &self.other.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{frame::AsPtr, socket::tests::socket_pair, CanErrorFrame, CanFrame};
+    use std::{mem, os::raw::c_int, ptr, thread};
+
+    #[test]
+    fn test_dispatcher() {
+        let (sock, peer) = socket_pair();
+        let data_frame = |id, data: &[u8]| CanFrame::from_raw_id(id, data).unwrap();
+        for frame in [
+            data_frame(0x123, &[1]),
+            data_frame(0x456, &[2]),
+            data_frame(0x123, &[3]),
+            data_frame(0x789, &[4]),
+        ] {
+            peer.send(frame.as_bytes()).unwrap();
+        }
+        peer.send(CanErrorFrame::new_error(0x0040, &[]).unwrap().as_bytes())
+            .unwrap();
+
+        let (mut rx_123, mut rx_456, mut others) = (Vec::new(), 0, 0);
+        {
+            let mut dispatcher = Dispatcher::new(&sock);
+            dispatcher
+                .on_id(0x123, |frame| {
+                    rx_123.push(embedded_can::Frame::data(frame)[0])
+                })
+                .on_id(0x456, |_| rx_456 += 1);
+            assert!(dispatcher.has_id(0x123));
+
+            assert!(dispatcher.dispatch().unwrap());
+            assert!(dispatcher.dispatch().unwrap());
+            assert!(dispatcher.remove_id(0x456));
+            assert!(!dispatcher.remove_id(0x456));
+
+            dispatcher.on_other(|_| others += 1);
+            dispatcher.run_for(Duration::from_millis(20)).unwrap();
+        }
+        assert_eq!(vec![1, 3], rx_123);
+        assert_eq!(1, rx_456);
+        assert_eq!(2, others);
+    }
+
+    #[test]
+    fn test_dispatcher_interrupted() {
+        extern "C" fn on_signal(_: c_int) {}
+
+        // A caught signal interrupts the poll for the next frame
+        unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = on_signal as *const () as libc::sighandler_t;
+            assert_eq!(0, libc::sigaction(libc::SIGUSR1, &action, ptr::null_mut()));
+        }
+
+        let (sock, _peer) = socket_pair();
+        let reader = unsafe { libc::pthread_self() };
+        let signaler = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            unsafe { libc::pthread_kill(reader, libc::SIGUSR1) };
+        });
+
+        let start = Instant::now();
+        Dispatcher::new(&sock)
+            .run_for(Duration::from_millis(50))
+            .unwrap();
+        // Polling rounds down to the millisecond, so allow some slack
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        signaler.join().unwrap();
+    }
+}
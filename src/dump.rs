@@ -24,13 +24,12 @@
 //! [csv](https://crates.io/crates/csv) crate.
 
 use crate::{
-    frame::{FdFlags, IdFlags},
-    CanDataFrame, CanFdFrame,
+    frame::{can_frame_default, FdFlags, CAN_EFF_FLAG, CAN_EFF_MASK, CAN_RTR_FLAG, CAN_SFF_MASK},
+    CanAnyFrame, CanDataFrame, CanFdFrame, CanFrame,
 };
-use embedded_can::StandardId;
 use hex::FromHex;
 use libc::canid_t;
-use std::{fs, io, path};
+use std::{error, fmt, fs, io, path};
 
 // cannot be generic, because from_str_radix is not part of any Trait
 fn parse_raw(bytes: &[u8], radix: u32) -> Option<u64> {
@@ -80,7 +79,7 @@ pub struct CanDumpRecord<'a> {
     /// The name of the device
     pub device: &'a str,
     /// The parsed frame
-    pub frame: super::CanAnyFrame,
+    pub frame: CanAnyFrame,
 }
 
 #[derive(Debug)]
@@ -96,10 +95,43 @@ pub enum ParseError {
     InvalidDeviceName,
     /// Invalid CAN frame
     InvalidCanFrame,
+    /// Invalid CAN ID in a frame
+    InvalidCanId,
+    /// Invalid data in a frame
+    InvalidCanData,
     /// Error creating the frame
     ConstructionError(super::ConstructionError),
 }
 
+impl error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ParseError::Io(e) => Some(e),
+            ParseError::ConstructionError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ParseError::*;
+        match self {
+            Io(e) => write!(f, "{}", e),
+            UnexpectedEndOfLine => write!(f, "unexpected end of line"),
+            InvalidTimestamp => write!(f, "invalid timestamp"),
+            InvalidDeviceName => write!(f, "invalid device name"),
+            InvalidCanFrame => write!(
+                f,
+                "invalid CAN frame, expected <id>#<data>, <id>#R<dlc>, or <id>##<flags><data>"
+            ),
+            InvalidCanId => write!(f, "invalid CAN ID, expected up to 8 hex digits"),
+            InvalidCanData => write!(f, "invalid CAN frame data, expected pairs of hex digits"),
+            ConstructionError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
 impl From<io::Error> for ParseError {
     fn from(e: io::Error) -> ParseError {
         ParseError::Io(e)
@@ -114,12 +146,12 @@ impl From<super::ConstructionError> for ParseError {
 
 impl<R: io::BufRead> Reader<R> {
     /// Returns an iterator over all records
-    pub fn records(&mut self) -> CanDumpRecords<R> {
+    pub fn records(&mut self) -> CanDumpRecords<'_, R> {
         CanDumpRecords { src: self }
     }
 
     /// Advance state, returning next record.
-    pub fn next_record(&mut self) -> Result<Option<CanDumpRecord>, ParseError> {
+    pub fn next_record(&mut self) -> Result<Option<CanDumpRecord<'_>>, ParseError> {
         self.line_buf.clear();
         let bytes_read = self.rdr.read_until(b'\n', &mut self.line_buf)?;
 
@@ -160,59 +192,11 @@ impl<R: io::BufRead> Reader<R> {
         // parse packet
         let can_raw = field_iter.next().ok_or(ParseError::UnexpectedEndOfLine)?;
 
-        let sep_idx = can_raw
+        let end = can_raw
             .iter()
-            .position(|&c| c == b'#')
-            .ok_or(ParseError::InvalidCanFrame)?;
-        let (can_id, mut can_data) = can_raw.split_at(sep_idx);
-
-        // determine frame type (FD or classical) and skip separator(s)
-        let mut fd_flags = FdFlags::empty();
-        let is_fd_frame = if let Some(&b'#') = can_data.get(1) {
-            fd_flags = FdFlags::from_bits_truncate(can_data[2]);
-            can_data = &can_data[3..];
-            true
-        } else {
-            can_data = &can_data[1..];
-            false
-        };
-
-        // cut of linefeed
-        if let Some(&b'\n') = can_data.last() {
-            can_data = &can_data[..can_data.len() - 1];
-        };
-        // cut off \r
-        if let Some(&b'\r') = can_data.last() {
-            can_data = &can_data[..can_data.len() - 1];
-        };
-
-        let mut flags = IdFlags::empty();
-        flags.set(IdFlags::RTR, b"R" == can_data);
-        // TODO: How are error frames saved?
-
-        let data = if flags.contains(IdFlags::RTR) {
-            Vec::new()
-        } else {
-            Vec::from_hex(can_data).map_err(|_| ParseError::InvalidCanFrame)?
-        };
-        let frame: super::CanAnyFrame = if is_fd_frame {
-            CanFdFrame::init(
-                parse_raw(can_id, 16).ok_or(ParseError::InvalidCanFrame)? as canid_t | flags.bits(),
-                &data,
-                fd_flags,
-            )
-            .map(super::CanAnyFrame::Fd)
-        } else {
-            // TODO: Check for other frame types?
-            // is extended?
-            let can_id = parse_raw(can_id, 16).ok_or(ParseError::InvalidCanFrame)?;
-            if can_id >= StandardId::MAX.as_raw() as u64 {
-                flags.set(IdFlags::EFF, true);
-            }
-            CanDataFrame::init(can_id as canid_t | flags.bits(), &data)
-                .map(super::CanFrame::Data)
-                .map(|f| f.into())
-        }?;
+            .rposition(|&c| c != b'\n' && c != b'\r')
+            .map_or(0, |i| i + 1);
+        let frame = parse_frame(&can_raw[..end])?;
 
         Ok(Some(CanDumpRecord {
             t_us,
@@ -222,8 +206,106 @@ impl<R: io::BufRead> Reader<R> {
     }
 }
 
+/// Parses a single frame in the compact format of `candump -L` and
+/// `cansend`, such as "123#DEADBEEF".
+///
+/// The ID is in hex, and is extended if it has more than three digits, as
+/// candump writes it, or if it's too large for a standard ID. It's followed
+/// by one of:
+///
+/// - `#` and the data, as pairs of hex digits, optionally separated by
+///   dots, for a classic data frame, like "123#DE.AD.BE.EF". Eight bytes
+///   of data can be followed by an underscore and a raw DLC of 9-15.
+/// - `#R` and an optional DLC digit, for a remote frame, like "123#R2". A
+///   DLC of 8 can be followed by a raw DLC digit, like "123#R8C".
+/// - `##`, a hex digit of FD flags, and the data, for an FD frame, like
+///   "123##1DEADBEEF". The digit is kept as the raw flags of the frame.
+///
+/// Error frames are not supported.
+pub fn parse_frame(s: &[u8]) -> Result<CanAnyFrame, ParseError> {
+    let sep_idx = s
+        .iter()
+        .position(|&c| c == b'#')
+        .ok_or(ParseError::InvalidCanFrame)?;
+    let (can_id, rest) = (&s[..sep_idx], &s[sep_idx + 1..]);
+
+    if can_id.is_empty() || can_id.len() > 8 {
+        return Err(ParseError::InvalidCanId);
+    }
+    let id = parse_raw(can_id, 16)
+        .filter(|&id| id <= u64::from(CAN_EFF_MASK))
+        .ok_or(ParseError::InvalidCanId)? as canid_t;
+    let id = match can_id.len() > 3 || id > CAN_SFF_MASK {
+        true => id | CAN_EFF_FLAG,
+        false => id,
+    };
+
+    if let Some(rest) = rest.strip_prefix(b"#") {
+        let (&flags, data) = rest.split_first().ok_or(ParseError::InvalidCanFrame)?;
+        let flags = (flags as char)
+            .to_digit(16)
+            .ok_or(ParseError::InvalidCanFrame)?;
+        let data = parse_data(data)?;
+        let mut frame = CanFdFrame::init(id, &data, FdFlags::empty())?;
+        frame.set_raw_flags(flags as u8);
+        Ok(frame.into())
+    } else if let Some(dlc) = rest.strip_prefix(b"R") {
+        let (dlc, len8_dlc) = match dlc {
+            [] => (0, 0),
+            [dlc] => (parse_hex_digit(*dlc)?, 0),
+            [b'8', len8_dlc] => (8, parse_len8_dlc(*len8_dlc)?),
+            _ => return Err(ParseError::InvalidCanData),
+        };
+        if dlc > 8 {
+            return Err(super::ConstructionError::TooMuchData.into());
+        }
+        let mut frame = can_frame_default();
+        frame.can_id = id | CAN_RTR_FLAG;
+        frame.can_dlc = dlc;
+        frame.len8_dlc = len8_dlc;
+        Ok(CanFrame::from(frame).into())
+    } else {
+        let (data, len8_dlc) = match rest.iter().position(|&c| c == b'_') {
+            Some(idx) => match &rest[idx + 1..] {
+                [len8_dlc] => (&rest[..idx], parse_len8_dlc(*len8_dlc)?),
+                _ => return Err(ParseError::InvalidCanData),
+            },
+            None => (rest, 0),
+        };
+        let data = parse_data(data)?;
+        if len8_dlc != 0 && data.len() != 8 {
+            return Err(ParseError::InvalidCanData);
+        }
+        let mut frame = *CanDataFrame::init(id, &data)?.as_ref();
+        frame.len8_dlc = len8_dlc;
+        Ok(CanFrame::from(frame).into())
+    }
+}
+
+// Parses a single hex digit.
+fn parse_hex_digit(c: u8) -> Result<u8, ParseError> {
+    (c as char)
+        .to_digit(16)
+        .map(|d| d as u8)
+        .ok_or(ParseError::InvalidCanData)
+}
+
+// Parses the raw DLC of 9-15 that can follow 8 bytes of classic data.
+fn parse_len8_dlc(c: u8) -> Result<u8, ParseError> {
+    match parse_hex_digit(c)? {
+        dlc @ 9..=15 => Ok(dlc),
+        _ => Err(ParseError::InvalidCanData),
+    }
+}
+
+// Parses the hex data of a frame, ignoring any dots between the bytes.
+fn parse_data(s: &[u8]) -> Result<Vec<u8>, ParseError> {
+    let hex: Vec<u8> = s.iter().copied().filter(|&c| c != b'.').collect();
+    Vec::from_hex(hex).map_err(|_| ParseError::InvalidCanData)
+}
+
 impl<'a, R: io::Read> Iterator for CanDumpRecords<'a, io::BufReader<R>> {
-    type Item = Result<(u64, super::CanAnyFrame), ParseError>;
+    type Item = Result<(u64, CanAnyFrame), ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         // lift Option:
@@ -238,7 +320,7 @@ impl<'a, R: io::Read> Iterator for CanDumpRecords<'a, io::BufReader<R>> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{CanAnyFrame, Frame};
+    use crate::{candump_format, CanAnyFrame, Frame};
     use embedded_can::Frame as EmbeddedFrame;
 
     #[test]
@@ -299,9 +381,9 @@ mod test {
 
             if let CanAnyFrame::Normal(frame) = rec1.frame {
                 assert_eq!(frame.raw_id(), 0x080080);
-                assert_eq!(frame.is_remote_frame(), false);
-                assert_eq!(frame.is_error_frame(), false);
-                assert_eq!(frame.is_extended(), true);
+                assert!(!frame.is_remote_frame());
+                assert!(!frame.is_error_frame());
+                assert!(frame.is_extended());
                 assert_eq!(frame.data(), &[]);
             } else {
                 panic!("Expected Normal frame, got FD");
@@ -315,9 +397,9 @@ mod test {
 
             if let CanAnyFrame::Normal(frame) = rec2.frame {
                 assert_eq!(frame.raw_id(), 0x053701);
-                assert_eq!(frame.is_remote_frame(), false);
-                assert_eq!(frame.is_error_frame(), false);
-                assert_eq!(frame.is_extended(), true);
+                assert!(!frame.is_remote_frame());
+                assert!(!frame.is_error_frame());
+                assert!(frame.is_extended());
                 assert_eq!(frame.data(), &[0x7F]);
             } else {
                 panic!("Expected Normal frame, got FD");
@@ -371,4 +453,106 @@ mod test {
 
         assert!(reader.next_record().unwrap().is_none());
     }
+
+    #[test]
+    fn test_parse_frame() {
+        let frame = CanFrame::from_hex("123#DEADBEEF").unwrap();
+        assert_eq!(frame.raw_id(), 0x123);
+        assert!(!frame.is_extended());
+        assert_eq!(frame.data(), &[0xDE, 0xAD, 0xBE, 0xEF]);
+
+        let frame = CanFrame::from_hex("7FF#DE.AD").unwrap();
+        assert_eq!(frame.raw_id(), 0x7FF);
+        assert!(!frame.is_extended());
+        assert_eq!(frame.data(), &[0xDE, 0xAD]);
+
+        let frame = CanFrame::from_hex("00000123#").unwrap();
+        assert_eq!(frame.raw_id(), 0x123);
+        assert!(frame.is_extended());
+        assert!(frame.data().is_empty());
+
+        let frame = CanFrame::from_hex("123#R3").unwrap();
+        assert!(frame.is_remote_frame());
+        assert_eq!(frame.dlc(), 3);
+
+        let frame = parse_frame(b"123##3112233").unwrap();
+        if let CanAnyFrame::Fd(frame) = frame {
+            assert!(frame.is_brs());
+            assert!(frame.is_esi());
+            assert_eq!(frame.data(), &[0x11, 0x22, 0x33]);
+        } else {
+            panic!("Expected FD frame");
+        }
+
+        assert!(matches!(
+            CanFrame::from_hex("123DEADBEEF"),
+            Err(ParseError::InvalidCanFrame)
+        ));
+        assert!(matches!(
+            CanFrame::from_hex("#DEADBEEF"),
+            Err(ParseError::InvalidCanId)
+        ));
+        assert!(matches!(
+            CanFrame::from_hex("123456789#00"),
+            Err(ParseError::InvalidCanId)
+        ));
+        assert!(matches!(
+            CanFrame::from_hex("12G#00"),
+            Err(ParseError::InvalidCanId)
+        ));
+        assert!(matches!(
+            CanFrame::from_hex("123#DEADBEE"),
+            Err(ParseError::InvalidCanData)
+        ));
+        assert!(matches!(
+            CanFrame::from_hex("123#001122334455667788"),
+            Err(ParseError::ConstructionError(_))
+        ));
+        assert!(matches!(
+            CanFrame::from_hex("123##100"),
+            Err(ParseError::ConstructionError(_))
+        ));
+
+        let frame = CanFrame::from_hex("123#0011223344556677_C").unwrap();
+        assert_eq!(frame.dlc(), 8);
+        assert_eq!(frame.as_ref().len8_dlc, 12);
+
+        let frame = CanFrame::from_hex("123#R8F").unwrap();
+        assert!(frame.is_remote_frame());
+        assert_eq!(frame.as_ref().len8_dlc, 15);
+
+        let frame = parse_frame(b"123##5AA").unwrap();
+        if let CanAnyFrame::Fd(frame) = frame {
+            assert_eq!(frame.raw_flags(), 0x05);
+        } else {
+            panic!("Expected FD frame");
+        }
+
+        assert!(matches!(
+            CanFrame::from_hex("123#0011_C"),
+            Err(ParseError::InvalidCanData)
+        ));
+        assert!(matches!(
+            CanFrame::from_hex("123#0011223344556677_8"),
+            Err(ParseError::InvalidCanData)
+        ));
+        assert!(matches!(
+            CanFrame::from_hex("123#R3C"),
+            Err(ParseError::InvalidCanData)
+        ));
+    }
+
+    // Each line of the fixture was written by `candump -L`, and formatting
+    // the parsed record should give back exactly the same line.
+    #[test]
+    fn test_candump_fixture() {
+        let input = include_str!("../tests/fixtures/candump.log");
+        let mut reader = Reader::from_reader(input.as_bytes());
+
+        for line in input.lines() {
+            let rec = reader.next_record().unwrap().unwrap();
+            assert_eq!(line, candump_format(rec.t_us, rec.device, &rec.frame));
+        }
+        assert!(reader.next_record().unwrap().is_none());
+    }
 }
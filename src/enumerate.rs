@@ -14,14 +14,34 @@
 //! This module provides functionality to enumerate available SocketCAN network
 //! interfaces.
 //!
+//! The [`list_interfaces`] function scans sysfs and is always available.
+//! The `available_interfaces` function uses libudev, and requires the
+//! `enumerate` feature.
 
-use crate::Result;
-
+use crate::IoResult;
 use libc::ARPHRD_CAN;
+use std::{fs, path::Path};
+
+#[cfg(feature = "enumerate")]
+use crate::Result;
+#[cfg(feature = "enumerate")]
 use libudev::{Context, Enumerator};
 
+/// The directory in sysfs with an entry for each network interface.
+const SYSFS_NET: &str = "/sys/class/net";
+
+/// The name and index of a CAN network interface.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InterfaceInfo {
+    /// The name of the interface, like "can0"
+    pub name: String,
+    /// The kernel index of the interface
+    pub index: u32,
+}
+
 /// Scans the system for available SocketCAN network interfaces and returns a
 /// list of them.
+#[cfg(feature = "enumerate")]
 pub fn available_interfaces() -> Result<Vec<String>> {
     let mut interfaces = Vec::new();
     if let Ok(context) = Context::new() {
@@ -39,3 +59,81 @@ pub fn available_interfaces() -> Result<Vec<String>> {
     }
     Ok(interfaces)
 }
+
+/// Lists the CAN network interfaces on the system, sorted by index.
+///
+/// This scans `/sys/class/net` for interfaces with a hardware type of CAN,
+/// which includes physical, virtual (vcan), and other CAN devices,
+/// without requiring libudev.
+pub fn list_interfaces() -> IoResult<Vec<InterfaceInfo>> {
+    list_interfaces_in(Path::new(SYSFS_NET))
+}
+
+/// Lists the CAN interfaces in a sysfs-style directory of network devices.
+fn list_interfaces_in(dir: &Path) -> IoResult<Vec<InterfaceInfo>> {
+    let read_attr = |path: &Path, attr: &str| -> Option<u32> {
+        fs::read_to_string(path.join(attr))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+
+    let mut interfaces = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if read_attr(&path, "type") != Some(u32::from(ARPHRD_CAN)) {
+            continue;
+        }
+        let name = path.file_name().and_then(|name| name.to_str());
+        if let (Some(name), Some(index)) = (name, read_attr(&path, "ifindex")) {
+            interfaces.push(InterfaceInfo {
+                name: name.into(),
+                index,
+            });
+        }
+    }
+    interfaces.sort_by_key(|iface| iface.index);
+    Ok(interfaces)
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn test_list_interfaces_in() {
+        let dir = env::temp_dir().join(format!("socketcan-enum-{}", process::id()));
+        for (name, ty, index) in [
+            ("vcan0", "280", "5"),
+            ("eth0", "1", "2"),
+            ("can0", "280", "3"),
+        ] {
+            let path = dir.join(name);
+            fs::create_dir_all(&path).unwrap();
+            fs::write(path.join("type"), format!("{}\n", ty)).unwrap();
+            fs::write(path.join("ifindex"), format!("{}\n", index)).unwrap();
+        }
+
+        let ifaces = list_interfaces_in(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let ifaces = ifaces.unwrap();
+        assert_eq!(
+            vec![
+                InterfaceInfo {
+                    name: "can0".into(),
+                    index: 3
+                },
+                InterfaceInfo {
+                    name: "vcan0".into(),
+                    index: 5
+                },
+            ],
+            ifaces
+        );
+    }
+}
@@ -39,7 +39,7 @@
 //!
 
 use crate::{CanErrorFrame, EmbeddedFrame, Frame};
-use std::{convert::TryFrom, error, fmt, io};
+use std::{convert::TryFrom, error, fmt, io, ops::Index};
 use thiserror::Error;
 
 // ===== Composite Error for the crate =====
@@ -225,6 +225,129 @@ impl From<CanErrorFrame> for CanError {
     }
 }
 
+// ===== ErrClass =====
+
+/// A class of error that can be reported by an error frame.
+///
+/// Each class is a bit in the error mask of a socket, and in the ID word of
+/// an error frame (the `CAN_ERR_*` values in `linux/can/error.h`). These
+/// can be used to select the errors that a socket receives, with
+/// `SocketOptions::set_error_classes()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum ErrClass {
+    /// TX timeout, by the netdevice driver (`CAN_ERR_TX_TIMEOUT`)
+    TransmitTimeout = 0x0001,
+    /// Lost arbitration (`CAN_ERR_LOSTARB`)
+    LostArbitration = 0x0002,
+    /// Controller problems (`CAN_ERR_CRTL`)
+    ControllerProblem = 0x0004,
+    /// Protocol violations (`CAN_ERR_PROT`)
+    ProtocolViolation = 0x0008,
+    /// Transceiver status (`CAN_ERR_TRX`)
+    TransceiverError = 0x0010,
+    /// No ACK received on transmission (`CAN_ERR_ACK`)
+    NoAck = 0x0020,
+    /// Bus off (`CAN_ERR_BUSOFF`)
+    BusOff = 0x0040,
+    /// Bus error (`CAN_ERR_BUSERROR`)
+    BusError = 0x0080,
+    /// The controller was restarted (`CAN_ERR_RESTARTED`)
+    Restarted = 0x0100,
+}
+
+impl ErrClass {
+    /// All of the error classes, in the order of their bits.
+    pub const ALL: [ErrClass; 9] = [
+        ErrClass::TransmitTimeout,
+        ErrClass::LostArbitration,
+        ErrClass::ControllerProblem,
+        ErrClass::ProtocolViolation,
+        ErrClass::TransceiverError,
+        ErrClass::NoAck,
+        ErrClass::BusOff,
+        ErrClass::BusError,
+        ErrClass::Restarted,
+    ];
+
+    /// Gets the bit in the error mask for the class.
+    pub fn mask(self) -> u32 {
+        self as u32
+    }
+
+    /// Gets the error mask that selects all of the specified classes.
+    pub fn mask_of(classes: &[ErrClass]) -> u32 {
+        classes.iter().fold(0, |mask, class| mask | class.mask())
+    }
+}
+
+// ===== ErrorCounter =====
+
+/// Counts of received error frames, by the class of error.
+///
+/// An error frame can report more than one class of error at once, so it
+/// is counted once for each of the classes set in it, and the sum of the
+/// counts may be more than the number of frames. The counts can be read
+/// by indexing with the class, like `counter[ErrClass::BusOff]`.
+///
+/// This is returned by [`Socket::count_errors()`](crate::Socket::count_errors),
+/// or it can be fed error frames directly with `add()`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCounter {
+    counts: [u64; ErrClass::ALL.len()],
+    frames: u64,
+}
+
+impl ErrorCounter {
+    /// Creates a counter with all the counts at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts an error frame under each of the classes that it reports.
+    pub fn add(&mut self, frame: &CanErrorFrame) {
+        let bits = frame.error_bits();
+        for (count, class) in self.counts.iter_mut().zip(ErrClass::ALL) {
+            if bits & class.mask() != 0 {
+                *count += 1;
+            }
+        }
+        self.frames += 1;
+    }
+
+    /// Gets the number of error frames that reported the class of error.
+    pub fn count(&self, class: ErrClass) -> u64 {
+        self[class]
+    }
+
+    /// Gets the total number of error frames that were counted.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Determines if no error frames were counted.
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// Gets an iterator over the classes of error that were seen, with
+    /// their counts.
+    pub fn iter(&self) -> impl Iterator<Item = (ErrClass, u64)> + '_ {
+        ErrClass::ALL
+            .into_iter()
+            .zip(self.counts)
+            .filter(|&(_, count)| count != 0)
+    }
+}
+
+impl Index<ErrClass> for ErrorCounter {
+    type Output = u64;
+
+    fn index(&self, class: ErrClass) -> &u64 {
+        &self.counts[class.mask().trailing_zeros() as usize]
+    }
+}
+
 // ===== ControllerProblem =====
 
 /// Error status of the CAN conroller.
@@ -606,13 +729,73 @@ impl fmt::Display for ConstructionError {
     }
 }
 
+// ===== WriteFramesError =====
+
+/// Error from writing a sequence of frames to a socket.
+///
+/// This reports the position of the frame that failed to be sent, which is
+/// also the number of frames that were sent successfully before it.
+#[derive(Error, Debug)]
+#[error("failed to write frame at index {index}: {error}")]
+pub struct WriteFramesError {
+    /// The index of the frame in the sequence that could not be sent.
+    pub index: usize,
+    /// The underlying I/O error.
+    #[source]
+    pub error: io::Error,
+}
+
+impl From<WriteFramesError> for io::Error {
+    fn from(err: WriteFramesError) -> Self {
+        err.error
+    }
+}
+
 /////////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
 mod tests {
-    use crate::Error;
+    use crate::{
+        errors::{ErrClass, ErrorCounter, WriteFramesError},
+        CanErrorFrame, Error,
+    };
     use std::io;
 
+    #[test]
+    fn test_err_class_mask() {
+        assert_eq!(0x0001, ErrClass::TransmitTimeout.mask());
+        assert_eq!(0x0100, ErrClass::Restarted.mask());
+        assert_eq!(0, ErrClass::mask_of(&[]));
+        assert_eq!(
+            0x0060,
+            ErrClass::mask_of(&[ErrClass::NoAck, ErrClass::BusOff, ErrClass::NoAck])
+        );
+    }
+
+    #[test]
+    fn test_error_counter() {
+        let mut counter = ErrorCounter::new();
+        assert!(counter.is_empty());
+
+        counter.add(&CanErrorFrame::new_error(0x0020, &[]).unwrap());
+        counter.add(&CanErrorFrame::new_error(0x0060, &[]).unwrap());
+        counter.add(&CanErrorFrame::new_error(0x0100, &[]).unwrap());
+
+        assert_eq!(3, counter.frames());
+        assert_eq!(2, counter[ErrClass::NoAck]);
+        assert_eq!(1, counter.count(ErrClass::BusOff));
+        assert_eq!(1, counter[ErrClass::Restarted]);
+        assert_eq!(0, counter[ErrClass::TransmitTimeout]);
+        assert_eq!(
+            vec![
+                (ErrClass::NoAck, 2),
+                (ErrClass::BusOff, 1),
+                (ErrClass::Restarted, 1)
+            ],
+            counter.iter().collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn test_errors() {
         const KIND: io::ErrorKind = io::ErrorKind::TimedOut;
@@ -633,4 +816,16 @@ mod tests {
             panic!("Wrong error conversion");
         }
     }
+
+    #[test]
+    fn test_write_frames_error() {
+        let err = WriteFramesError {
+            index: 3,
+            error: io::Error::from(io::ErrorKind::TimedOut),
+        };
+        assert!(err.to_string().contains("index 3"));
+
+        let ioerr = io::Error::from(err);
+        assert_eq!(ioerr.kind(), io::ErrorKind::TimedOut);
+    }
 }
@@ -0,0 +1,124 @@
+// socketcan/src/filters.rs
+//
+// Implements composable sets of CAN filters.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Composable sets of CAN filters.
+
+use crate::{CanFilter, IoResult, SocketOptions};
+
+/// A collection of CAN filters to install on a socket.
+///
+/// This allows filter policies from multiple sources to be composed before
+/// they are installed on a socket with a single call. A frame is accepted
+/// by the socket if it matches any of the filters in the set (unless join
+/// filters is enabled on the socket).
+///
+/// Note that an empty set accepts no frames at all when it is applied.
+#[derive(Debug, Default, Clone, Hash, PartialEq, Eq)]
+pub struct FilterSet(Vec<CanFilter>);
+
+impl FilterSet {
+    /// Creates a new, empty filter set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a filter to the set, if it is not already in it.
+    pub fn push<F: Into<CanFilter>>(&mut self, filter: F) {
+        let filter = filter.into();
+        if !self.0.contains(&filter) {
+            self.0.push(filter);
+        }
+    }
+
+    /// Merges all the filters from another set into this one.
+    ///
+    /// Any filters that are already in this set are skipped.
+    pub fn merge(&mut self, other: &FilterSet) {
+        for filter in &other.0 {
+            self.push(*filter);
+        }
+    }
+
+    /// Gets the number of filters in the set.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Determines if the set contains no filters.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Gets the filters as a slice.
+    pub fn as_slice(&self) -> &[CanFilter] {
+        &self.0
+    }
+
+    /// Installs the filters on the socket, replacing any existing ones.
+    pub fn apply<S: SocketOptions>(&self, sock: &S) -> IoResult<()> {
+        sock.set_filters(&self.0)
+    }
+}
+
+impl From<Vec<CanFilter>> for FilterSet {
+    fn from(filters: Vec<CanFilter>) -> Self {
+        filters.into_iter().collect()
+    }
+}
+
+impl<F: Into<CanFilter>> FromIterator<F> for FilterSet {
+    fn from_iter<I: IntoIterator<Item = F>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<F: Into<CanFilter>> Extend<F> for FilterSet {
+    fn extend<I: IntoIterator<Item = F>>(&mut self, iter: I) {
+        for filter in iter {
+            self.push(filter);
+        }
+    }
+}
+
+impl AsRef<[CanFilter]> for FilterSet {
+    fn as_ref(&self) -> &[CanFilter] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_set() {
+        let mut set1 = FilterSet::new();
+        assert!(set1.is_empty());
+
+        set1.push((0x100, 0x7FF));
+        set1.push(CanFilter::new(0x200, 0x700));
+        set1.push((0x100, 0x7FF));
+        assert_eq!(2, set1.len());
+
+        let set2: FilterSet = vec![(0x200, 0x700), (0x300, 0x7FF)].into_iter().collect();
+        set1.merge(&set2);
+        assert_eq!(
+            &[
+                CanFilter::new(0x100, 0x7FF),
+                CanFilter::new(0x200, 0x700),
+                CanFilter::new(0x300, 0x7FF),
+            ],
+            set1.as_slice()
+        );
+    }
+}
@@ -36,7 +36,9 @@ use embedded_can::{ExtendedId, Frame as EmbeddedFrame, Id, StandardId};
 use itertools::Itertools;
 use libc::{can_frame, canfd_frame, canid_t};
 use std::{
+    cmp::Ordering,
     ffi::c_void,
+    hash::{Hash, Hasher},
     mem::size_of,
     {convert::TryFrom, fmt, matches, mem},
 };
@@ -46,6 +48,13 @@ pub use libc::{
     CAN_MAX_DLEN, CAN_RTR_FLAG, CAN_SFF_MASK,
 };
 
+/// The flag that the kernel (since Linux 6.2) sets on every FD frame that
+/// it sends, to mark it as an FD frame.
+///
+/// Not every version of libc defines it, and it says nothing more about a
+/// frame than its type does, so it's left out when comparing FD frames.
+pub(crate) const CANFD_FDF: u8 = 0x04;
+
 /// An error mask that will cause SocketCAN to report all errors
 pub const ERR_MASK_ALL: u32 = CAN_ERR_MASK;
 
@@ -72,8 +81,21 @@ bitflags! {
     }
 }
 
+/// The kind of CAN identifier used by a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdKind {
+    /// A standard, 11-bit, ID
+    Standard,
+    /// An extended, 29-bit, ID
+    Extended,
+}
+
 /// Gets the canid_t value from an Id
 /// If it's an extended ID, the CAN_EFF_FLAG bit is also set.
+///
+/// The width comes from the type of the ID, not its value, so a small
+/// extended ID, like 0x1, keeps the flag, and stays distinct from the
+/// standard ID with the same value.
 pub fn id_to_canid_t(id: impl Into<Id>) -> canid_t {
     let id = id.into();
     match id {
@@ -102,6 +124,19 @@ pub fn id_from_raw(id: u32) -> Option<Id> {
 
 // ===== can_frame =====
 
+/// Checks that the length fields of a classic C `can_frame` agree.
+///
+/// The length can be 0-8 bytes. The raw DLC in `len8_dlc` is only used
+/// for an 8-byte payload, where CAN allows DLC values of 9-15 to also mean
+/// 8 bytes. Otherwise it must be zero.
+fn can_frame_dlc_consistent(frame: &can_frame) -> bool {
+    match (frame.can_dlc as usize, frame.len8_dlc) {
+        (n, 0) => n <= CAN_MAX_DLEN,
+        (CAN_MAX_DLEN, dlc) => (9..=15).contains(&dlc),
+        _ => false,
+    }
+}
+
 /// Creates a default C `can_frame`.
 /// This initializes the entire structure to zeros.
 #[inline(always)]
@@ -116,6 +151,42 @@ pub fn canfd_frame_default() -> canfd_frame {
     unsafe { mem::zeroed() }
 }
 
+/// Gets the length of an FD data payload, padded up to the next size that
+/// can be represented by an FD data length code.
+///
+/// FD frames can only carry 0-8, 12, 16, 20, 24, 32, 48, or 64 bytes on
+/// the bus, so a payload of any other length is padded out to the next
+/// one of these. A payload longer than the maximum returns the maximum.
+pub fn fd_padded_len(len: usize) -> usize {
+    match len {
+        n if n <= 8 => n,
+        n if n <= 24 => (n + 3) & !3,
+        n if n <= 32 => 32,
+        n if n <= 48 => 48,
+        _ => CANFD_MAX_DLEN,
+    }
+}
+
+// ===== Debug helpers =====
+
+/// Debug formatting for a raw ID word, as fixed-width hex with the flags.
+struct DebugId(canid_t);
+
+impl fmt::Debug for DebugId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:08X}", self.0)
+    }
+}
+
+/// Debug formatting for a data payload, as space-separated hex bytes.
+struct DebugData<'a>(&'a [u8]);
+
+impl fmt::Debug for DebugData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{:02X}]", self.0.iter().format(" "))
+    }
+}
+
 // ===== AsPtr trait =====
 
 /// Trait to get a pointer to an inner type
@@ -209,21 +280,189 @@ pub trait Frame: EmbeddedFrame {
         }
     }
 
+    /// Gets the kind of CAN ID used by the frame, standard or extended.
+    fn id_kind(&self) -> IdKind {
+        match self.is_extended() {
+            true => IdKind::Extended,
+            false => IdKind::Standard,
+        }
+    }
+
+    /// Gets the standard CAN ID of the frame, or `None` if the frame uses
+    /// an extended ID.
+    fn standard_id(&self) -> Option<StandardId> {
+        match self.hal_id() {
+            Id::Standard(id) => Some(id),
+            Id::Extended(_) => None,
+        }
+    }
+
+    /// Gets the extended CAN ID of the frame, or `None` if the frame uses
+    /// a standard ID.
+    fn extended_id(&self) -> Option<ExtendedId> {
+        match self.hal_id() {
+            Id::Extended(id) => Some(id),
+            Id::Standard(_) => None,
+        }
+    }
+
     /// Get the data length
     fn len(&self) -> usize {
         self.dlc()
     }
 
+    /// Determines if the frame has the specified ID word and data.
+    ///
+    /// The `id` is compared against the composite ID word, including the
+    /// EFF/RTR/ERR flags, so an extended ID must have `CAN_EFF_FLAG` set
+    /// to match. This is handy in tests, to check a received frame without
+    /// building an expected one.
+    fn matches(&self, id: canid_t, data: &[u8]) -> bool {
+        self.id_word() == id && self.data() == data
+    }
+
     /// Check if frame is an error message
     fn is_error_frame(&self) -> bool {
         self.id_flags().contains(IdFlags::ERR)
     }
 
+    /// Checks that the data length code of the frame is consistent with
+    /// its data, according to the CAN specifications.
+    ///
+    /// A frame read from the bus should always be consistent, but frames
+    /// built from raw C structs, or by other software, might not be. See
+    /// the individual frame types for the rules that they follow.
+    fn is_dlc_consistent(&self) -> bool {
+        self.data().len() == self.len()
+    }
+
     /// Sets the CAN ID for the frame
     fn set_id(&mut self, id: impl Into<Id>);
 
-    /// Sets the data payload of the frame.
+    /// Sets the data payload of the frame, in place.
+    ///
+    /// This lets a transmit loop that only varies the payload reuse one
+    /// frame, rather than building a new one each time. The ID and flags are
+    /// unchanged, and the DLC follows the new length. It fails with
+    /// `TooMuchData` if the data is longer than the frame can carry, 8 bytes
+    /// for a classic frame, or 64 for an FD frame. An error frame can't be
+    /// given data, and fails with `WrongFrameType`.
     fn set_data(&mut self, data: &[u8]) -> Result<(), ConstructionError>;
+
+    /// Writes the frame in the compact format of the `candump -L` and
+    /// `cansend` utilities, like "123#0102AB", "12345678#R2", or, for FD
+    /// frames, "123##1AABB".
+    ///
+    /// This writes directly to the output, without allocating any
+    /// intermediate strings, so it's suitable for high-rate logging.
+    fn write_candump<W: fmt::Write>(&self, out: &mut W) -> fmt::Result
+    where
+        Self: Sized,
+    {
+        write_candump_id(out, self.id_word())?;
+        if self.is_remote_frame() {
+            out.write_str("#R")?;
+            match self.dlc() {
+                0 => Ok(()),
+                dlc => write!(out, "{:X}", dlc),
+            }
+        } else {
+            out.write_char('#')?;
+            write_candump_data(out, self.data())
+        }
+    }
+
+    /// Gets the worst-case number of bits that the frame takes on the bus,
+    /// for estimating bus load.
+    ///
+    /// This counts every bit from the start-of-frame through the end of
+    /// the interframe space, along with the most stuff bits that the frame
+    /// could need. The result is in nominal bit times, so dividing it by
+    /// the nominal bitrate gives the time that the frame occupies the bus.
+    ///
+    /// The `bitrate_ratio` is the data bitrate divided by the nominal
+    /// bitrate. It only applies to FD frames with the bitrate switch (BRS)
+    /// flag set, for which the data phase is sent at the faster rate. It's
+    /// ignored for classic frames.
+    ///
+    /// For classic frames this is the well-known bound from the CAN
+    /// schedulability analysis, such as 135 bits for a standard frame with
+    /// 8 bytes of data and 160 bits for an extended one.
+    fn bus_bits(&self, _bitrate_ratio: f64) -> f64 {
+        let data_bits = match self.is_remote_frame() {
+            true => 0,
+            false => 8 * self.data().len(),
+        };
+        // SOF, arbitration, control, and CRC fields, which are all subject
+        // to bit stuffing.
+        let header_bits = match self.is_extended() {
+            true => 54,
+            false => 34,
+        };
+        let stuffed = header_bits + data_bits;
+        (stuffed + worst_case_stuff_bits(stuffed) + FRAME_TRAILER_BITS) as f64
+    }
+}
+
+/// The bits at the end of every frame, which are not stuffed: the CRC
+/// delimiter, the ACK slot and delimiter, the end-of-frame, and the
+/// interframe space.
+const FRAME_TRAILER_BITS: usize = 1 + 2 + 7 + 3;
+
+/// Gets the most stuff bits that can be inserted into a run of `n` bits.
+///
+/// A stuff bit follows every five bits of the same value, and, in the
+/// worst case, each stuff bit starts the next run of five.
+fn worst_case_stuff_bits(n: usize) -> usize {
+    n.saturating_sub(1) / 4
+}
+
+/// Writes the ID of a frame in candump format.
+///
+/// Standard IDs are written as three hex digits, and extended IDs and
+/// error classes as eight.
+fn write_candump_id<W: fmt::Write>(out: &mut W, id_word: canid_t) -> fmt::Result {
+    if id_word & CAN_ERR_FLAG != 0 {
+        write!(out, "{:08X}", id_word & (CAN_ERR_FLAG | CAN_ERR_MASK))
+    } else if id_word & CAN_EFF_FLAG != 0 {
+        write!(out, "{:08X}", id_word & CAN_EFF_MASK)
+    } else {
+        write!(out, "{:03X}", id_word & CAN_SFF_MASK)
+    }
+}
+
+/// Writes a data payload in candump format, as hex bytes with no spaces.
+fn write_candump_data<W: fmt::Write>(out: &mut W, data: &[u8]) -> fmt::Result {
+    data.iter().try_for_each(|b| write!(out, "{:02X}", b))
+}
+
+/// Gets the raw DLC of 9-15 that a classic frame with 8 bytes of data
+/// might carry, which candump writes after the data or remote length.
+fn candump_len8_dlc(frame: &can_frame) -> Option<u8> {
+    Some(frame.len8_dlc)
+        .filter(|dlc| frame.can_dlc as usize == CAN_MAX_DLEN && (9..=15).contains(dlc))
+}
+
+/// Formats a frame as a line of the log output of `candump -L`, like
+/// "(1469439874.299591) can1 123#0102AB".
+///
+/// The time is in microseconds since the epoch, as in a dump record, and
+/// the line is returned without a line ending. The output is byte-for-byte
+/// what candump writes, with the seconds zero-padded to ten digits, the
+/// hex digits in upper case, and any raw DLC or FD flags as they were
+/// received. When listening on several interfaces, candump pads the names
+/// to the longest one given on its command line, but this writes the name
+/// as-is.
+pub fn candump_format<F: Frame>(t_us: u64, iface: &str, frame: &F) -> String {
+    let mut out = format!(
+        "({:010}.{:06}) {} ",
+        t_us / 1_000_000,
+        t_us % 1_000_000,
+        iface
+    );
+    // Writing to a String can't fail
+    let _ = frame.write_candump(&mut out);
+    out
 }
 
 // ===== CanAnyFrame =====
@@ -251,7 +490,7 @@ impl From<canfd_frame> for CanRawFrame {
 }
 
 /// Any frame type.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CanAnyFrame {
     /// A classic CAN 2.0 frame, with up to 8-bytes of data
     Normal(CanDataFrame),
@@ -263,6 +502,43 @@ pub enum CanAnyFrame {
     Fd(CanFdFrame),
 }
 
+impl CanAnyFrame {
+    /// Parses a frame of either type from its raw bytes.
+    ///
+    /// This accepts the bytes of a C `can_frame` (16 bytes) or a
+    /// `canfd_frame` (72 bytes), as are read from and written to a socket,
+    /// and is the inverse of `AsPtr::as_bytes()`. It returns `None` if the
+    /// buffer is any other size, or if the frame in it is invalid. A
+    /// frame must have a consistent data length code, as reported by
+    /// `Frame::is_dlc_consistent()`, which for an FD frame means one of the
+    /// lengths that an FD DLC can encode, up to 64 bytes. An FD frame also
+    /// can't have the RTR flag set, as there are no FD remote frames.
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() == size_of::<canfd_frame>() {
+            let mut frame = canfd_frame_default();
+            crate::as_bytes_mut(&mut frame).copy_from_slice(buf);
+            let frame = CanFdFrame(frame);
+            match frame.is_dlc_consistent() && frame.id_word() & CAN_RTR_FLAG == 0 {
+                true => Some(frame.into()),
+                false => None,
+            }
+        } else {
+            CanFrame::from_bytes(buf).map(Self::from)
+        }
+    }
+}
+
+impl fmt::Debug for CanAnyFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Normal(frame) => fmt::Debug::fmt(frame, f),
+            Self::Remote(frame) => fmt::Debug::fmt(frame, f),
+            Self::Error(frame) => fmt::Debug::fmt(frame, f),
+            Self::Fd(frame) => fmt::Debug::fmt(frame, f),
+        }
+    }
+}
+
 impl fmt::UpperHex for CanAnyFrame {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -274,6 +550,161 @@ impl fmt::UpperHex for CanAnyFrame {
     }
 }
 
+impl EmbeddedFrame for CanAnyFrame {
+    /// Create a new data frame.
+    ///
+    /// This creates a classic CAN 2.0 frame if the data fits, i.e. it's
+    /// 8 or fewer bytes, otherwise it creates an FD frame.
+    fn new(id: impl Into<Id>, data: &[u8]) -> Option<Self> {
+        if data.len() <= CAN_MAX_DLEN {
+            CanDataFrame::new(id, data).map(CanAnyFrame::Normal)
+        } else {
+            CanFdFrame::new(id, data).map(CanAnyFrame::Fd)
+        }
+    }
+
+    /// Create a new remote transmission request frame.
+    fn new_remote(id: impl Into<Id>, dlc: usize) -> Option<Self> {
+        CanRemoteFrame::new_remote(id, dlc).map(CanAnyFrame::Remote)
+    }
+
+    /// Check if frame uses 29-bit extended ID format.
+    fn is_extended(&self) -> bool {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.is_extended(),
+            Remote(frame) => frame.is_extended(),
+            Error(frame) => frame.is_extended(),
+            Fd(frame) => frame.is_extended(),
+        }
+    }
+
+    /// Check if frame is a remote transmission request.
+    fn is_remote_frame(&self) -> bool {
+        matches!(self, CanAnyFrame::Remote(_))
+    }
+
+    /// Check if frame is a data frame.
+    fn is_data_frame(&self) -> bool {
+        matches!(self, CanAnyFrame::Normal(_) | CanAnyFrame::Fd(_))
+    }
+
+    /// Return the frame identifier.
+    fn id(&self) -> Id {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.id(),
+            Remote(frame) => frame.id(),
+            Error(frame) => frame.id(),
+            Fd(frame) => frame.id(),
+        }
+    }
+
+    /// Data length
+    fn dlc(&self) -> usize {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.dlc(),
+            Remote(frame) => frame.dlc(),
+            Error(frame) => frame.dlc(),
+            Fd(frame) => frame.dlc(),
+        }
+    }
+
+    /// A slice into the actual data.
+    fn data(&self) -> &[u8] {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.data(),
+            Remote(frame) => frame.data(),
+            Error(frame) => frame.data(),
+            Fd(frame) => frame.data(),
+        }
+    }
+}
+
+impl Frame for CanAnyFrame {
+    /// Get the composite SocketCAN ID word, with EFF/RTR/ERR flags
+    fn id_word(&self) -> canid_t {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.id_word(),
+            Remote(frame) => frame.id_word(),
+            Error(frame) => frame.id_word(),
+            Fd(frame) => frame.id_word(),
+        }
+    }
+
+    /// Get the data length
+    fn len(&self) -> usize {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.len(),
+            Remote(frame) => frame.len(),
+            Error(frame) => frame.len(),
+            Fd(frame) => frame.len(),
+        }
+    }
+
+    /// Sets the CAN ID for the frame
+    fn set_id(&mut self, id: impl Into<Id>) {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.set_id(id),
+            Remote(frame) => frame.set_id(id),
+            Error(frame) => frame.set_id(id),
+            Fd(frame) => frame.set_id(id),
+        }
+    }
+
+    /// Sets the data payload of the frame.
+    ///
+    /// Note that this will not change the type of the frame, so a classic
+    /// frame can still only take up to 8 bytes of data.
+    fn set_data(&mut self, data: &[u8]) -> Result<(), ConstructionError> {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.set_data(data),
+            Remote(frame) => frame.set_data(data),
+            Error(frame) => frame.set_data(data),
+            Fd(frame) => frame.set_data(data),
+        }
+    }
+
+    /// Checks the data length code of the frame.
+    fn is_dlc_consistent(&self) -> bool {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.is_dlc_consistent(),
+            Remote(frame) => frame.is_dlc_consistent(),
+            Error(frame) => frame.is_dlc_consistent(),
+            Fd(frame) => frame.is_dlc_consistent(),
+        }
+    }
+
+    /// Writes the frame in candump format.
+    fn write_candump<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.write_candump(out),
+            Remote(frame) => frame.write_candump(out),
+            Error(frame) => frame.write_candump(out),
+            Fd(frame) => frame.write_candump(out),
+        }
+    }
+
+    /// Gets the worst-case number of bits that the frame takes on the bus.
+    fn bus_bits(&self, bitrate_ratio: f64) -> f64 {
+        use CanAnyFrame::*;
+        match self {
+            Normal(frame) => frame.bus_bits(bitrate_ratio),
+            Remote(frame) => frame.bus_bits(bitrate_ratio),
+            Error(frame) => frame.bus_bits(bitrate_ratio),
+            Fd(frame) => frame.bus_bits(bitrate_ratio),
+        }
+    }
+}
+
 impl From<CanFrame> for CanAnyFrame {
     fn from(frame: CanFrame) -> Self {
         use CanFrame::*;
@@ -349,7 +780,7 @@ impl AsPtr for CanAnyFrame {
 // ===== CanFrame =====
 
 /// The classic CAN 2.0 frame with up to 8-bytes of data.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CanFrame {
     /// A data frame
     Data(CanDataFrame),
@@ -359,6 +790,63 @@ pub enum CanFrame {
     Error(CanErrorFrame),
 }
 
+impl CanFrame {
+    /// Creates a new CAN 2.0 data frame from a standard or extended ID.
+    ///
+    /// This takes the unified `Id` type, so an ID of either width can be
+    /// passed through without branching on it. Unlike `EmbeddedFrame::new`,
+    /// this is an inherent function, so it doesn't need the trait in scope,
+    /// and it reports why the frame could not be created.
+    ///
+    /// ```
+    /// use socketcan::{CanFrame, EmbeddedFrame, ExtendedId, Id};
+    ///
+    /// let id: Id = ExtendedId::new(0x1234).unwrap().into();
+    /// let frame = CanFrame::from_id(id, &[1, 2, 3]).unwrap();
+    /// assert!(frame.is_extended());
+    /// ```
+    pub fn from_id(id: Id, data: &[u8]) -> Result<Self, ConstructionError> {
+        CanDataFrame::init(id_to_canid_t(id), data).map(CanFrame::Data)
+    }
+
+    /// Parses a classic frame from its raw bytes.
+    ///
+    /// This accepts the 16 bytes of a C `can_frame`, as are read from and
+    /// written to a socket, and is the inverse of `AsPtr::as_bytes()`. It
+    /// returns `None` if the buffer is any other size, or if the data
+    /// length code of the frame is not consistent, as reported by
+    /// `Frame::is_dlc_consistent()`.
+    pub fn from_bytes(buf: &[u8]) -> Option<Self> {
+        if buf.len() != size_of::<can_frame>() {
+            return None;
+        }
+        let mut frame = can_frame_default();
+        crate::as_bytes_mut(&mut frame).copy_from_slice(buf);
+        Some(CanFrame::from(frame)).filter(|frame| frame.is_dlc_consistent())
+    }
+
+    /// Parses a classic frame from the compact text format used by
+    /// `cansend` and `candump -L`, like "123#DEADBEEF" or "123#R".
+    ///
+    /// See [`dump::parse_frame()`](crate::dump::parse_frame) for the
+    /// details of the format. An FD frame, like "123##1DEADBEEF", is
+    /// rejected with `WrongFrameType`.
+    ///
+    /// ```
+    /// use socketcan::{CanFrame, EmbeddedFrame, Frame};
+    ///
+    /// let frame = CanFrame::from_hex("123#DEADBEEF").unwrap();
+    /// assert_eq!(frame.raw_id(), 0x123);
+    /// assert_eq!(frame.data(), &[0xDE, 0xAD, 0xBE, 0xEF]);
+    /// assert!(CanFrame::from_hex("123#DEADBEE").is_err());
+    /// ```
+    #[cfg(feature = "dump")]
+    pub fn from_hex(s: &str) -> Result<Self, crate::dump::ParseError> {
+        let frame = crate::dump::parse_frame(s.as_bytes())?;
+        Ok(CanFrame::try_from(frame)?)
+    }
+}
+
 impl AsPtr for CanFrame {
     type Inner = can_frame;
 
@@ -472,6 +960,26 @@ impl Frame for CanFrame {
             Error(frame) => frame.set_data(data),
         }
     }
+
+    /// Checks the data length code of the frame.
+    fn is_dlc_consistent(&self) -> bool {
+        use CanFrame::*;
+        match self {
+            Data(frame) => frame.is_dlc_consistent(),
+            Remote(frame) => frame.is_dlc_consistent(),
+            Error(frame) => frame.is_dlc_consistent(),
+        }
+    }
+
+    /// Writes the frame in candump format.
+    fn write_candump<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        use CanFrame::*;
+        match self {
+            Data(frame) => frame.write_candump(out),
+            Remote(frame) => frame.write_candump(out),
+            Error(frame) => frame.write_candump(out),
+        }
+    }
 }
 
 impl Default for CanFrame {
@@ -482,6 +990,47 @@ impl Default for CanFrame {
     }
 }
 
+impl fmt::Debug for CanFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        use CanFrame::*;
+        match self {
+            Data(frame) => fmt::Debug::fmt(frame, f),
+            Remote(frame) => fmt::Debug::fmt(frame, f),
+            Error(frame) => fmt::Debug::fmt(frame, f),
+        }
+    }
+}
+
+/// Frames are ordered by ID first, so that sorting a capture groups the
+/// frames by ID.
+///
+/// The primary key is the numeric value of the ID. Frames with the same
+/// value are then ordered by the whole ID word, including the flags, so a
+/// standard data frame comes first, then an error frame (with the ERR
+/// flag, 0x2000_0000), then a standard remote frame (RTR, 0x4000_0000),
+/// and last the extended frames (EFF, 0x8000_0000). Frames with the same
+/// ID word are always of the same kind, and are then ordered by their
+/// data, or, for remote frames, by their DLC. This is consistent with
+/// equality, so frames can be kept in a `BTreeSet` or used as `BTreeMap`
+/// keys.
+impl Ord for CanFrame {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.raw_id()
+            .cmp(&other.raw_id())
+            .then_with(|| self.id_word().cmp(&other.id_word()))
+            .then_with(|| match (self, other) {
+                (CanFrame::Remote(a), CanFrame::Remote(b)) => a.dlc().cmp(&b.dlc()),
+                _ => self.data().cmp(other.data()),
+            })
+    }
+}
+
+impl PartialOrd for CanFrame {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl fmt::UpperHex for CanFrame {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         use CanFrame::*;
@@ -543,12 +1092,31 @@ impl TryFrom<CanFdFrame> for CanFrame {
 
     /// Try to convert a CAN FD frame into a classic CAN 2.0 frame.
     ///
-    /// This should work if it's a data frame with 8 or fewer data bytes.
+    /// This should work if it's a data frame with 8 or fewer data bytes,
+    /// and none of the FD-only flags (BRS, ESI) are set.
     fn try_from(frame: CanFdFrame) -> Result<Self, <Self as TryFrom<CanFdFrame>>::Error> {
         CanDataFrame::try_from(frame).map(CanFrame::Data)
     }
 }
 
+impl TryFrom<CanAnyFrame> for CanFrame {
+    type Error = ConstructionError;
+
+    /// Try to get the classic CAN 2.0 frame out of any type of frame.
+    ///
+    /// This fails with `WrongFrameType` if it's an FD frame, even if the
+    /// FD frame could be converted to a classic one.
+    fn try_from(frame: CanAnyFrame) -> Result<Self, <Self as TryFrom<CanAnyFrame>>::Error> {
+        use CanAnyFrame::*;
+        match frame {
+            Normal(frame) => Ok(CanFrame::Data(frame)),
+            Remote(frame) => Ok(CanFrame::Remote(frame)),
+            Error(frame) => Ok(CanFrame::Error(frame)),
+            Fd(_) => Err(ConstructionError::WrongFrameType),
+        }
+    }
+}
+
 // ===== CanDataFrame =====
 
 /// The classic CAN 2.0 frame with up to 8-bytes of data.
@@ -640,16 +1208,39 @@ impl Frame for CanDataFrame {
     }
 
     /// Sets the data payload of the frame.
+    ///
+    /// Any bytes following the data are reset to zero, and any raw DLC of
+    /// 9-15 is cleared, so the frame stays consistent.
     fn set_data(&mut self, data: &[u8]) -> Result<(), ConstructionError> {
         match data.len() {
             n if n <= CAN_MAX_DLEN => {
                 self.0.can_dlc = n as u8;
+                self.0.len8_dlc = 0;
                 self.0.data[..n].copy_from_slice(data);
+                self.0.data[n..].fill(0);
                 Ok(())
             }
             _ => Err(ConstructionError::TooMuchData),
         }
     }
+
+    /// Checks that the data length is 0-8 bytes, and that a raw DLC of
+    /// 9-15, if any, is only used with 8 bytes of data.
+    fn is_dlc_consistent(&self) -> bool {
+        can_frame_dlc_consistent(&self.0)
+    }
+
+    /// Writes the frame in candump format, followed by an underscore and
+    /// the raw DLC, if it has one, like "123#0102030405060708_C".
+    fn write_candump<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        write_candump_id(out, self.id_word())?;
+        out.write_char('#')?;
+        write_candump_data(out, self.data())?;
+        match candump_len8_dlc(&self.0) {
+            Some(dlc) => write!(out, "_{:X}", dlc),
+            None => Ok(()),
+        }
+    }
 }
 
 impl Default for CanDataFrame {
@@ -661,9 +1252,28 @@ impl Default for CanDataFrame {
 
 impl fmt::Debug for CanDataFrame {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "CanDataFrame {{ ")?;
-        fmt::UpperHex::fmt(self, f)?;
-        write!(f, " }}")
+        f.debug_struct("CanDataFrame")
+            .field("id", &DebugId(self.id_word()))
+            .field("dlc", &self.dlc())
+            .field("data", &DebugData(self.data()))
+            .finish()
+    }
+}
+
+/// Data frames are equal if they have the same ID word and data. Any
+/// unused bytes past the length of the data are ignored.
+impl PartialEq for CanDataFrame {
+    fn eq(&self, other: &Self) -> bool {
+        self.id_word() == other.id_word() && self.data() == other.data()
+    }
+}
+
+impl Eq for CanDataFrame {}
+
+impl Hash for CanDataFrame {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id_word().hash(state);
+        self.data().hash(state);
     }
 }
 
@@ -694,10 +1304,18 @@ impl TryFrom<can_frame> for CanDataFrame {
 impl TryFrom<CanFdFrame> for CanDataFrame {
     type Error = ConstructionError;
 
+    /// Try to convert a CAN FD frame into a classic CAN 2.0 data frame.
+    ///
+    /// This will fail if the FD frame has more than 8 data bytes, or if
+    /// any of the FD-only flags (BRS, ESI) are set, since those can not
+    /// be represented in a classic frame.
     fn try_from(frame: CanFdFrame) -> Result<Self, Self::Error> {
         if frame.len() > CAN_MAX_DLEN {
             return Err(ConstructionError::TooMuchData);
         }
+        if !frame.flags().is_empty() {
+            return Err(ConstructionError::WrongFrameType);
+        }
 
         CanDataFrame::init(frame.id_word(), &frame.data()[..(frame.0.len as usize)])
     }
@@ -726,6 +1344,7 @@ impl CanRemoteFrame {
     pub fn set_dlc(&mut self, dlc: usize) -> Result<(), ConstructionError> {
         if dlc <= CAN_MAX_DLEN {
             self.0.can_dlc = dlc as u8;
+            self.0.len8_dlc = 0;
             Ok(())
         } else {
             Err(ConstructionError::TooMuchData)
@@ -815,6 +1434,27 @@ impl Frame for CanRemoteFrame {
     fn set_data(&mut self, data: &[u8]) -> Result<(), ConstructionError> {
         self.set_dlc(data.len())
     }
+
+    /// Checks that the requested data length is 0-8 bytes, and that a raw
+    /// DLC of 9-15, if any, is only used with a length of 8.
+    fn is_dlc_consistent(&self) -> bool {
+        can_frame_dlc_consistent(&self.0)
+    }
+
+    /// Writes the frame in candump format, with the requested length, if
+    /// any, followed by the raw DLC, if it has one, like "123#R8C".
+    fn write_candump<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        write_candump_id(out, self.id_word())?;
+        out.write_str("#R")?;
+        match self.dlc() {
+            0 => return Ok(()),
+            dlc => write!(out, "{:X}", dlc)?,
+        }
+        match candump_len8_dlc(&self.0) {
+            Some(dlc) => write!(out, "{:X}", dlc),
+            None => Ok(()),
+        }
+    }
 }
 
 impl Default for CanRemoteFrame {
@@ -828,9 +1468,27 @@ impl Default for CanRemoteFrame {
 
 impl fmt::Debug for CanRemoteFrame {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "CanRemoteFrame {{ ")?;
-        fmt::UpperHex::fmt(self, f)?;
-        write!(f, " }}")
+        f.debug_struct("CanRemoteFrame")
+            .field("id", &DebugId(self.id_word()))
+            .field("dlc", &self.dlc())
+            .finish()
+    }
+}
+
+/// Remote frames are equal if they have the same ID word and DLC. The
+/// contents of the data buffer are ignored.
+impl PartialEq for CanRemoteFrame {
+    fn eq(&self, other: &Self) -> bool {
+        self.id_word() == other.id_word() && self.dlc() == other.dlc()
+    }
+}
+
+impl Eq for CanRemoteFrame {}
+
+impl Hash for CanRemoteFrame {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id_word().hash(state);
+        self.dlc().hash(state);
     }
 }
 
@@ -998,13 +1656,36 @@ impl Frame for CanErrorFrame {
     fn set_data(&mut self, _data: &[u8]) -> Result<(), ConstructionError> {
         Err(ConstructionError::WrongFrameType)
     }
+
+    /// Checks that the frame has the full 8 bytes of error data.
+    fn is_dlc_consistent(&self) -> bool {
+        self.0.can_dlc as usize == CAN_MAX_DLEN && self.0.len8_dlc == 0
+    }
 }
 
 impl fmt::Debug for CanErrorFrame {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "CanErrorFrame {{ ")?;
-        fmt::UpperHex::fmt(self, f)?;
-        write!(f, " }}")
+        f.debug_struct("CanErrorFrame")
+            .field("id", &DebugId(self.id_word()))
+            .field("dlc", &self.dlc())
+            .field("data", &DebugData(self.data()))
+            .finish()
+    }
+}
+
+/// Error frames are equal if they have the same ID word and data.
+impl PartialEq for CanErrorFrame {
+    fn eq(&self, other: &Self) -> bool {
+        self.id_word() == other.id_word() && self.data() == other.data()
+    }
+}
+
+impl Eq for CanErrorFrame {}
+
+impl Hash for CanErrorFrame {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id_word().hash(state);
+        self.data().hash(state);
     }
 }
 
@@ -1079,6 +1760,58 @@ impl AsRef<can_frame> for CanErrorFrame {
 pub struct CanFdFrame(canfd_frame);
 
 impl CanFdFrame {
+    /// Creates an FD frame from a classic CAN 2.0 frame.
+    ///
+    /// This is always valid, since any classic frame fits into an FD frame.
+    /// The ID and the EFF and ERR flags are preserved. FD frames don't
+    /// support remote requests, so a remote frame gets converted into an
+    /// FD frame with no data and the RTR flag cleared.
+    pub fn from_can(frame: CanFrame) -> Self {
+        match frame {
+            CanFrame::Data(frame) => frame.into(),
+            CanFrame::Remote(frame) => {
+                let mut fdframe = canfd_frame_default();
+                fdframe.can_id = frame.id_word() & !CAN_RTR_FLAG;
+                Self(fdframe)
+            }
+            CanFrame::Error(frame) => {
+                let n = frame.dlc();
+
+                let mut fdframe = canfd_frame_default();
+                fdframe.can_id = frame.id_word();
+                fdframe.len = n as u8;
+                fdframe.data[..n].copy_from_slice(&frame.data()[..n]);
+                Self(fdframe)
+            }
+        }
+    }
+
+    /// Creates a builder for an FD frame with the specified ID.
+    ///
+    /// This gives explicit control over the FD flags of the frame.
+    ///
+    /// ```
+    /// use socketcan::{CanFdFrame, StandardId};
+    ///
+    /// let id = StandardId::new(0x123).unwrap();
+    /// let frame = CanFdFrame::builder(id)
+    ///     .data(&[0u8; 12])
+    ///     .brs(true)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert!(frame.is_brs());
+    /// assert!(!frame.is_esi());
+    /// ```
+    pub fn builder<'a>(id: impl Into<Id>) -> CanFdFrameBuilder<'a> {
+        CanFdFrameBuilder {
+            id: id.into(),
+            data: &[],
+            flags: FdFlags::empty(),
+            pad: 0,
+        }
+    }
+
     /// Create a new FD frame with FD flags
     pub fn with_flags(id: impl Into<Id>, data: &[u8], flags: FdFlags) -> Option<Self> {
         let can_id = id_to_canid_t(id);
@@ -1104,6 +1837,19 @@ impl CanFdFrame {
         }
     }
 
+    /// Sets the value of the bytes used to pad the data out to the length
+    /// given by the data length code.
+    ///
+    /// The padding is not part of the data payload, and is not counted in
+    /// [`len()`](Frame::len), but the bytes are kept in the frame buffer
+    /// following the data. Whether they are sent on the bus is up to the
+    /// driver for the interface, though most copy them from the buffer.
+    /// The padding is reset to zero whenever the data is changed.
+    pub fn set_pad_byte(&mut self, pad: u8) {
+        let n = self.0.len as usize;
+        self.0.data[n..fd_padded_len(n)].fill(pad);
+    }
+
     /// Gets the flags for the FD frame.
     ///
     /// These are the bits from the separate FD frame flags, not the flags
@@ -1112,6 +1858,23 @@ impl CanFdFrame {
         FdFlags::from_bits_truncate(self.0.flags)
     }
 
+    /// Gets the whole raw flags byte of the FD frame.
+    ///
+    /// Unlike `flags()`, this includes any bits that aren't covered by
+    /// `FdFlags`, such as those used by newer or experimental kernel
+    /// features.
+    pub fn raw_flags(&self) -> u8 {
+        self.0.flags
+    }
+
+    /// Sets the whole raw flags byte of the FD frame.
+    ///
+    /// This replaces all of the flags, including BRS and ESI, with the
+    /// given bits, which are passed to the kernel as-is.
+    pub fn set_raw_flags(&mut self, flags: u8) {
+        self.0.flags = flags;
+    }
+
     /// Whether the frame uses a bit rate switch (second bit rate for
     /// payload data).
     pub fn is_brs(&self) -> bool {
@@ -1140,6 +1903,82 @@ impl CanFdFrame {
             self.0.flags &= !CANFD_ESI as u8;
         }
     }
+
+    /// Converts the frame to a classic CAN 2.0 data frame, truncating the
+    /// data to the first 8 bytes.
+    ///
+    /// Unlike the checked `TryFrom` conversion, this never fails, but it
+    /// is lossy: any data after the first 8 bytes is dropped, as are the
+    /// FD flags (BRS, ESI). The ID, including whether it's extended, is
+    /// kept. This is meant for things like logging FD traffic to a sink
+    /// that only handles classic frames.
+    pub fn truncate_to_classic(&self) -> CanFrame {
+        let n = self.len().min(CAN_MAX_DLEN);
+        let mut frame = can_frame_default();
+        frame.can_id = self.id_word();
+        frame.can_dlc = n as u8;
+        frame.data[..n].copy_from_slice(&self.data()[..n]);
+        CanFrame::Data(CanDataFrame(frame))
+    }
+}
+
+/// A builder for an FD frame.
+///
+/// This is created with [`CanFdFrame::builder`].
+#[derive(Clone, Copy, Debug)]
+pub struct CanFdFrameBuilder<'a> {
+    id: Id,
+    data: &'a [u8],
+    flags: FdFlags,
+    pad: u8,
+}
+
+impl<'a> CanFdFrameBuilder<'a> {
+    /// Sets the data payload of the frame.
+    ///
+    /// This can be up to 64 bytes. The length is checked when the frame
+    /// is built.
+    pub fn data(mut self, data: &'a [u8]) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Sets whether the frame uses a bit rate switch (second bit rate for
+    /// payload data).
+    pub fn brs(mut self, on: bool) -> Self {
+        self.flags.set(FdFlags::BRS, on);
+        self
+    }
+
+    /// Sets the error state indicator of the transmitting node.
+    pub fn esi(mut self, on: bool) -> Self {
+        self.flags.set(FdFlags::ESI, on);
+        self
+    }
+
+    /// Sets all of the FD flags for the frame at once.
+    pub fn flags(mut self, flags: FdFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Sets the value of the bytes used to pad the data out to the next
+    /// valid FD length.
+    ///
+    /// This defaults to zero. See [`CanFdFrame::set_pad_byte`].
+    pub fn with_pad_byte(mut self, pad: u8) -> Self {
+        self.pad = pad;
+        self
+    }
+
+    /// Builds the frame.
+    ///
+    /// This fails if the data is too long for an FD frame.
+    pub fn build(self) -> Result<CanFdFrame, ConstructionError> {
+        let mut frame = CanFdFrame::init(id_to_canid_t(self.id), self.data, self.flags)?;
+        frame.set_pad_byte(self.pad);
+        Ok(frame)
+    }
 }
 
 impl AsPtr for CanFdFrame {
@@ -1185,14 +2024,21 @@ impl EmbeddedFrame for CanFdFrame {
         self.hal_id()
     }
 
-    /// Data length code
+    /// Data length code, as the number of bytes it represents.
+    ///
+    /// An FD frame can only send certain lengths of data on the bus, so
+    /// this is the length of the data padded up to the next valid size,
+    /// as given by [`fd_padded_len`]. The actual length of the payload is
+    /// returned by [`len()`](Frame::len). So a frame with 20 bytes of data
+    /// has a `dlc()` and `len()` of 20, but one with 21 bytes has a `dlc()`
+    /// of 24 and a `len()` of 21.
     fn dlc(&self) -> usize {
-        self.0.len as usize
+        fd_padded_len(self.0.len as usize)
     }
 
     /// A slice into the actual data.
     ///
-    /// For normal CAN frames the slice will always be <= 8 bytes in length.
+    /// This does not include any padding bytes.
     fn data(&self) -> &[u8] {
         &self.0.data[..(self.0.len as usize)]
     }
@@ -1209,17 +2055,79 @@ impl Frame for CanFdFrame {
         self.0.can_id = id_to_canid_t(id);
     }
 
+    /// Get the length of the data payload, not including any padding.
+    fn len(&self) -> usize {
+        self.0.len as usize
+    }
+
     /// Sets the data payload of the frame.
+    ///
+    /// Any padding following the data is reset to zero.
     fn set_data(&mut self, data: &[u8]) -> Result<(), ConstructionError> {
         match data.len() {
             n if n <= CANFD_MAX_DLEN => {
                 self.0.len = n as u8;
                 self.0.data[..n].copy_from_slice(data);
+                self.0.data[n..].fill(0);
                 Ok(())
             }
             _ => Err(ConstructionError::TooMuchData),
         }
     }
+
+    /// Checks that the data length is one that can be represented exactly
+    /// by an FD data length code, with no padding.
+    ///
+    /// The kernel accepts other lengths, up to 64 bytes, and pads them out
+    /// to the next valid size when the frame is sent, so a frame that is
+    /// not consistent can still be sent. See [`fd_padded_len`].
+    fn is_dlc_consistent(&self) -> bool {
+        let n = self.0.len as usize;
+        n <= CANFD_MAX_DLEN && fd_padded_len(n) == n
+    }
+
+    /// Writes the frame in candump format, with the low four bits of the
+    /// raw FD flags as a single hex digit after a double separator.
+    fn write_candump<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        write_candump_id(out, self.id_word())?;
+        write!(out, "##{:X}", self.raw_flags() & 0x0F)?;
+        write_candump_data(out, self.data())
+    }
+
+    /// Gets the worst-case number of bits that the FD frame takes on the
+    /// bus, in nominal bit times.
+    ///
+    /// The arbitration phase, up to the BRS bit, and the end of the frame,
+    /// from the CRC delimiter on, are always sent at the nominal bitrate.
+    /// If the BRS flag is set, the bits in between are sent at the data
+    /// bitrate, and are scaled down by the `bitrate_ratio`. The data is
+    /// counted at its padded length, as it is sent on the bus. The CRC
+    /// field has fixed stuff bits, rather than the dynamic stuffing of the
+    /// rest of the frame.
+    fn bus_bits(&self, bitrate_ratio: f64) -> f64 {
+        // SOF through BRS
+        let arb_bits = match self.is_extended() {
+            true => 36,
+            false => 17,
+        };
+        // ESI, DLC, and data
+        let ctrl_bits = 1 + 4 + 8 * self.dlc();
+
+        let stuff_bits = worst_case_stuff_bits(arb_bits + ctrl_bits);
+        let arb_stuff_bits = worst_case_stuff_bits(arb_bits);
+
+        // Stuff bit count and CRC, with a fixed stuff bit every four bits
+        let crc_bits = 4 + if self.dlc() <= 16 { 17 } else { 21 };
+        let crc_bits = crc_bits + (crc_bits + 3) / 4;
+
+        let nominal = (arb_bits + arb_stuff_bits + FRAME_TRAILER_BITS) as f64;
+        let data = (ctrl_bits + stuff_bits - arb_stuff_bits + crc_bits) as f64;
+
+        match self.is_brs() {
+            true => nominal + data / bitrate_ratio,
+            false => nominal + data,
+        }
+    }
 }
 
 impl Default for CanFdFrame {
@@ -1231,9 +2139,33 @@ impl Default for CanFdFrame {
 
 impl fmt::Debug for CanFdFrame {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "CanFdFrame {{ ")?;
-        fmt::UpperHex::fmt(self, f)?;
-        write!(f, " }}")
+        f.debug_struct("CanFdFrame")
+            .field("id", &DebugId(self.id_word()))
+            .field("flags", &self.flags())
+            .field("dlc", &self.dlc())
+            .field("data", &DebugData(self.data()))
+            .finish()
+    }
+}
+
+/// FD frames are equal if they have the same ID word, raw flags byte, and
+/// data. The FDF flag, which the kernel adds to sent frames, and any
+/// unused or padding bytes past the length of the data are ignored.
+impl PartialEq for CanFdFrame {
+    fn eq(&self, other: &Self) -> bool {
+        self.id_word() == other.id_word()
+            && self.raw_flags() & !CANFD_FDF == other.raw_flags() & !CANFD_FDF
+            && self.data() == other.data()
+    }
+}
+
+impl Eq for CanFdFrame {}
+
+impl Hash for CanFdFrame {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id_word().hash(state);
+        (self.raw_flags() & !CANFD_FDF).hash(state);
+        self.data().hash(state);
     }
 }
 
@@ -1258,12 +2190,33 @@ impl From<CanDataFrame> for CanFdFrame {
     }
 }
 
+impl From<CanFrame> for CanFdFrame {
+    fn from(frame: CanFrame) -> Self {
+        Self::from_can(frame)
+    }
+}
+
 impl From<canfd_frame> for CanFdFrame {
     fn from(frame: canfd_frame) -> Self {
         Self(frame)
     }
 }
 
+impl TryFrom<CanAnyFrame> for CanFdFrame {
+    type Error = ConstructionError;
+
+    /// Try to get the FD frame out of any type of frame.
+    ///
+    /// This fails with `WrongFrameType` if it's a classic frame. Use
+    /// `From<CanFrame>` to convert a classic data frame to FD.
+    fn try_from(frame: CanAnyFrame) -> Result<Self, Self::Error> {
+        match frame {
+            CanAnyFrame::Fd(frame) => Ok(frame),
+            _ => Err(ConstructionError::WrongFrameType),
+        }
+    }
+}
+
 impl AsRef<canfd_frame> for CanFdFrame {
     fn as_ref(&self) -> &canfd_frame {
         &self.0
@@ -1309,6 +2262,43 @@ mod tests {
         assert_eq!(0, flags.bits() & CAN_EFF_FLAG);
     }
 
+    #[test]
+    fn test_small_extended_id() {
+        let ext_id = ExtendedId::new(0x1).unwrap();
+        let std_id = StandardId::new(0x1).unwrap();
+
+        assert_eq!(CAN_EFF_FLAG | 0x1, id_to_canid_t(ext_id));
+        assert_eq!(0x1, id_to_canid_t(std_id));
+
+        let frame = CanFrame::new(ext_id, &[1, 2]).unwrap();
+        assert!(frame.is_extended());
+        assert_eq!(CAN_EFF_FLAG, frame.id_word() & CAN_EFF_FLAG);
+        assert_eq!(0x1, frame.raw_id());
+        assert_eq!(Id::Extended(ext_id), frame.id());
+
+        let std_frame = CanFrame::new(std_id, &[1, 2]).unwrap();
+        assert!(!std_frame.is_extended());
+        assert_ne!(frame, std_frame);
+
+        let mut text = String::new();
+        frame.write_candump(&mut text).unwrap();
+        assert_eq!("00000001#0102", text);
+
+        let frame = CanFrame::new_remote(ext_id, 2).unwrap();
+        assert!(frame.is_extended());
+        assert!(frame.is_remote_frame());
+
+        let frame = CanFdFrame::new(ext_id, &[1, 2]).unwrap();
+        assert!(frame.is_extended());
+        assert_eq!(0x1, frame.raw_id());
+
+        let mut frame = CanFrame::new(std_id, &[]).unwrap();
+        frame.set_id(ext_id);
+        assert!(frame.is_extended());
+        frame.set_id(std_id);
+        assert!(!frame.is_extended());
+    }
+
     #[test]
     fn test_defaults() {
         let frame = CanFrame::default();
@@ -1493,9 +2483,7 @@ mod tests {
                 assert_eq!(vtype, errors::ViolationType::BitStuffingError);
                 assert_eq!(location, errors::Location::Id0400);
             }
-            _ => {
-                assert!(false);
-            }
+            _ => panic!("Wrong error type"),
         }
     }
 
@@ -1540,4 +2528,570 @@ mod tests {
         assert!(!frame.is_error_frame());
         assert_eq!(DATA, frame.data());
     }
+
+    #[test]
+    fn test_fd_to_frame() {
+        let frame = CanFrame::new(EXT_ID, DATA).unwrap();
+        let fdframe = CanFdFrame::from_can(frame);
+        assert_eq!(EXT_ID, fdframe.id());
+        assert!(fdframe.is_extended());
+        assert_eq!(DATA, fdframe.data());
+
+        let frame = CanFrame::try_from(fdframe).unwrap();
+        assert_eq!(EXT_ID, frame.id());
+        assert!(frame.is_data_frame());
+        assert_eq!(DATA, frame.data());
+
+        let frame = CanFrame::new_remote(STD_ID, DATA_LEN).unwrap();
+        let fdframe = CanFdFrame::from(frame);
+        assert_eq!(STD_ID, fdframe.id());
+        assert!(!fdframe.id_flags().contains(IdFlags::RTR));
+        assert_eq!(EMPTY_DATA, fdframe.data());
+
+        let mut fdframe = CanFdFrame::new(STD_ID, DATA).unwrap();
+        fdframe.set_brs(true);
+        assert!(matches!(
+            CanFrame::try_from(fdframe),
+            Err(ConstructionError::WrongFrameType)
+        ));
+
+        let fdframe = CanFdFrame::new(STD_ID, &[0u8; 12]).unwrap();
+        assert!(matches!(
+            CanFrame::try_from(fdframe),
+            Err(ConstructionError::TooMuchData)
+        ));
+    }
+
+    #[test]
+    fn test_any_frame() {
+        let frame = CanAnyFrame::new(STD_ID, DATA).unwrap();
+        assert!(matches!(frame, CanAnyFrame::Normal(_)));
+        assert_eq!(STD_ID, frame.id());
+        assert_eq!(id_to_raw(STD_ID), frame.raw_id());
+        assert!(frame.is_data_frame());
+        assert_eq!(DATA, frame.data());
+
+        let frame = CanAnyFrame::new(EXT_ID, &[0u8; 12]).unwrap();
+        assert!(matches!(frame, CanAnyFrame::Fd(_)));
+        assert_eq!(EXT_ID, frame.id());
+        assert!(frame.is_extended());
+        assert_eq!(12, frame.len());
+
+        let frame = CanAnyFrame::new_remote(STD_ID, DATA_LEN).unwrap();
+        assert!(matches!(frame, CanAnyFrame::Remote(_)));
+        assert!(frame.is_remote_frame());
+        assert_eq!(DATA_LEN, frame.dlc());
+
+        let frame = CanAnyFrame::Error(CanErrorFrame::from(CanError::NoAck));
+        assert!(frame.is_error_frame());
+        assert!(!frame.is_data_frame());
+    }
+
+    #[test]
+    fn test_frame_eq_ignores_padding() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash<T: Hash>(val: &T) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            val.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        // Classic data frames with different bytes past the data length
+        let frame1 = CanDataFrame::new(STD_ID, DATA).unwrap();
+        let mut raw = can_frame_default();
+        raw.can_id = frame1.id_word();
+        raw.can_dlc = DATA_LEN as u8;
+        raw.data = [0, 1, 2, 3, 0xAA, 0xBB, 0xCC, 0xDD];
+        let frame2 = CanDataFrame::try_from(raw).unwrap();
+
+        assert_eq!(frame1, frame2);
+        assert_eq!(hash(&frame1), hash(&frame2));
+        assert_eq!(CanFrame::from(frame1), CanFrame::from(frame2));
+        assert_ne!(frame1, CanDataFrame::new(STD_ID, ZERO_DATA).unwrap());
+        assert_ne!(frame1, CanDataFrame::new(EXT_LOW_ID, DATA).unwrap());
+
+        // FD frames with different padding
+        let frame1 = CanFdFrame::new(STD_ID, DATA).unwrap();
+        let mut raw = *frame1.as_ref();
+        raw.data[DATA_LEN..].fill(0x55);
+        let frame2 = CanFdFrame::from(raw);
+
+        assert_eq!(frame1, frame2);
+        assert_eq!(hash(&frame1), hash(&frame2));
+        assert_eq!(CanAnyFrame::from(frame1), CanAnyFrame::from(frame2));
+
+        let mut frame3 = frame1;
+        frame3.set_brs(true);
+        assert_ne!(frame1, frame3);
+
+        // Remote frames only compare the DLC
+        let frame1 = CanRemoteFrame::new_remote(STD_ID, 2).unwrap();
+        let mut raw = *frame1.as_ref();
+        raw.data = [0xFF; 8];
+        let frame2 = CanRemoteFrame::try_from(raw).unwrap();
+        assert_eq!(frame1, frame2);
+        assert_ne!(frame1, CanRemoteFrame::new_remote(STD_ID, 3).unwrap());
+    }
+
+    #[test]
+    fn test_fd_frame_builder() {
+        let frame = CanFdFrame::builder(STD_ID).build().unwrap();
+        assert_eq!(STD_ID, frame.id());
+        assert_eq!(EMPTY_DATA, frame.data());
+        assert!(frame.flags().is_empty());
+
+        let frame = CanFdFrame::builder(EXT_ID)
+            .data(DATA)
+            .brs(true)
+            .esi(true)
+            .build()
+            .unwrap();
+        assert!(frame.is_extended());
+        assert_eq!(DATA, frame.data());
+        assert!(frame.is_brs());
+        assert!(frame.is_esi());
+        assert_eq!(
+            frame,
+            CanFdFrame::with_flags(EXT_ID, DATA, FdFlags::all()).unwrap()
+        );
+
+        let frame = CanFdFrame::builder(STD_ID)
+            .flags(FdFlags::all())
+            .esi(false)
+            .build()
+            .unwrap();
+        assert!(frame.is_brs());
+        assert!(!frame.is_esi());
+
+        let data = [0u8; CANFD_MAX_DLEN + 1];
+        assert!(matches!(
+            CanFdFrame::builder(STD_ID).data(&data).build(),
+            Err(ConstructionError::TooMuchData)
+        ));
+    }
+
+    #[test]
+    fn test_frame_from_id() {
+        let frame = CanFrame::from_id(STD_ID, DATA).unwrap();
+        assert_eq!(STD_ID, frame.id());
+        assert!(frame.is_standard());
+        assert_eq!(DATA, frame.data());
+
+        let frame = CanFrame::from_id(EXT_ID, DATA).unwrap();
+        assert_eq!(EXT_ID, frame.id());
+        assert!(frame.is_extended());
+
+        assert!(matches!(
+            CanFrame::from_id(STD_ID, &[0u8; 9]),
+            Err(ConstructionError::TooMuchData)
+        ));
+    }
+
+    #[test]
+    fn test_dlc_consistent() {
+        for n in 0..=8 {
+            let frame = CanDataFrame::new(STD_ID, &[0u8; 8][..n]).unwrap();
+            assert!(frame.is_dlc_consistent());
+            let frame = CanRemoteFrame::new_remote(STD_ID, n).unwrap();
+            assert!(frame.is_dlc_consistent());
+        }
+
+        let mut raw = can_frame_default();
+        raw.can_dlc = 9;
+        assert!(!CanFrame::from(raw).is_dlc_consistent());
+
+        raw.can_dlc = 8;
+        raw.len8_dlc = 12;
+        assert!(CanFrame::from(raw).is_dlc_consistent());
+        raw.len8_dlc = 16;
+        assert!(!CanFrame::from(raw).is_dlc_consistent());
+
+        raw.can_dlc = 4;
+        raw.len8_dlc = 12;
+        assert!(!CanFrame::from(raw).is_dlc_consistent());
+
+        raw.can_id = CAN_RTR_FLAG;
+        assert!(!CanFrame::from(raw).is_dlc_consistent());
+
+        let frame = CanErrorFrame::from(CanError::NoAck);
+        assert!(frame.is_dlc_consistent());
+        let mut raw = *frame.as_ref();
+        raw.can_dlc = 4;
+        assert!(!CanFrame::from(raw).is_dlc_consistent());
+
+        for n in [0, 8, 12, 20, 24, 32, 48, 64] {
+            let frame = CanFdFrame::new(STD_ID, &[0u8; 64][..n]).unwrap();
+            assert!(frame.is_dlc_consistent());
+            assert!(CanAnyFrame::from(frame).is_dlc_consistent());
+        }
+        for n in [9, 21, 33, 63] {
+            let frame = CanFdFrame::new(STD_ID, &[0u8; 64][..n]).unwrap();
+            assert!(!frame.is_dlc_consistent());
+        }
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let frame = CanFrame::from_id(EXT_ID, DATA).unwrap();
+        assert_eq!(Some(frame), CanFrame::from_bytes(frame.as_bytes()));
+        assert_eq!(
+            Some(CanAnyFrame::from(frame)),
+            CanAnyFrame::from_bytes(frame.as_bytes())
+        );
+
+        let frame = CanFrame::new_remote(STD_ID, 4).unwrap();
+        assert_eq!(Some(frame), CanFrame::from_bytes(frame.as_bytes()));
+
+        let frame = CanFdFrame::new(STD_ID, &[0xAAu8; 20]).unwrap();
+        assert_eq!(
+            Some(CanAnyFrame::from(frame)),
+            CanAnyFrame::from_bytes(frame.as_bytes())
+        );
+        assert_eq!(None, CanFrame::from_bytes(frame.as_bytes()));
+
+        // Wrong sizes
+        assert_eq!(None, CanFrame::from_bytes(&[]));
+        assert_eq!(None, CanFrame::from_bytes(&[0u8; 17]));
+        assert_eq!(None, CanAnyFrame::from_bytes(&[0u8; 71]));
+
+        // Bad lengths
+        let mut raw = can_frame_default();
+        raw.can_dlc = 9;
+        assert_eq!(None, CanFrame::from_bytes(crate::as_bytes(&raw)));
+        assert_eq!(None, CanAnyFrame::from_bytes(crate::as_bytes(&raw)));
+
+        let mut fdraw = canfd_frame_default();
+        fdraw.len = 13;
+        assert_eq!(None, CanAnyFrame::from_bytes(crate::as_bytes(&fdraw)));
+        fdraw.len = 12;
+        assert!(CanAnyFrame::from_bytes(crate::as_bytes(&fdraw)).is_some());
+
+        // No FD remote frames
+        fdraw.can_id = 0x123 | CAN_RTR_FLAG;
+        assert_eq!(None, CanAnyFrame::from_bytes(crate::as_bytes(&fdraw)));
+
+        let mut raw = canfd_frame_default();
+        raw.len = 65;
+        assert_eq!(None, CanAnyFrame::from_bytes(crate::as_bytes(&raw)));
+    }
+
+    #[test]
+    fn test_debug_format() {
+        let frame =
+            CanDataFrame::new(StandardId::new(0x123).unwrap(), &[0x01, 0x02, 0xAB]).unwrap();
+        let expected = "CanDataFrame { id: 00000123, dlc: 3, data: [01 02 AB] }";
+        assert_eq!(expected, format!("{:?}", frame));
+        assert_eq!(expected, format!("{:?}", CanFrame::from(frame)));
+        assert_eq!(
+            expected,
+            format!("{:?}", CanAnyFrame::from(CanFrame::from(frame)))
+        );
+
+        let frame = CanRemoteFrame::new_remote(ExtendedId::new(0x1234).unwrap(), 2).unwrap();
+        assert_eq!(
+            "CanRemoteFrame { id: C0001234, dlc: 2 }",
+            format!("{:?}", CanFrame::from(frame))
+        );
+
+        let frame =
+            CanFdFrame::with_flags(StandardId::new(0x42).unwrap(), &[0xFF; 9], FdFlags::BRS)
+                .unwrap();
+        assert_eq!(
+            "CanFdFrame { id: 00000042, flags: BRS, dlc: 12, \
+             data: [FF FF FF FF FF FF FF FF FF] }",
+            format!("{:?}", CanAnyFrame::from(frame))
+        );
+    }
+
+    #[test]
+    fn test_truncate_to_classic() {
+        let id = ExtendedId::new(0x1234).unwrap();
+        let data: Vec<u8> = (0..12).collect();
+
+        let frame = CanFdFrame::with_flags(id, &data, FdFlags::BRS).unwrap();
+        let classic = frame.truncate_to_classic();
+        assert!(classic.is_extended());
+        assert_eq!(frame.raw_id(), classic.raw_id());
+        assert_eq!(CAN_MAX_DLEN, classic.dlc());
+        assert_eq!(&data[..CAN_MAX_DLEN], classic.data());
+
+        // Data at or under the classic limit is kept whole
+        for n in [0, 7, 8] {
+            let frame = CanFdFrame::new(id, &data[..n]).unwrap();
+            assert_eq!(&data[..n], frame.truncate_to_classic().data());
+        }
+    }
+
+    #[test]
+    fn test_id_accessors() {
+        let frame = CanFrame::new(StandardId::new(0x123).unwrap(), DATA).unwrap();
+        assert_eq!(IdKind::Standard, frame.id_kind());
+        assert_eq!(StandardId::new(0x123), frame.standard_id());
+        assert_eq!(None, frame.extended_id());
+
+        // An extended ID below the standard range is still extended
+        let frame =
+            CanAnyFrame::from(CanFdFrame::new(ExtendedId::new(0x12).unwrap(), DATA).unwrap());
+        assert_eq!(IdKind::Extended, frame.id_kind());
+        assert_eq!(None, frame.standard_id());
+        assert_eq!(ExtendedId::new(0x12), frame.extended_id());
+    }
+
+    #[test]
+    fn test_try_from_any_frame() {
+        let id = StandardId::new(0x123).unwrap();
+        let frame = CanFrame::new(id, DATA).unwrap();
+        let fdframe = CanFdFrame::new(id, DATA).unwrap();
+
+        let any = CanAnyFrame::from(frame);
+        assert_eq!(frame, CanFrame::try_from(any).unwrap());
+        assert!(matches!(
+            CanFdFrame::try_from(any),
+            Err(ConstructionError::WrongFrameType)
+        ));
+
+        let any = CanAnyFrame::from(fdframe);
+        assert_eq!(fdframe, CanFdFrame::try_from(any).unwrap());
+        assert!(matches!(
+            CanFrame::try_from(any),
+            Err(ConstructionError::WrongFrameType)
+        ));
+    }
+
+    #[test]
+    fn test_write_candump() {
+        let mut s = String::new();
+        let frame = CanDataFrame::new(StandardId::new(0x12).unwrap(), &[0x01, 0x02, 0xAB]).unwrap();
+        frame.write_candump(&mut s).unwrap();
+        assert_eq!("012#0102AB", s);
+
+        s.clear();
+        let frame = CanRemoteFrame::new_remote(ExtendedId::new(0x1234).unwrap(), 2).unwrap();
+        CanFrame::from(frame).write_candump(&mut s).unwrap();
+        assert_eq!("00001234#R2", s);
+
+        s.clear();
+        let frame = CanErrorFrame::from(CanError::NoAck);
+        frame.write_candump(&mut s).unwrap();
+        assert_eq!("20000020#0000000000000000", s);
+
+        s.clear();
+        let frame =
+            CanFdFrame::with_flags(StandardId::new(0x123).unwrap(), &[0xAA, 0xBB], FdFlags::BRS)
+                .unwrap();
+        CanAnyFrame::from(frame).write_candump(&mut s).unwrap();
+        assert_eq!("123##1AABB", s);
+
+        s.clear();
+        let mut raw = can_frame_default();
+        raw.can_id = 0x123;
+        raw.can_dlc = 8;
+        raw.len8_dlc = 12;
+        CanFrame::from(raw).write_candump(&mut s).unwrap();
+        assert_eq!("123#0000000000000000_C", s);
+
+        s.clear();
+        raw.can_id |= CAN_RTR_FLAG;
+        CanFrame::from(raw).write_candump(&mut s).unwrap();
+        assert_eq!("123#R8C", s);
+    }
+
+    #[test]
+    fn test_candump_format() {
+        let frame = CanDataFrame::new(StandardId::new(0x701).unwrap(), &[0x7F]).unwrap();
+        assert_eq!(
+            "(1469439874.299654) can1 701#7F",
+            candump_format(1469439874299654, "can1", &frame)
+        );
+
+        let frame = CanRemoteFrame::new_remote(StandardId::new(0x80).unwrap(), 0).unwrap();
+        assert_eq!(
+            "(0000000001.000002) vcan0 080#R",
+            candump_format(1_000_002, "vcan0", &frame)
+        );
+    }
+
+    #[test]
+    fn test_fd_raw_flags() {
+        let mut frame = CanFdFrame::new(StandardId::new(0x123).unwrap(), &[1, 2]).unwrap();
+        assert_eq!(0, frame.raw_flags());
+
+        frame.set_brs(true);
+        assert_eq!(CANFD_BRS as u8, frame.raw_flags());
+
+        // Bits beyond the typed flags are kept
+        frame.set_raw_flags(0x80 | CANFD_ESI as u8);
+        assert_eq!(0x80 | CANFD_ESI as u8, frame.raw_flags());
+        assert_eq!(FdFlags::ESI, frame.flags());
+        assert!(!frame.is_brs());
+
+        // ...and are part of the frame's identity
+        let mut other = frame;
+        other.set_raw_flags(CANFD_ESI as u8);
+        assert_eq!(frame.flags(), other.flags());
+        assert_ne!(frame, other);
+    }
+
+    #[test]
+    fn test_fd_fdf_flag() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |frame: &CanFdFrame| {
+            let mut hasher = DefaultHasher::new();
+            frame.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        // A frame echoed by the kernel has the FDF flag added
+        let sent =
+            CanFdFrame::with_flags(StandardId::new(0x123).unwrap(), &[1, 2], FdFlags::BRS).unwrap();
+        let mut echo = sent;
+        echo.set_raw_flags(sent.raw_flags() | CANFD_FDF);
+        assert_ne!(sent.raw_flags(), echo.raw_flags());
+        assert_eq!(sent, echo);
+        assert_eq!(hash(&sent), hash(&echo));
+    }
+
+    #[test]
+    fn test_matches() {
+        let frame = CanDataFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+        assert!(frame.matches(0x123, &[1, 2, 3]));
+        assert!(!frame.matches(0x123, &[1, 2]));
+        assert!(!frame.matches(0x124, &[1, 2, 3]));
+
+        let frame = CanFdFrame::new(ExtendedId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+        assert!(frame.matches(0x123 | CAN_EFF_FLAG, &[1, 2, 3]));
+        assert!(!frame.matches(0x123, &[1, 2, 3]));
+    }
+
+    #[test]
+    fn test_frame_ord() {
+        use std::collections::BTreeSet;
+
+        let frame = |id, data: &[u8]| CanFrame::from_raw_id(id, data).unwrap();
+        let std_id = StandardId::new(0x100).unwrap();
+        let ext_id = ExtendedId::new(0x100).unwrap();
+
+        assert!(frame(0x100, &[1]) < frame(0x101, &[0]));
+        assert!(frame(0x100, &[1]) < frame(0x100, &[2]));
+        assert!(frame(0x100, &[1]) < frame(0x100, &[1, 0]));
+        assert!(CanFrame::new(std_id, &[]).unwrap() < CanFrame::new(ext_id, &[]).unwrap());
+        assert!(CanFrame::new(std_id, &[]).unwrap() < CanFrame::new_remote(std_id, 0).unwrap());
+
+        // The ERR flag sorts below the RTR flag for the same raw value
+        let err_frame = CanFrame::from(CanErrorFrame::new_error(0x040, &[]).unwrap());
+        let rtr_frame = CanFrame::new_remote(StandardId::new(0x040).unwrap(), 0).unwrap();
+        assert_eq!(err_frame.raw_id(), rtr_frame.raw_id());
+        assert!(err_frame < rtr_frame);
+
+        let set: BTreeSet<_> = [
+            frame(0x200, &[1]),
+            frame(0x100, &[2]),
+            frame(0x100, &[1]),
+            frame(0x200, &[1]),
+        ]
+        .into_iter()
+        .collect();
+        let ids: Vec<_> = set.iter().map(|f| (f.raw_id(), f.data()[0])).collect();
+        assert_eq!(vec![(0x100, 1), (0x100, 2), (0x200, 1)], ids);
+    }
+
+    #[test]
+    fn test_bus_bits() {
+        let std_id = StandardId::new(0x123).unwrap();
+        let ext_id = ExtendedId::new(0x12345).unwrap();
+
+        // Worst-case classic frame lengths from the CAN response time analysis
+        let frame = CanDataFrame::new(std_id, &[0xFF; 8]).unwrap();
+        assert_eq!(135.0, frame.bus_bits(1.0));
+        let frame = CanDataFrame::new(ext_id, &[0xFF; 8]).unwrap();
+        assert_eq!(160.0, frame.bus_bits(1.0));
+        let frame = CanDataFrame::new(std_id, &[]).unwrap();
+        assert_eq!(55.0, frame.bus_bits(1.0));
+        let frame = CanDataFrame::new(ext_id, &[]).unwrap();
+        assert_eq!(80.0, frame.bus_bits(1.0));
+
+        // A remote frame carries no data, whatever its DLC
+        let frame = CanRemoteFrame::new_remote(std_id, 8).unwrap();
+        assert_eq!(55.0, CanFrame::from(frame).bus_bits(1.0));
+
+        let frame = CanFdFrame::new(std_id, &[0xFF; 8]).unwrap();
+        assert_eq!(147.0, frame.bus_bits(4.0));
+        let frame = CanFdFrame::with_flags(std_id, &[0xFF; 8], FdFlags::BRS).unwrap();
+        assert_eq!(34.0 + 113.0 / 4.0, frame.bus_bits(4.0));
+        assert_eq!(147.0, frame.bus_bits(1.0));
+
+        // Padded out to 64 bytes, with the longer CRC
+        let frame = CanFdFrame::with_flags(ext_id, &[0xFF; 63], FdFlags::BRS).unwrap();
+        assert_eq!(
+            CanAnyFrame::from(frame).bus_bits(8.0),
+            (36 + 8 + 13) as f64 + (517 + (553 / 4 - 8) + 32) as f64 / 8.0
+        );
+    }
+
+    #[test]
+    fn test_fd_frame_padding() {
+        assert_eq!(8, fd_padded_len(8));
+        assert_eq!(12, fd_padded_len(9));
+        assert_eq!(24, fd_padded_len(21));
+        assert_eq!(32, fd_padded_len(25));
+        assert_eq!(48, fd_padded_len(33));
+        assert_eq!(64, fd_padded_len(49));
+
+        let data = [0x11u8; 21];
+        let mut frame = CanFdFrame::builder(STD_ID)
+            .data(&data)
+            .with_pad_byte(0xCC)
+            .build()
+            .unwrap();
+        assert_eq!(21, frame.len());
+        assert_eq!(24, frame.dlc());
+        assert_eq!(&data, frame.data());
+        assert_eq!([0xCC; 3], frame.0.data[21..24]);
+        assert_eq!(0, frame.0.data[24]);
+
+        frame.set_data(&data[..9]).unwrap();
+        assert_eq!(9, frame.len());
+        assert_eq!(12, frame.dlc());
+        assert_eq!([0; 3], frame.0.data[9..12]);
+
+        let frame = CanAnyFrame::from(frame);
+        assert_eq!(9, frame.len());
+        assert_eq!(12, frame.dlc());
+    }
+
+    #[test]
+    fn test_set_data() {
+        let mut frame = CanFrame::new(STD_ID, &[0xFF; 8]).unwrap();
+        if let CanFrame::Data(frame) = &mut frame {
+            frame.0.len8_dlc = 12;
+        }
+
+        frame.set_data(&[1, 2]).unwrap();
+        assert_eq!(&[1, 2], frame.data());
+        assert_eq!(2, frame.dlc());
+        assert!(frame.is_dlc_consistent());
+        assert_eq!(
+            CanFrame::new(STD_ID, &[1, 2]).unwrap().as_bytes(),
+            frame.as_bytes()
+        );
+
+        assert!(matches!(
+            frame.set_data(&[0; 9]),
+            Err(ConstructionError::TooMuchData)
+        ));
+        assert_eq!(&[1, 2], frame.data());
+
+        let mut frame = CanFdFrame::new(STD_ID, &[]).unwrap();
+        frame.set_data(&[0xAA; 64]).unwrap();
+        assert_eq!(64, frame.len());
+        assert!(frame.set_data(&[0; 65]).is_err());
+
+        let mut frame = CanErrorFrame::from(CanError::NoAck);
+        assert!(matches!(
+            frame.set_data(&[1]),
+            Err(ConstructionError::WrongFrameType)
+        ));
+    }
 }
@@ -0,0 +1,100 @@
+// socketcan/src/intervals.rs
+//
+// Implements timing analysis of timestamped CAN frames.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Timing analysis of timestamped frames.
+
+use std::time::{Duration, SystemTime};
+
+/// An extension for iterators of timestamped frames, to get the time
+/// between them.
+///
+/// This is implemented for any iterator of `(frame, time)` pairs, like
+/// those from the timestamped reads of a [`CanSocket`](crate::CanSocket),
+/// such as `read_frames_with_timestamps()`.
+pub trait FrameIntervals: Iterator + Sized {
+    /// Converts the frames into the gaps between the receive times of each
+    /// consecutive pair, so `n` frames give `n - 1` intervals.
+    ///
+    /// Timestamps from the system clock can go backwards if the clock is
+    /// stepped, and an interval to an earlier time is given as zero.
+    fn intervals(self) -> Intervals<Self> {
+        Intervals {
+            iter: self,
+            last: None,
+        }
+    }
+}
+
+impl<I, F> FrameIntervals for I where I: Iterator<Item = (F, SystemTime)> {}
+
+/// An iterator over the time between consecutive timestamped frames.
+///
+/// This is created with [`FrameIntervals::intervals()`].
+#[derive(Debug, Clone)]
+pub struct Intervals<I> {
+    iter: I,
+    last: Option<SystemTime>,
+}
+
+impl<I, F> Iterator for Intervals<I>
+where
+    I: Iterator<Item = (F, SystemTime)>,
+{
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (_, t) = self.iter.next()?;
+            if let Some(last) = self.last.replace(t) {
+                return Some(t.duration_since(last).unwrap_or_default());
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.iter.size_hint();
+        match self.last {
+            Some(_) => (lo, hi),
+            None => (lo.saturating_sub(1), hi.map(|n| n.saturating_sub(1))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{frame::can_frame_default, CanFrame};
+
+    #[test]
+    fn test_intervals() {
+        let frame = CanFrame::from(can_frame_default());
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let frames = vec![
+            (frame, t0),
+            (frame, t0 + Duration::from_millis(10)),
+            (frame, t0 + Duration::from_millis(25)),
+            (frame, t0 + Duration::from_millis(5)),
+        ];
+
+        let intervals = frames.into_iter().intervals();
+        assert_eq!((3, Some(3)), intervals.size_hint());
+        assert_eq!(
+            vec![
+                Duration::from_millis(10),
+                Duration::from_millis(15),
+                Duration::ZERO
+            ],
+            intervals.collect::<Vec<_>>()
+        );
+
+        assert_eq!(None, [(frame, t0)].into_iter().intervals().next());
+    }
+}
@@ -71,9 +71,19 @@
 //!   dependencies like [anyhow](https://docs.rs/anyhow/latest/anyhow/) and
 //!   [clap](https://docs.rs/clap/latest/clap/)
 //!
+//! * **test_harness** -
+//!   Include `nl::TemporaryInterface`, a helper to create a virtual CAN
+//!   interface that is deleted when it goes out of scope, for running socket
+//!   tests without CAN hardware. This enables **netlink**, and the helper
+//!   requires the `CAP_NET_ADMIN` capability at runtime.
+//!
 //! * **tokio** -
 //!   Include support for async/await using [tokio](https://crates.io/crates/tokio).
 //!
+//! * **tokio-util** -
+//!   Include codecs to use CAN frames with the `Framed` types from
+//!   [tokio-util](https://crates.io/crates/tokio-util). This enables **tokio**.
+//!
 //! * **async-io** -
 //!   Include support for async/await using [async-io](https://crates.io/crates/async-io)
 //!   This will work with any runtime that uses _async_io_, including
@@ -103,7 +113,7 @@
     unsafe_op_in_unsafe_fn
 )]
 
-use std::{io::ErrorKind, mem::size_of};
+use std::mem::size_of;
 
 // Re-export the embedded_can crate so that applications can rely on
 // finding the same version we use.
@@ -114,8 +124,8 @@ pub use embedded_can::{
 
 pub mod errors;
 pub use errors::{
-    CanError, CanErrorDecodingFailure, ConstructionError, Error, IoError, IoErrorKind, IoResult,
-    Result,
+    CanError, CanErrorDecodingFailure, ConstructionError, ErrClass, Error, ErrorCounter, IoError,
+    IoErrorKind, IoResult, Result, WriteFramesError,
 };
 
 pub mod addr;
@@ -123,21 +133,54 @@ pub use addr::CanAddr;
 
 pub mod frame;
 pub use frame::{
-    CanAnyFrame, CanDataFrame, CanErrorFrame, CanFdFrame, CanFrame, CanRawFrame, CanRemoteFrame,
-    Frame,
+    candump_format, CanAnyFrame, CanDataFrame, CanErrorFrame, CanFdFrame, CanFdFrameBuilder,
+    CanFrame, CanRawFrame, CanRemoteFrame, Frame,
 };
 
+/// Masks and flags for the composite SocketCAN ID word.
+///
+/// These can be used to mask and test the ID word of a frame, like the
+/// value returned by `Frame::id_word()`, without depending on libc directly.
+pub mod id {
+    pub use crate::frame::{
+        CAN_EFF_FLAG, CAN_EFF_MASK, CAN_ERR_FLAG, CAN_ERR_MASK, CAN_RTR_FLAG, CAN_SFF_MASK,
+    };
+}
+pub use id::{CAN_EFF_FLAG, CAN_EFF_MASK, CAN_ERR_FLAG, CAN_ERR_MASK, CAN_RTR_FLAG, CAN_SFF_MASK};
+
 #[cfg(feature = "dump")]
 pub mod dump;
 
 pub mod socket;
-pub use socket::{CanFdSocket, CanFilter, CanSocket, ShouldRetry, Socket, SocketOptions};
+pub use socket::{
+    AncillaryData, AncillaryMessage, AncillaryMessages, CanBus, CanFdSocket, CanFilter, CanReader,
+    CanSocket, CanWriter, Errors, FrameBuf, FrameTimestamps, OpenOptions, ReadShutdown, RecvFlags,
+    RetryPolicy, ShouldRetry, Socket, SocketOptions, TimestampConfig, TxTimestamp, UnboundSocket,
+};
+
+pub mod dispatch;
+pub use dispatch::Dispatcher;
+
+pub mod filters;
+pub use filters::FilterSet;
+
+pub mod intervals;
+pub use intervals::{FrameIntervals, Intervals};
+
+pub mod multi;
+pub use multi::MultiSocket;
+
+pub mod replay;
+pub use replay::ReplayWriter;
+
+pub mod stats;
+pub use stats::{Stats, StatsSnapshot};
 
 #[cfg(feature = "netlink")]
 pub mod nl;
 
 #[cfg(feature = "netlink")]
-pub use nl::{CanCtrlMode, CanInterface, SetCanParams};
+pub use nl::{CanCtrlMode, CanInterface, NetemParams, SetCanParams};
 
 /// Optional tokio support
 #[cfg(feature = "tokio")]
@@ -159,10 +202,10 @@ pub mod async_std {
     pub use crate::async_io::*;
 }
 
-#[cfg(feature = "enumerate")]
 pub mod enumerate;
 #[cfg(feature = "enumerate")]
 pub use enumerate::available_interfaces;
+pub use enumerate::{list_interfaces, InterfaceInfo};
 
 // ===== helper functions =====
 
@@ -183,6 +226,17 @@ pub(crate) fn as_bytes_mut<T: Sized>(val: &mut T) -> &mut [u8] {
 
 // ===== embedded_can I/O traits =====
 
+/// Converts an I/O error from a socket into a non-blocking result error.
+///
+/// Any error for which a retry is expected, such as `WouldBlock` from a
+/// socket in nonblocking mode, is reported as `nb::Error::WouldBlock`.
+fn nb_error(err: IoError) -> nb::Error<Error> {
+    match err.should_retry() {
+        true => nb::Error::WouldBlock,
+        false => Error::from(err).into(),
+    }
+}
+
 impl embedded_can::blocking::Can for CanSocket {
     type Frame = CanFrame;
     type Error = Error;
@@ -207,6 +261,29 @@ impl embedded_can::blocking::Can for CanSocket {
     }
 }
 
+impl embedded_can::blocking::Can for CanFdSocket {
+    type Frame = CanAnyFrame;
+    type Error = Error;
+
+    /// Blocking call to receive the next frame from the bus.
+    ///
+    /// This blocks and waits for the next frame, classic or FD, to be
+    /// received from the bus. If an error frame is received, it will be
+    /// converted to a `CanError` and returned as an error.
+    fn receive(&mut self) -> Result<Self::Frame> {
+        match self.read_frame() {
+            Ok(CanAnyFrame::Error(frame)) => Err(frame.into_error().into()),
+            Ok(frame) => Ok(frame),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Blocking transmit of a frame to the bus.
+    fn transmit(&mut self, frame: &Self::Frame) -> Result<()> {
+        self.write_frame_insist(frame).map_err(|err| err.into())
+    }
+}
+
 impl embedded_can::nb::Can for CanSocket {
     type Frame = CanFrame;
     type Error = Error;
@@ -222,25 +299,39 @@ impl embedded_can::nb::Can for CanSocket {
             Ok(Data(frame)) => Ok(Data(frame)),
             Ok(Remote(frame)) => Ok(Remote(frame)),
             Ok(Error(frame)) => Err(crate::Error::from(frame.into_error()).into()),
-            Err(err) => Err(match err.kind() {
-                ErrorKind::WouldBlock => nb::Error::WouldBlock,
-                _ => crate::Error::from(err).into(),
-            }),
+            Err(err) => Err(nb_error(err)),
         }
     }
 
     /// Non-blocking transmit of a frame to the bus.
     fn transmit(&mut self, frame: &Self::Frame) -> nb::Result<Option<Self::Frame>, Self::Error> {
-        match self.write_frame(frame) {
-            Ok(_) => Ok(None),
-            Err(err) => {
-                match err.kind() {
-                    ErrorKind::WouldBlock => Err(nb::Error::WouldBlock),
-                    // TODO: How to indicate buffer is full?
-                    // ErrorKind::StorageFull => Ok(frame),
-                    _ => Err(Error::from(err).into()),
-                }
-            }
+        // TODO: How to indicate buffer is full?
+        // ErrorKind::StorageFull => Ok(frame),
+        self.write_frame(frame).map(|_| None).map_err(nb_error)
+    }
+}
+
+impl embedded_can::nb::Can for CanFdSocket {
+    type Frame = CanAnyFrame;
+    type Error = Error;
+
+    /// Non-blocking call to receive the next frame, classic or FD, from
+    /// the bus.
+    ///
+    /// The socket should be put into nonblocking mode for this to be
+    /// useful. If an error frame is received, it will be converted to a
+    /// `CanError` and returned as an error. If no frame is available, it
+    /// returns a `WouldBlock` error.
+    fn receive(&mut self) -> nb::Result<Self::Frame, Self::Error> {
+        match self.read_frame() {
+            Ok(CanAnyFrame::Error(frame)) => Err(Error::from(frame.into_error()).into()),
+            Ok(frame) => Ok(frame),
+            Err(err) => Err(nb_error(err)),
         }
     }
+
+    /// Non-blocking transmit of a frame to the bus.
+    fn transmit(&mut self, frame: &Self::Frame) -> nb::Result<Option<Self::Frame>, Self::Error> {
+        self.write_frame(frame).map(|_| None).map_err(nb_error)
+    }
 }
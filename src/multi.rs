@@ -0,0 +1,252 @@
+// socketcan/src/multi.rs
+//
+// Implements reading from several CAN interfaces at once.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Reading from several CAN interfaces at once.
+
+use crate::{
+    frame::AsPtr, socket::open_error, CanAddr, CanFrame, CanSocket, IoError, IoErrorKind, IoResult,
+    Socket,
+};
+use std::{
+    os::{raw::c_int, unix::io::AsRawFd},
+    time::Duration,
+};
+
+/// A set of classic CAN sockets, one per interface, that are read together.
+///
+/// This opens a separate socket on each of the interfaces and multiplexes
+/// reads across all of them with `poll(2)`, returning each frame along with
+/// the index of the interface on which it was received. When frames are
+/// waiting on more than one interface, the sockets take turns, so that a
+/// busy bus can not starve the others.
+///
+/// ```no_run
+/// use socketcan::MultiSocket;
+///
+/// let mut socks = MultiSocket::open(["can0", "can1"]).unwrap();
+///
+/// loop {
+///     let (ifindex, frame) = socks.read_frame().unwrap();
+///     println!("{}: {:X}", ifindex, frame);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MultiSocket {
+    socks: Vec<(u32, CanSocket)>,
+    next: usize,
+}
+
+impl MultiSocket {
+    /// Opens a socket on each of the named interfaces.
+    pub fn open<I, S>(ifnames: I) -> IoResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let socks = ifnames
+            .into_iter()
+            .map(|ifname| Self::open_iface(ifname.as_ref()))
+            .collect::<IoResult<_>>()?;
+        Ok(Self { socks, next: 0 })
+    }
+
+    // Opens a socket on the named interface, along with its index.
+    fn open_iface(ifname: &str) -> IoResult<(u32, CanSocket)> {
+        CanAddr::from_iface(ifname)
+            .and_then(|addr| Ok((addr.ifindex(), CanSocket::open_addr(&addr)?)))
+            .map_err(|err| open_error(err, ifname))
+    }
+
+    /// Creates a set from sockets that are already open, along with the
+    /// interface index of each.
+    pub fn from_sockets<I>(socks: I) -> Self
+    where
+        I: IntoIterator<Item = (u32, CanSocket)>,
+    {
+        Self {
+            socks: socks.into_iter().collect(),
+            next: 0,
+        }
+    }
+
+    /// Gets the number of sockets in the set.
+    pub fn len(&self) -> usize {
+        self.socks.len()
+    }
+
+    /// Determines if the set contains no sockets.
+    pub fn is_empty(&self) -> bool {
+        self.socks.is_empty()
+    }
+
+    /// Gets an iterator over the sockets in the set, along with the
+    /// interface index of each.
+    ///
+    /// This can be used to configure the individual sockets, such as to
+    /// set filters on them.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &CanSocket)> {
+        self.socks.iter().map(|(ifindex, sock)| (*ifindex, sock))
+    }
+
+    /// Gets the socket for the interface with the specified index.
+    pub fn socket(&self, ifindex: u32) -> Option<&CanSocket> {
+        self.iter()
+            .find(|(idx, _)| *idx == ifindex)
+            .map(|(_, sock)| sock)
+    }
+
+    /// Opens a socket on the named interface and adds it to the set.
+    ///
+    /// This lets the set follow interfaces as they come and go, such as
+    /// USB adapters being plugged in, and the new socket is included in
+    /// the next read. Returns the index of the interface. If the set
+    /// already has a socket for the interface, it is kept, and no new
+    /// socket is opened.
+    pub fn add_interface(&mut self, ifname: &str) -> IoResult<u32> {
+        let ifindex = CanAddr::from_iface(ifname)
+            .map_err(|err| open_error(err, ifname))?
+            .ifindex();
+        if self.socket(ifindex).is_none() {
+            self.socks.push(Self::open_iface(ifname)?);
+        }
+        Ok(ifindex)
+    }
+
+    /// Removes the socket for the named interface from the set, and
+    /// returns it.
+    ///
+    /// The name is looked up to get the index of the interface, which
+    /// fails once the interface is gone. Use `remove()` with the index of
+    /// an interface that has already been removed from the system.
+    pub fn remove_interface(&mut self, ifname: &str) -> IoResult<CanSocket> {
+        let ifindex = CanAddr::from_iface(ifname)
+            .map_err(|err| open_error(err, ifname))?
+            .ifindex();
+        self.remove(ifindex)
+            .ok_or_else(|| IoError::from(IoErrorKind::NotFound))
+    }
+
+    /// Removes the socket for the interface with the specified index from
+    /// the set, and returns it, if there was one.
+    pub fn remove(&mut self, ifindex: u32) -> Option<CanSocket> {
+        let i = self.socks.iter().position(|(idx, _)| *idx == ifindex)?;
+        let (_, sock) = self.socks.remove(i);
+        // Keep the turn with the socket that was due to be read next
+        if i < self.next {
+            self.next -= 1;
+        }
+        if self.next >= self.socks.len() {
+            self.next = 0;
+        }
+        Some(sock)
+    }
+
+    /// Blocking read of the next frame from any of the interfaces.
+    ///
+    /// Returns the index of the interface on which the frame was received,
+    /// along with the frame.
+    pub fn read_frame(&mut self) -> IoResult<(u32, CanFrame)> {
+        self.read_frame_poll(-1)
+    }
+
+    /// Blocking read of the next frame from any of the interfaces, with a
+    /// timeout.
+    ///
+    /// This returns an error with the kind `TimedOut` if no frame is
+    /// received on any of the interfaces before the timeout expires.
+    pub fn read_frame_timeout(&mut self, timeout: Duration) -> IoResult<(u32, CanFrame)> {
+        self.read_frame_poll(timeout.as_millis().min(c_int::MAX as u128) as c_int)
+    }
+
+    /// Writes a frame to the interface with the specified index.
+    pub fn write_frame<F>(&self, ifindex: u32, frame: &F) -> IoResult<()>
+    where
+        F: Into<CanFrame> + AsPtr,
+    {
+        self.socket(ifindex)
+            .ok_or_else(|| IoError::from(IoErrorKind::NotFound))?
+            .write_frame(frame)
+    }
+
+    // Polls all the sockets and reads from the first one that's ready,
+    // starting with the one after the socket that was last read.
+    fn read_frame_poll(&mut self, timeout: c_int) -> IoResult<(u32, CanFrame)> {
+        use nix::poll::{poll, PollFd, PollFlags};
+
+        if self.socks.is_empty() {
+            return Err(IoErrorKind::NotFound.into());
+        }
+
+        let mut pollfds: Vec<_> = self
+            .socks
+            .iter()
+            .map(|(_, sock)| PollFd::new(sock.as_raw_fd(), PollFlags::POLLIN))
+            .collect();
+
+        if poll(&mut pollfds, timeout)? == 0 {
+            return Err(IoErrorKind::TimedOut.into());
+        }
+
+        let n = self.socks.len();
+        for i in (0..n).map(|i| (self.next + i) % n) {
+            if let Some(events) = pollfds[i].revents() {
+                if !events.is_empty() {
+                    self.next = (i + 1) % n;
+                    let (ifindex, sock) = &self.socks[i];
+                    return Ok((*ifindex, sock.read_frame()?));
+                }
+            }
+        }
+        Err(IoErrorKind::WouldBlock.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{frame::can_frame_default, socket::tests::socket_pair};
+
+    #[test]
+    fn test_empty_multi_socket() {
+        let mut socks = MultiSocket::from_sockets([]);
+        assert!(socks.is_empty());
+
+        let err = socks.read_frame().unwrap_err();
+        assert_eq!(IoErrorKind::NotFound, err.kind());
+    }
+
+    #[test]
+    fn test_multi_socket_remove() {
+        let (sock1, _peer1) = socket_pair();
+        let (sock2, peer2) = socket_pair();
+        let mut socks = MultiSocket::from_sockets([(1, sock1), (2, sock2)]);
+        assert_eq!(2, socks.len());
+
+        assert!(socks.remove(1).is_some());
+        assert!(socks.remove(1).is_none());
+        assert_eq!(1, socks.len());
+        assert!(socks.socket(1).is_none());
+
+        let frame = CanFrame::from(can_frame_default());
+        peer2.send(frame.as_bytes()).unwrap();
+        assert_eq!((2, frame), socks.read_frame().unwrap());
+
+        assert!(socks.remove(2).is_some());
+        assert!(socks.is_empty());
+    }
+
+    #[test]
+    fn test_remove_missing_interface() {
+        let mut socks = MultiSocket::from_sockets([]);
+        let err = socks.remove_interface("nosuchcan0").unwrap_err();
+        assert!(err.to_string().contains("'nosuchcan0'"));
+    }
+}
@@ -52,27 +52,31 @@
 //! <https://github.com/lalten/libsocketcan>
 //!
 
+use crate::as_bytes;
 use neli::{
     attr::Attribute,
     consts::{
         nl::{NlType, NlmF, NlmFFlags},
         rtnl::{Arphrd, RtAddrFamily, Rtm},
-        rtnl::{Iff, IffFlags, Ifla, IflaInfo},
+        rtnl::{Iff, IffFlags, Ifla, IflaInfo, Tca},
         socket::NlFamily,
     },
     err::NlError,
     nl::{NlPayload, Nlmsghdr},
-    rtnl::{Ifinfomsg, Rtattr},
+    rtnl::{Ifinfomsg, Rtattr, Tcmsg},
     socket::NlSocketHandle,
     types::{Buffer, RtBuffer},
     FromBytes, ToBytes,
 };
 use nix::{self, net::if_::if_nametoindex, unistd};
-use rt::IflaCan;
+use rt::{IflaCan, IflaCanCtrlMode, TcaNetem};
 use std::{
     ffi::CStr,
     fmt::Debug,
+    io,
+    mem::size_of,
     os::raw::{c_int, c_uint},
+    time::Duration,
 };
 
 /// Low-level Netlink CAN struct bindings.
@@ -107,6 +111,8 @@ pub struct InterfaceDetails {
     pub index: c_uint,
     /// Whether the interface is currently up
     pub is_up: bool,
+    /// Whether the interface is currently running (operationally up)
+    pub is_running: bool,
     /// The MTU size of the interface (Standard or FD frames support)
     pub mtu: Option<Mtu>,
     /// The CAN-specific parameters for the interface
@@ -132,16 +138,19 @@ pub enum Mtu {
     Standard = 16,
     /// FD CAN frame, 64-byte data (64-byte total)
     Fd = 72,
+    /// XL CAN frame, 2048-byte data (2060-byte total)
+    Xl = 2060,
 }
 
 impl TryFrom<u32> for Mtu {
-    type Error = std::io::Error;
+    type Error = io::Error;
 
     fn try_from(val: u32) -> Result<Self, Self::Error> {
         match val {
             16 => Ok(Mtu::Standard),
             72 => Ok(Mtu::Fd),
-            _ => Err(std::io::Error::from(std::io::ErrorKind::InvalidData)),
+            2060 => Ok(Mtu::Xl),
+            _ => Err(io::Error::from(io::ErrorKind::InvalidData)),
         }
     }
 }
@@ -292,6 +301,26 @@ impl CanCtrlModes {
     pub fn clear(&mut self) {
         self.0 = can_ctrlmode::default();
     }
+
+    /// Gets the mask of the modes in the collection.
+    pub fn mask(&self) -> u32 {
+        self.0.mask
+    }
+
+    /// Gets the flags of the modes in the collection that are on.
+    pub fn flags(&self) -> u32 {
+        self.0.flags
+    }
+
+    /// Determines if the mode is in the mask of the collection.
+    pub fn contains(&self, mode: CanCtrlMode) -> bool {
+        self.0.mask & mode.mask() != 0
+    }
+
+    /// Determines if the mode is on in the collection.
+    pub fn is_on(&self, mode: CanCtrlMode) -> bool {
+        self.0.flags & mode.mask() != 0
+    }
 }
 
 impl From<can_ctrlmode> for CanCtrlModes {
@@ -324,6 +353,77 @@ pub struct SetCanParams {
     pub termination: Option<u16>,
 }
 
+// ===== NetemParams =====
+
+/// The faults for a network emulator (netem) queueing discipline to inject
+/// into the frames sent on an interface, used in
+/// [add_netem][CanInterface::add_netem].
+///
+/// The default injects no faults at all. The probabilities are from 0.0,
+/// never, to 1.0, always.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct NetemParams {
+    /// The delay added to each frame
+    pub delay: Duration,
+    /// The random variation of the delay, up to this amount either way
+    pub jitter: Duration,
+    /// The probability of dropping a frame
+    pub loss: f64,
+    /// The probability of sending a frame right away, ahead of the delayed
+    /// frames that were queued before it. This requires a delay.
+    pub reorder: f64,
+    /// The maximum number of frames held in the queue.
+    /// Zero uses the netem default of 1000.
+    pub limit: u32,
+}
+
+impl NetemParams {
+    /// Checks the parameters and converts them into the `TCA_OPTIONS`
+    /// attribute of a netem qdisc.
+    fn to_options(self) -> NlResult<Rtattr<Tca, Buffer>> {
+        let valid = |p: f64| (0.0..=1.0).contains(&p);
+        if !valid(self.loss) || !valid(self.reorder) {
+            return Err(NlError::Msg("Probability must be from 0.0 to 1.0".into()));
+        }
+        if self.reorder > 0.0 && self.delay.is_zero() {
+            return Err(NlError::Msg("Re-ordering frames requires a delay".into()));
+        }
+
+        let nanos = |t: Duration| t.as_nanos().min(i64::MAX as u128) as i64;
+        let ticks = |t: Duration| (nanos(t) >> rt::PSCHED_SHIFT).min(u32::MAX as i64) as u32;
+        let probability = |p: f64| (p * u32::MAX as f64) as u32;
+
+        let qopt = rt::tc_netem_qopt {
+            latency: ticks(self.delay),
+            limit: if self.limit == 0 { 1000 } else { self.limit },
+            loss: probability(self.loss),
+            gap: u32::from(self.reorder > 0.0),
+            duplicate: 0,
+            jitter: ticks(self.jitter),
+        };
+
+        let mut options = Rtattr::new(None, Tca::Options, Buffer::from(as_bytes(&qopt)))?;
+        options.add_nested_attribute(&Rtattr::new(
+            None,
+            TcaNetem::Latency64,
+            nanos(self.delay),
+        )?)?;
+        options.add_nested_attribute(&Rtattr::new(
+            None,
+            TcaNetem::Jitter64,
+            nanos(self.jitter),
+        )?)?;
+        if self.reorder > 0.0 {
+            let reorder = rt::tc_netem_reorder {
+                probability: probability(self.reorder),
+                correlation: 0,
+            };
+            options.add_nested_attribute(&Rtattr::new(None, TcaNetem::Reorder, reorder)?)?;
+        }
+        Ok(options)
+    }
+}
+
 // ===== CanInterface =====
 
 /// SocketCAN Netlink CanInterface
@@ -379,8 +479,17 @@ impl CanInterface {
         )
     }
 
-    /// Sends an info message to the kernel.
-    fn send_info_msg(msg_type: Rtm, info: Ifinfomsg, additional_flags: &[NlmF]) -> NlResult<()> {
+    /// Creates a `Tcmsg` for the root qdisc of this CAN interface from a
+    /// buffer
+    fn tc_msg(&self, buf: RtBuffer<Tca, Buffer>) -> Tcmsg {
+        Tcmsg::new(0, self.if_index as c_int, 0, rt::TC_H_ROOT, 0, buf)
+    }
+
+    /// Sends an info or traffic control message to the kernel.
+    fn send_info_msg<P>(msg_type: Rtm, info: P, additional_flags: &[NlmF]) -> NlResult<()>
+    where
+        P: ToBytes + neli::Size + Debug,
+    {
         let mut nl = Self::open_route_socket()?;
 
         // prepare message
@@ -569,6 +678,7 @@ impl CanInterface {
 
                 if let Ok(payload) = msg_hdr.get_payload() {
                     info.is_up = payload.ifi_flags.contains(&Iff::Up);
+                    info.is_running = payload.ifi_flags.contains(&Iff::Running);
 
                     for attr in payload.rtattrs.iter() {
                         match attr.rta_type {
@@ -598,6 +708,24 @@ impl CanInterface {
         }
     }
 
+    /// Determines whether the interface is up.
+    ///
+    /// This is the administrative state of the interface, as set by
+    /// `bring_up()` and `bring_down()`. Frames can only be sent and received
+    /// on an interface that is up.
+    pub fn is_up(&self) -> Result<bool, NlInfoError> {
+        Ok(self.details()?.is_up)
+    }
+
+    /// Determines whether the interface is running.
+    ///
+    /// This is the operational state of the interface. An interface that
+    /// is up may still not be running, such as if the controller is in the
+    /// bus-off state or the driver has not finished starting it.
+    pub fn is_running(&self) -> Result<bool, NlInfoError> {
+        Ok(self.details()?.is_running)
+    }
+
     /// Set the MTU of this interface.
     ///
     /// PRIVILEGED: This requires root privilege.
@@ -612,6 +740,22 @@ impl CanInterface {
         Self::send_info_msg(Rtm::Newlink, info, &[])
     }
 
+    /// Gets the MTU of this interface.
+    ///
+    /// This indicates the largest type of frame that the interface can
+    /// handle. It is `None` if the MTU is not one of the known CAN sizes.
+    pub fn mtu(&self) -> Result<Option<Mtu>, NlInfoError> {
+        Ok(self.details()?.mtu)
+    }
+
+    /// Determines whether the interface currently supports FD frames.
+    ///
+    /// This is true if the MTU of the interface is large enough to carry
+    /// FD frames, which is the case for FD and XL interfaces.
+    pub fn supports_fd(&self) -> Result<bool, NlInfoError> {
+        Ok(matches!(self.mtu()?, Some(Mtu::Fd) | Some(Mtu::Xl)))
+    }
+
     /// Set a CAN-specific parameter.
     ///
     /// This send a netlink message down to the kernel to set an attribute
@@ -641,7 +785,7 @@ impl CanInterface {
     /// Set a CAN-specific set of parameters.
     ///
     /// This sends a netlink message down to the kernel to set multiple
-    /// attributes in the link info, such as bitrate, control modes, etc. 
+    /// attributes in the link info, such as bitrate, control modes, etc.
     ///
     /// If you have many attributes to set this is preferred to calling
     /// [set_can_params][CanInterface::set_can_param] multiple times, since this only sends a
@@ -693,6 +837,15 @@ impl CanInterface {
     pub fn can_param<P>(&self, param: IflaCan) -> Result<Option<P>, NlInfoError>
     where
         P: for<'a> FromBytes<'a> + Clone,
+    {
+        self.can_param_with(param, |attr| Ok(attr.get_payload_as::<P>()?))
+    }
+
+    /// Queries an individual CAN parameter on the interface, using the
+    /// function to parse the attribute, if it's found.
+    fn can_param_with<P, F>(&self, param: IflaCan, f: F) -> Result<Option<P>, NlInfoError>
+    where
+        F: FnOnce(&Rtattr<IflaCan, Buffer>) -> Result<P, NlInfoError>,
     {
         if let Some(hdr) = self.query_details()? {
             if let Ok(payload) = hdr.get_payload() {
@@ -702,7 +855,7 @@ impl CanInterface {
                             if info.rta_type == IflaInfo::Data {
                                 for attr in info.get_attr_handle::<IflaCan>()?.get_attrs() {
                                     if attr.rta_type == param {
-                                        return Ok(Some(attr.get_payload_as::<P>()?));
+                                        return f(attr).map(Some);
                                     }
                                 }
                             }
@@ -760,6 +913,12 @@ impl CanInterface {
 
     /// Sets the bit timing params for the interface
     ///
+    /// This can either give a bit rate and sample point, for the kernel
+    /// to calculate the segments, or give exact segments, with a time
+    /// quantum and a bit rate of zero, such as from
+    /// `CanBitTiming::from_segments()`, for full control over the sample
+    /// point. The limits for the segments are in `bit_timing_const()`.
+    ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_bit_timing(&self, timing: CanBitTiming) -> NlResult<()> {
@@ -778,6 +937,23 @@ impl CanInterface {
             .map(|clk| clk.freq))
     }
 
+    /// Gets the base clock frequency of the CAN controller, in Hz.
+    ///
+    /// This is the same as `clock()`, for tools that need the value, such
+    /// as to compute bit-timing registers, and would rather treat a missing
+    /// clock as an error. An interface that doesn't report a clock, such as
+    /// a virtual one, returns an error of the kind `Unsupported`.
+    pub fn clock_freq(&self) -> io::Result<u32> {
+        match self.clock() {
+            Ok(Some(freq)) => Ok(freq),
+            Ok(None) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "interface does not report a CAN clock frequency",
+            )),
+            Err(err) => Err(io::Error::new(io::ErrorKind::Other, err)),
+        }
+    }
+
     /// Gets the state of the interface
     pub fn state(&self) -> Result<Option<CanState>, NlInfoError> {
         Ok(self
@@ -785,6 +961,35 @@ impl CanInterface {
             .and_then(|st| CanState::try_from(st).ok()))
     }
 
+    /// Gets the control modes that the controller supports.
+    ///
+    /// The mask of the collection holds the supported modes, and the flags
+    /// hold the modes that are currently on. The kernel refuses to turn on
+    /// a mode that is not supported, with `EOPNOTSUPP`, so this can be
+    /// checked first, like:
+    /// `iface.supported_ctrlmodes()?.map_or(false, |m| m.contains(CanCtrlMode::Fd))`
+    ///
+    /// This is `None` if the kernel doesn't report the supported modes,
+    /// which it does from Linux 5.16, along with the netlink support for
+    /// transmitter delay compensation. It's also `None` for an interface
+    /// that isn't a CAN controller, like a virtual one.
+    pub fn supported_ctrlmodes(&self) -> Result<Option<CanCtrlModes>, NlInfoError> {
+        let supported = self.can_param_with(IflaCan::CtrlModeExt, |attr| {
+            Ok(attr
+                .get_attr_handle::<IflaCanCtrlMode>()?
+                .get_attr_payload_as::<u32>(IflaCanCtrlMode::Supported)
+                .ok())
+        })?;
+        let supported = match supported.flatten() {
+            Some(supported) => supported,
+            None => return Ok(None),
+        };
+        let flags = self
+            .can_param::<can_ctrlmode>(IflaCan::CtrlMode)?
+            .map_or(0, |mode| mode.flags);
+        Ok(Some(CanCtrlModes::new(supported, flags & supported)))
+    }
+
     /// Set the full control mode (bit) collection.
     ///
     /// PRIVILEGED: This requires root privilege.
@@ -861,6 +1066,10 @@ impl CanInterface {
 
     /// Sets the data bit timing params for the interface
     ///
+    /// This is for the data phase of FD frames, and takes the same kinds
+    /// of parameters as `set_bit_timing()`. The limits for the segments
+    /// are in `data_bit_timing_const()`.
+    ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_data_bit_timing(&self, timing: CanBitTiming) -> NlResult<()> {
@@ -896,15 +1105,47 @@ impl CanInterface {
         self.can_param::<CanBitTimingConst>(IflaCan::DataBitTimingConst)
     }
 
+    /// Gets the range of data bit rates that the controller supports, in
+    /// bits/second, as a `(min, max)` pair.
+    ///
+    /// This is calculated from the data bit timing const params and the
+    /// CAN clock of the interface. It's `None` if the interface doesn't
+    /// report them, as is the case for one that doesn't support FD.
+    pub fn data_bitrate_range(&self) -> Result<Option<(u32, u32)>, NlInfoError> {
+        let timing_const = match self.data_bit_timing_const()? {
+            Some(timing_const) => timing_const,
+            None => return Ok(None),
+        };
+        Ok(self
+            .clock()?
+            .and_then(|clock| timing_const.bitrate_range(clock)))
+    }
+
     /// Sets the CANbus termination for the interface
     ///
     /// Not all interfaces support setting a termination.
     /// Termination is in ohms. Your interface most likely only supports
     /// certain values. Common values are 0 and 120.
     ///
+    /// If the interface reports the values that it supports, the
+    /// termination is checked against them, and an error is returned,
+    /// without changing anything, if it's not one of them. An error
+    /// querying the supported values is returned as well.
+    ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_termination(&self, termination: u16) -> NlResult<()> {
+        let supported = self
+            .supported_terminations()
+            .map_err(|err| NlError::Msg(err.to_string()))?;
+        if let Some(supported) = supported {
+            if !supported.contains(&termination) {
+                return Err(NlError::Msg(format!(
+                    "Unsupported termination {} ohms, expected one of {:?}",
+                    termination, supported
+                )));
+            }
+        }
         self.set_can_param(IflaCan::Termination, termination)
     }
 
@@ -912,61 +1153,142 @@ impl CanInterface {
     pub fn termination(&self) -> Result<Option<u16>, NlInfoError> {
         self.can_param::<u16>(IflaCan::Termination)
     }
+
+    /// Gets the termination values, in ohms, that the interface supports.
+    ///
+    /// This returns `None` if the interface doesn't have a software
+    /// controlled termination.
+    pub fn supported_terminations(&self) -> Result<Option<Vec<u16>>, NlInfoError> {
+        self.can_param_with(IflaCan::TerminationConst, |attr| {
+            Ok(parse_u16_array(attr.rta_payload.as_ref()))
+        })
+    }
+
+    /// Attaches a network emulator (netem) queueing discipline to the
+    /// interface, to inject faults into the frames that are sent on it.
+    ///
+    /// This adds delay, jitter, loss, or re-ordering to the outgoing
+    /// frames, which is useful to test how an application copes with a bad
+    /// bus, without any special hardware. It replaces the root qdisc of the
+    /// interface, including any netem that was already attached, so it can
+    /// be called again to change the faults. The frames received from the
+    /// bus are not affected.
+    ///
+    /// This requires the `sch_netem` kernel module.
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn add_netem(&self, params: &NetemParams) -> NlResult<()> {
+        let tc = self.tc_msg({
+            let mut rtattrs = RtBuffer::new();
+            rtattrs.push(Rtattr::new(None, Tca::Kind, "netem")?);
+            rtattrs.push(params.to_options()?);
+            rtattrs
+        });
+        Self::send_info_msg(Rtm::Newqdisc, tc, &[NlmF::Create, NlmF::Replace])
+    }
+
+    /// Removes the network emulator queueing discipline from the
+    /// interface, restoring the default qdisc.
+    ///
+    /// This removes whatever qdisc is at the root of the interface, and
+    /// fails if there is none.
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn remove_netem(&self) -> NlResult<()> {
+        let tc = self.tc_msg(RtBuffer::new());
+        Self::send_info_msg(Rtm::Delqdisc, tc, &[])
+    }
 }
 
-/////////////////////////////////////////////////////////////////////////////
+/// Parses an attribute payload that holds an array of native-endian
+/// `u16` values, such as the supported terminations of an interface.
+fn parse_u16_array(buf: &[u8]) -> Vec<u16> {
+    buf.chunks_exact(size_of::<u16>())
+        .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+        .collect()
+}
 
-#[cfg(feature = "netlink_tests")]
-#[cfg(test)]
-pub mod tests {
-    use super::*;
-    use serial_test::serial;
-    use std::ops::Deref;
-
-    /// RAII-style helper to create and clean-up a specific vcan interface for a single test.
-    /// Using drop here ensures that the interface always gets cleaned up
-    /// (although a restart would also remove it).
-    ///
-    /// Intended for use (ONLY) in tests as follows:
-    /// ```
-    /// #[test]
-    /// fn my_test() {
-    ///     let interface = TemporaryInterface::new("my_test").unwrap();
-    ///     // use the interface..
-    /// }
-    /// ```
-    /// Please note that there is a limit to the length of interface names,
-    /// namely 16 characters on Linux.
-    #[allow(missing_copy_implementations)]
-    #[derive(Debug)]
-    pub struct TemporaryInterface {
-        interface: CanInterface,
-    }
-
-    impl TemporaryInterface {
-        #[allow(unused)]
-        pub fn new(name: &str) -> NlResult<Self> {
-            Ok(Self {
-                interface: CanInterface::create_vcan(name, None)?,
-            })
-        }
+// ===== TemporaryInterface =====
+
+/// RAII-style helper to create and clean-up a virtual CAN interface for a
+/// test.
+///
+/// This creates a new vcan interface with the given name, and deletes it
+/// when it is dropped, so that socket tests can run without any existing
+/// CAN hardware or setup. It dereferences to a [`CanInterface`], and is
+/// created down, so it needs to be brought up before sockets can be bound
+/// to it:
+///
+/// ```no_run
+/// use socketcan::{nl::TemporaryInterface, CanSocket, Socket};
+///
+/// let iface = TemporaryInterface::new("mytest0").unwrap();
+/// iface.bring_up().unwrap();
+/// let sock = CanSocket::open(iface.name()).unwrap();
+/// ```
+///
+/// PRIVILEGED: Creating and deleting interfaces requires the
+/// `CAP_NET_ADMIN` capability, and the `vcan` kernel module.
+///
+/// Please note that there is a limit to the length of interface names,
+/// namely 15 characters on Linux.
+///
+/// This requires the `test_harness` feature.
+#[cfg(feature = "test_harness")]
+#[derive(Debug)]
+pub struct TemporaryInterface {
+    name: String,
+    interface: CanInterface,
+}
+
+#[cfg(feature = "test_harness")]
+impl TemporaryInterface {
+    /// Creates a new vcan interface with the specified name.
+    pub fn new(name: &str) -> NlResult<Self> {
+        Ok(Self {
+            name: name.into(),
+            interface: CanInterface::create_vcan(name, None)?,
+        })
     }
 
-    impl Drop for TemporaryInterface {
-        fn drop(&mut self) {
-            assert!(CanInterface::open_iface(self.interface.if_index)
-                .delete()
-                .is_ok());
+    /// Gets the name of the interface.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(feature = "test_harness")]
+impl Drop for TemporaryInterface {
+    /// Deletes the interface.
+    ///
+    /// As a test helper, this panics if the interface can't be deleted,
+    /// unless the thread is already panicking.
+    fn drop(&mut self) {
+        let res = CanInterface::open_iface(self.interface.if_index).delete();
+        if !std::thread::panicking() {
+            assert!(res.is_ok(), "unable to delete interface {}", self.name);
         }
     }
+}
 
-    impl Deref for TemporaryInterface {
-        type Target = CanInterface;
+#[cfg(feature = "test_harness")]
+impl std::ops::Deref for TemporaryInterface {
+    type Target = CanInterface;
 
-        fn deref(&self) -> &Self::Target {
-            &self.interface
-        }
+    fn deref(&self) -> &Self::Target {
+        &self.interface
     }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "netlink_tests")]
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use serial_test::serial;
 
     #[test]
     #[serial]
@@ -975,9 +1297,70 @@ pub mod tests {
 
         assert!(interface.bring_up().is_ok());
         assert!(interface.details().unwrap().is_up);
+        assert!(interface.is_up().unwrap());
+        assert!(interface.is_running().unwrap());
 
         assert!(interface.bring_down().is_ok());
         assert!(!interface.details().unwrap().is_up);
+        assert!(!interface.is_up().unwrap());
+        assert!(!interface.is_running().unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn data_bitrate_range() {
+        // A virtual interface has no bit timing
+        let interface = TemporaryInterface::new("data_bitrate").unwrap();
+        assert_eq!(None, interface.data_bitrate_range().unwrap());
+    }
+
+    #[test]
+    #[serial]
+    fn supported_ctrlmodes() {
+        // A virtual interface has no CAN attributes
+        let interface = TemporaryInterface::new("ctrlmodes").unwrap();
+        assert!(interface.supported_ctrlmodes().unwrap().is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn netem() {
+        let interface = TemporaryInterface::new("netem").unwrap();
+
+        let params = NetemParams {
+            reorder: 0.5,
+            ..NetemParams::default()
+        };
+        assert!(interface.add_netem(&params).is_err());
+
+        let params = NetemParams {
+            delay: Duration::from_millis(10),
+            jitter: Duration::from_millis(2),
+            loss: 0.1,
+            reorder: 0.25,
+            ..NetemParams::default()
+        };
+        interface.add_netem(&params).unwrap();
+        interface.remove_netem().unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn clock_freq() {
+        let interface = TemporaryInterface::new("clock").unwrap();
+        let err = interface.clock_freq().unwrap_err();
+        assert_eq!(io::ErrorKind::Unsupported, err.kind());
+    }
+
+    #[test]
+    #[serial]
+    fn termination() {
+        // A virtual interface has no software controlled termination
+        let interface = TemporaryInterface::new("term").unwrap();
+        assert!(interface.supported_terminations().unwrap().is_none());
+
+        let buf = [0u16, 120].map(u16::to_ne_bytes).concat();
+        assert_eq!(vec![0, 120], parse_u16_array(&buf));
     }
 
     #[test]
@@ -1001,4 +1384,18 @@ pub mod tests {
         assert!(interface.set_mtu(Mtu::Standard).is_ok());
         assert_eq!(Mtu::Standard, interface.details().unwrap().mtu.unwrap());
     }
+
+    #[test]
+    #[serial]
+    fn supports_fd() {
+        let interface = TemporaryInterface::new("supports_fd").unwrap();
+
+        assert!(interface.set_mtu(Mtu::Fd).is_ok());
+        assert_eq!(Some(Mtu::Fd), interface.mtu().unwrap());
+        assert!(interface.supports_fd().unwrap());
+
+        assert!(interface.set_mtu(Mtu::Standard).is_ok());
+        assert_eq!(Some(Mtu::Standard), interface.mtu().unwrap());
+        assert!(!interface.supports_fd().unwrap());
+    }
 }
@@ -59,6 +59,58 @@ pub struct can_bittiming {
     pub brp: u32,          // Bit-rate prescaler
 }
 
+impl can_bittiming {
+    /// Creates bit-timing parameters from explicit segment lengths.
+    ///
+    /// The time quantum is calculated from the CAN clock frequency of the
+    /// controller, in Hz, and the bit-rate prescaler. The bit rate and
+    /// sample point are left as zero, which tells the kernel to use the
+    /// segments as given, and to calculate those from them. They can be
+    /// read back from the interface once the timing is set.
+    ///
+    /// The clock frequency can be queried with `CanInterface::clock()`.
+    pub fn from_segments(
+        clock_freq: u32,
+        brp: u32,
+        prop_seg: u32,
+        phase_seg1: u32,
+        phase_seg2: u32,
+        sjw: u32,
+    ) -> Self {
+        let tq = (u64::from(brp) * 1_000_000_000 + u64::from(clock_freq) / 2)
+            / u64::from(clock_freq.max(1));
+        Self {
+            tq: tq as u32,
+            prop_seg,
+            phase_seg1,
+            phase_seg2,
+            sjw,
+            brp,
+            ..Self::default()
+        }
+    }
+
+    /// Gets the length of a bit, in time quanta.
+    ///
+    /// This is the total of the sync segment, which is always one time
+    /// quantum, and the other segments.
+    pub fn bit_time(&self) -> u32 {
+        1 + self.prop_seg + self.phase_seg1 + self.phase_seg2
+    }
+
+    /// Calculates the bit rate, in bits/second, that the segments give
+    /// with the specified CAN clock frequency, in Hz.
+    pub fn calc_bitrate(&self, clock_freq: u32) -> u32 {
+        clock_freq / (self.brp * self.bit_time()).max(1)
+    }
+
+    /// Calculates the sample point that the segments give, in tenths of a
+    /// percent.
+    pub fn calc_sample_point(&self) -> u32 {
+        1000 * (1 + self.prop_seg + self.phase_seg1) / self.bit_time()
+    }
+}
+
 /// CAN hardware-dependent bit-timing constant
 /// Missing from libc, from linux/can/netlink.h:
 ///
@@ -99,6 +151,28 @@ impl Size for can_bittiming_const {
     }
 }
 
+impl can_bittiming_const {
+    /// Calculates the range of bit rates, in bits/second, that the
+    /// controller can be set to with the specified CAN clock frequency, in
+    /// Hz, as a `(min, max)` pair.
+    ///
+    /// The fastest rate uses the smallest prescaler and the shortest bit,
+    /// and the slowest uses the largest of each. Not every rate within the
+    /// range can be reached exactly. Returns `None` if the limits can't
+    /// give any rate, such as when they're all zero.
+    pub fn bitrate_range(&self, clock_freq: u32) -> Option<(u32, u32)> {
+        let rate = |brp: u32, tseg1: u32, tseg2: u32| {
+            let bit = u64::from(brp) * (1 + u64::from(tseg1) + u64::from(tseg2));
+            u64::from(clock_freq)
+                .checked_div(bit)
+                .map(|rate| rate as u32)
+        };
+        let min = rate(self.brp_max, self.tseg1_max, self.tseg2_max)?;
+        let max = rate(self.brp_min.max(1), self.tseg1_min, self.tseg2_min)?;
+        Some((min, max))
+    }
+}
+
 /// CAN clock parameters
 ///
 #[repr(C)]
@@ -246,6 +320,86 @@ pub enum IflaCan {
 
 impl RtaType for IflaCan {}
 
+pub const IFLA_CAN_CTRLMODE_UNSPEC: u16 = 0;
+pub const IFLA_CAN_CTRLMODE_SUPPORTED: u16 = 1;
+
+/// CAN netlink extended control mode attributes, nested in
+/// `IFLA_CAN_CTRLMODE_EXT`
+///
+#[neli_enum(serialized_type = "libc::c_ushort")]
+pub enum IflaCanCtrlMode {
+    Unspec = IFLA_CAN_CTRLMODE_UNSPEC,
+    Supported = IFLA_CAN_CTRLMODE_SUPPORTED,
+}
+
+impl RtaType for IflaCanCtrlMode {}
+
+/// The root of the traffic control tree of an interface.
+pub const TC_H_ROOT: u32 = 0xFFFF_FFFF;
+
+/// The number of bits in a nanosecond that are dropped to get the
+/// scheduler ticks used by the older traffic control options.
+pub const PSCHED_SHIFT: u32 = 6;
+
+/// Network emulator (netem) queueing discipline options
+///
+/// This is the fixed part of the `TCA_OPTIONS` attribute for a netem
+/// qdisc, which is followed by the nested `TCA_NETEM_*` attributes.
+/// The latency and jitter are in scheduler ticks, and the probabilities
+/// are scaled so that `u32::MAX` is 100%.
+///
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, FromBytes, ToBytes, Size)]
+pub struct tc_netem_qopt {
+    pub latency: u32,   // Added delay, in ticks
+    pub limit: u32,     // Max number of packets queued
+    pub loss: u32,      // Random packet loss probability
+    pub gap: u32,       // Re-ordering gap, in packets
+    pub duplicate: u32, // Random packet duplication probability
+    pub jitter: u32,    // Random jitter in latency, in ticks
+}
+
+/// Network emulator re-ordering options
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, FromBytes, ToBytes, Size)]
+pub struct tc_netem_reorder {
+    pub probability: u32,
+    pub correlation: u32,
+}
+
+pub const TCA_NETEM_UNSPEC: u16 = 0;
+pub const TCA_NETEM_CORR: u16 = 1;
+pub const TCA_NETEM_DELAY_DIST: u16 = 2;
+pub const TCA_NETEM_REORDER: u16 = 3;
+pub const TCA_NETEM_CORRUPT: u16 = 4;
+pub const TCA_NETEM_LOSS: u16 = 5;
+pub const TCA_NETEM_RATE: u16 = 6;
+pub const TCA_NETEM_ECN: u16 = 7;
+pub const TCA_NETEM_RATE64: u16 = 8;
+pub const TCA_NETEM_PAD: u16 = 9;
+pub const TCA_NETEM_LATENCY64: u16 = 10;
+pub const TCA_NETEM_JITTER64: u16 = 11;
+
+/// Network emulator qdisc attributes, nested in `TCA_OPTIONS`
+///
+#[neli_enum(serialized_type = "libc::c_ushort")]
+pub enum TcaNetem {
+    Unspec = TCA_NETEM_UNSPEC,
+    Corr = TCA_NETEM_CORR,
+    DelayDist = TCA_NETEM_DELAY_DIST,
+    Reorder = TCA_NETEM_REORDER,
+    Corrupt = TCA_NETEM_CORRUPT,
+    Loss = TCA_NETEM_LOSS,
+    Rate = TCA_NETEM_RATE,
+    Ecn = TCA_NETEM_ECN,
+    Rate64 = TCA_NETEM_RATE64,
+    Pad = TCA_NETEM_PAD,
+    Latency64 = TCA_NETEM_LATENCY64,
+    Jitter64 = TCA_NETEM_JITTER64,
+}
+
+impl RtaType for TcaNetem {}
+
 /////////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
@@ -272,4 +426,41 @@ pub mod tests {
             as_bytes(&timing)
         );
     }
+
+    #[test]
+    fn test_from_segments() {
+        // 500 kbps with an 80 MHz clock: 10 tq/bit at 5 MHz, 80% sample point
+        let timing = can_bittiming::from_segments(80_000_000, 16, 3, 4, 2, 1);
+        assert_eq!(200, timing.tq);
+        assert_eq!(0, timing.bitrate);
+        assert_eq!(0, timing.sample_point);
+        assert_eq!(10, timing.bit_time());
+        assert_eq!(500_000, timing.calc_bitrate(80_000_000));
+        assert_eq!(800, timing.calc_sample_point());
+    }
+
+    #[test]
+    fn test_bitrate_range() {
+        // Data phase limits like those of a typical FD controller
+        let timing_const = can_bittiming_const {
+            tseg1_min: 1,
+            tseg1_max: 32,
+            tseg2_min: 1,
+            tseg2_max: 16,
+            sjw_max: 16,
+            brp_min: 1,
+            brp_max: 32,
+            brp_inc: 1,
+            ..can_bittiming_const::default()
+        };
+        assert_eq!(
+            Some((25_510, 13_333_333)),
+            timing_const.bitrate_range(40_000_000)
+        );
+
+        assert_eq!(
+            None,
+            can_bittiming_const::default().bitrate_range(40_000_000)
+        );
+    }
 }
@@ -0,0 +1,204 @@
+// socketcan/src/replay.rs
+//
+// Implements replaying recorded CAN frames at their original timing.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Replaying recorded frames at their original timing.
+
+use crate::{frame::AsPtr, IoError, IoErrorKind, IoResult, Socket, WriteFramesError};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Writes timestamped frames to a socket, reproducing their original timing.
+///
+/// This is meant for replaying a recorded capture, such as a candump log,
+/// at the cadence in which the frames were originally received. Each frame
+/// is given with a timestamp relative to any fixed point (like the start of
+/// the capture), and the writer sleeps between writes to reproduce the gaps
+/// between consecutive timestamps.
+///
+/// Timestamps that go backwards are clamped, so that the frame is sent
+/// immediately after the previous one. The replay can be sped up or slowed
+/// down with a speed multiplier, and long silences in the capture can be
+/// cut short by capping the maximum gap between frames.
+///
+/// ```no_run
+/// use socketcan::{dump::Reader, CanFdSocket, ReplayWriter, Socket};
+/// use std::time::Duration;
+///
+/// let sock = CanFdSocket::open("vcan0").unwrap();
+/// let mut rdr = Reader::from_file("candump.log").unwrap();
+///
+/// let mut replay = ReplayWriter::new(&sock);
+/// replay.set_max_gap(Some(Duration::from_secs(1)));
+///
+/// for rec in rdr.records() {
+///     let (t_us, frame) = rec.unwrap();
+///     replay.write_frame(Duration::from_micros(t_us), &frame).unwrap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ReplayWriter<'a, S> {
+    sock: &'a S,
+    speed: f64,
+    max_gap: Option<Duration>,
+    last: Option<(Duration, Instant)>,
+}
+
+impl<'a, S: Socket> ReplayWriter<'a, S> {
+    /// Creates a writer to replay frames on the socket at their original
+    /// speed.
+    pub fn new(sock: &'a S) -> Self {
+        Self {
+            sock,
+            speed: 1.0,
+            max_gap: None,
+            last: None,
+        }
+    }
+
+    /// Gets the speed multiplier for the replay.
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Sets the speed multiplier for the replay.
+    ///
+    /// A value of 2.0 replays the frames twice as fast as they were
+    /// recorded, and 0.5 replays them at half speed. A value that is not
+    /// positive disables the delays altogether, so that the frames are
+    /// written as fast as possible.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed;
+    }
+
+    /// Gets the maximum gap between consecutive frames, if any.
+    pub fn max_gap(&self) -> Option<Duration> {
+        self.max_gap
+    }
+
+    /// Sets the maximum gap between consecutive frames.
+    ///
+    /// This is applied after the speed multiplier. A value of `None` allows
+    /// any gap.
+    pub fn set_max_gap(&mut self, max_gap: Option<Duration>) {
+        self.max_gap = max_gap;
+    }
+
+    /// Clears the timing reference, so that the next frame is written
+    /// immediately, and subsequent ones are timed relative to it.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// Writes a frame with the specified timestamp to the socket.
+    ///
+    /// This sleeps until the time that the frame is due, relative to the
+    /// previous frame, then sends it with `write_frame_insist`. The first
+    /// frame is sent immediately. If the frame is due too far in the future
+    /// to represent, which a tiny speed multiplier can cause, an error of
+    /// the kind `InvalidInput` is returned and the frame is not sent.
+    pub fn write_frame<F>(&mut self, ts: Duration, frame: &F) -> IoResult<()>
+    where
+        F: Into<S::FrameType> + AsPtr,
+    {
+        let (ts, due) = match self.last {
+            Some((last_ts, last_due)) => {
+                let gap = replay_gap(last_ts, ts, self.speed, self.max_gap);
+                let due = last_due.checked_add(gap).ok_or_else(|| {
+                    IoError::new(IoErrorKind::InvalidInput, "replay gap out of range")
+                })?;
+                (ts.max(last_ts), due)
+            }
+            None => (ts, Instant::now()),
+        };
+
+        let delay = due.saturating_duration_since(Instant::now());
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+
+        self.last = Some((ts, due));
+        self.sock.write_frame_insist(frame)
+    }
+
+    /// Writes a sequence of timestamped frames to the socket.
+    ///
+    /// This stops on the first error, reporting the index of the frame that
+    /// failed. On success it returns the number of frames that were sent.
+    pub fn write_all_frames<I, F>(&mut self, frames: I) -> Result<usize, WriteFramesError>
+    where
+        I: IntoIterator<Item = (Duration, F)>,
+        F: Into<S::FrameType> + AsPtr,
+    {
+        let mut n = 0;
+        for (ts, frame) in frames {
+            self.write_frame(ts, &frame)
+                .map_err(|error| WriteFramesError { index: n, error })?;
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+/// Gets the delay between two frames in a replay.
+///
+/// A tiny speed can scale the gap past the largest `Duration`, in which
+/// case it saturates, before being capped by the maximum gap.
+fn replay_gap(last_ts: Duration, ts: Duration, speed: f64, max_gap: Option<Duration>) -> Duration {
+    let gap = match ts.saturating_sub(last_ts) {
+        gap if speed > 0.0 => {
+            Duration::try_from_secs_f64(gap.as_secs_f64() / speed).unwrap_or(Duration::MAX)
+        }
+        _ => Duration::ZERO,
+    };
+    match max_gap {
+        Some(max_gap) => gap.min(max_gap),
+        None => gap,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{frame::can_frame_default, socket::tests::socket_pair, CanFrame};
+
+    #[test]
+    fn test_replay_gap() {
+        const MS: Duration = Duration::from_millis(1);
+
+        assert_eq!(10 * MS, replay_gap(10 * MS, 20 * MS, 1.0, None));
+        assert_eq!(5 * MS, replay_gap(10 * MS, 20 * MS, 2.0, None));
+        assert_eq!(20 * MS, replay_gap(10 * MS, 20 * MS, 0.5, None));
+
+        // Backwards timestamps and disabled delays
+        assert_eq!(Duration::ZERO, replay_gap(20 * MS, 10 * MS, 1.0, None));
+        assert_eq!(Duration::ZERO, replay_gap(10 * MS, 20 * MS, 0.0, None));
+        assert_eq!(Duration::ZERO, replay_gap(10 * MS, 20 * MS, f64::NAN, None));
+
+        // Capped gap
+        assert_eq!(3 * MS, replay_gap(10 * MS, 20 * MS, 1.0, Some(3 * MS)));
+        assert_eq!(5 * MS, replay_gap(10 * MS, 20 * MS, 2.0, Some(8 * MS)));
+
+        // A gap scaled out of range saturates
+        let speed = f64::MIN_POSITIVE;
+        assert_eq!(Duration::MAX, replay_gap(10 * MS, 20 * MS, speed, None));
+        assert_eq!(3 * MS, replay_gap(10 * MS, 20 * MS, speed, Some(3 * MS)));
+
+        let (sock, _peer) = socket_pair();
+        let frame = CanFrame::from(can_frame_default());
+        let mut replay = ReplayWriter::new(&sock);
+        replay.set_speed(speed);
+        replay.write_frame(10 * MS, &frame).unwrap();
+        let err = replay.write_frame(20 * MS, &frame).unwrap_err();
+        assert_eq!(IoErrorKind::InvalidInput, err.kind());
+    }
+}
@@ -13,21 +13,29 @@
 
 use crate::{
     as_bytes, as_bytes_mut,
-    frame::{can_frame_default, canfd_frame_default, AsPtr, CAN_ERR_MASK},
-    CanAddr, CanAnyFrame, CanFdFrame, CanFrame, CanRawFrame, IoError, IoErrorKind, IoResult,
+    frame::{can_frame_default, canfd_frame_default, AsPtr, CAN_ERR_MASK, CAN_RTR_FLAG},
+    CanAddr, CanAnyFrame, CanError, CanFrame, CanRawFrame, ErrClass, ErrorCounter, Frame, IoError,
+    IoErrorKind, IoResult, WriteFramesError,
 };
+use bitflags::bitflags;
 use libc::{canid_t, socklen_t, AF_CAN, EINPROGRESS};
 use socket2::SockAddr;
 use std::{
     fmt,
     io::{Read, Write},
-    mem::{size_of, size_of_val},
+    mem::{self, align_of, size_of, size_of_val},
     os::{
-        raw::{c_int, c_void},
-        unix::io::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, OwnedFd, RawFd},
+        raw::{c_int, c_uint, c_void},
+        unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
     },
+    path::Path,
     ptr,
-    time::Duration,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
 
 pub use libc::{
@@ -35,6 +43,317 @@ pub use libc::{
     CAN_RAW_JOIN_FILTERS, CAN_RAW_LOOPBACK, CAN_RAW_RECV_OWN_MSGS, SOL_CAN_BASE, SOL_CAN_RAW,
 };
 
+/// Raw socket option to enable the reception of CAN XL frames.
+///
+/// This is defined here, rather than re-exported, since it is missing
+/// from older versions of libc.
+pub const CAN_RAW_XL_FRAMES: c_int = 7;
+
+/// The ioctl to get the receive time of the last packet read from a
+/// socket, as a `timespec`. This is missing from libc.
+const SIOCGSTAMPNS: libc::c_ulong = 0x8907;
+
+/// The maximum number of filters that can be set on a raw CAN socket.
+///
+/// This is fixed when the kernel is compiled, and can not be raised with
+/// a `sysctl` or by a privileged process.
+pub const CAN_RAW_FILTER_MAX: usize = 512;
+
+bitflags! {
+    /// Flags that the kernel sets on a received frame.
+    ///
+    /// These report where a frame came from, when loopback is enabled on
+    /// the sockets, and are returned with a frame by
+    /// [`Socket::read_frame_with_flags()`].
+    pub struct RecvFlags: c_int {
+        /// The frame was sent by a socket on this host, and came back
+        /// through loopback, rather than from the bus.
+        const LOOPBACK = libc::MSG_DONTROUTE;
+        /// The frame was sent by this socket, and was received back because
+        /// `set_recv_own_msgs()` is enabled. This is a confirmation that
+        /// the frame was sent.
+        const OWN = libc::MSG_CONFIRM;
+        /// The frame was larger than the buffer that it was read into, so
+        /// the end of it was discarded. See [`Socket::read_frame_bytes()`].
+        const TRUNCATED = libc::MSG_TRUNC;
+        /// The control messages of the frame were larger than the buffer
+        /// that they were read into, so some were discarded. See
+        /// [`Socket::read_frame_with_ancillary()`].
+        const CONTROL_TRUNCATED = libc::MSG_CTRUNC;
+    }
+}
+
+/// The times that a frame was transmitted, as reported by the kernel.
+///
+/// These are read back from the socket with
+/// [`Socket::read_tx_timestamp()`], after transmit timestamps are enabled
+/// with [`SocketOptions::set_tx_timestamping()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxTimestamp {
+    /// The time, on the system clock, that the driver passed the frame to
+    /// the hardware.
+    pub software: Option<SystemTime>,
+    /// The time that the frame was sent, reported by the hardware. This is
+    /// on the clock of the controller, which might not be synchronized with
+    /// the system clock.
+    pub hardware: Option<SystemTime>,
+}
+
+/// The sources of the receive timestamps to report for a socket.
+///
+/// This is set on a socket with [`SocketOptions::set_timestamp_config()`],
+/// and the times are read along with the frames by
+/// [`Socket::read_frame_with_timestamps()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampConfig {
+    /// No receive timestamps
+    Disabled,
+    /// The time on the system clock that the kernel received the frame
+    Software,
+    /// The time that the controller received the frame, if the hardware
+    /// and driver support it
+    Hardware,
+    /// Both the software and hardware times
+    Both,
+}
+
+impl TimestampConfig {
+    /// Gets the `SO_TIMESTAMPING` receive flags for the configuration.
+    fn rx_flags(&self) -> c_uint {
+        use TimestampConfig::*;
+        match self {
+            Disabled => 0,
+            Software => libc::SOF_TIMESTAMPING_RX_SOFTWARE,
+            Hardware => libc::SOF_TIMESTAMPING_RX_HARDWARE,
+            Both => libc::SOF_TIMESTAMPING_RX_SOFTWARE | libc::SOF_TIMESTAMPING_RX_HARDWARE,
+        }
+    }
+}
+
+/// The times that a frame was received, as reported by the kernel.
+///
+/// Each time is only available if its source was selected for the socket,
+/// and supported by the driver.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameTimestamps {
+    /// The time, on the system clock, that the kernel received the frame.
+    pub software: Option<SystemTime>,
+    /// The time that the frame was received, reported by the hardware.
+    /// This is on the clock of the controller, which might not be
+    /// synchronized with the system clock.
+    pub hardware: Option<SystemTime>,
+}
+
+/// The ancillary data received along with a frame.
+///
+/// This holds the raw control messages that the kernel attached to the
+/// frame, which borrow the buffer that they were read into by
+/// [`Socket::read_frame_with_ancillary()`], along with the flags and the
+/// source interface of the frame. The messages can be iterated over with
+/// `messages()` to parse them directly, or the common ones can be decoded
+/// with the typed accessors.
+#[derive(Debug, Clone, Copy)]
+pub struct AncillaryData<'a> {
+    control: &'a [u8],
+    flags: RecvFlags,
+    ifindex: Option<u32>,
+}
+
+impl<'a> AncillaryData<'a> {
+    /// Gets the raw bytes of all the control messages.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.control
+    }
+
+    /// Gets an iterator over the control messages.
+    pub fn messages(&self) -> AncillaryMessages<'a> {
+        AncillaryMessages(self.control)
+    }
+
+    /// Gets the flags that the kernel set on the received frame.
+    pub fn flags(&self) -> RecvFlags {
+        self.flags
+    }
+
+    /// Determines if some of the control messages were discarded, because
+    /// the buffer was too small to hold them.
+    pub fn is_truncated(&self) -> bool {
+        self.flags.contains(RecvFlags::CONTROL_TRUNCATED)
+    }
+
+    /// Gets the index of the interface that the frame was received on.
+    ///
+    /// This is mostly of use with a socket bound to all interfaces. It's
+    /// `None` if the kernel didn't report a CAN source address.
+    pub fn ifindex(&self) -> Option<u32> {
+        self.ifindex
+    }
+
+    /// Gets the receive timestamps of the frame.
+    ///
+    /// These are only present when enabled on the socket, such as with
+    /// `set_timestamp()` or `set_timestamp_config()`.
+    pub fn timestamps(&self) -> FrameTimestamps {
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        hdr.msg_control = self.control.as_ptr() as *mut c_void;
+        hdr.msg_controllen = self.control.len() as _;
+        raw_cmsg_frame_timestamps(&hdr)
+    }
+
+    /// Gets the total number of frames that the socket has dropped, because
+    /// its receive queue was full.
+    ///
+    /// This is only reported once enabled with `set_rxq_overflow()`.
+    pub fn dropped(&self) -> Option<u32> {
+        self.messages()
+            .find(|msg| msg.level == libc::SOL_SOCKET && msg.ty == libc::SO_RXQ_OVFL)
+            .and_then(|msg| msg.data.get(..size_of::<u32>()))
+            .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+/// A single raw control message in the ancillary data of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AncillaryMessage<'a> {
+    /// The protocol level of the message, like `SOL_SOCKET`
+    pub level: c_int,
+    /// The protocol-specific type of the message, like `SCM_TIMESTAMPNS`
+    pub ty: c_int,
+    /// The data of the message
+    pub data: &'a [u8],
+}
+
+/// An iterator over the control messages in the ancillary data of a frame.
+///
+/// This is created with [`AncillaryData::messages()`].
+#[derive(Debug, Clone)]
+pub struct AncillaryMessages<'a>(&'a [u8]);
+
+impl<'a> Iterator for AncillaryMessages<'a> {
+    type Item = AncillaryMessage<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.len() < size_of::<libc::cmsghdr>() {
+            return None;
+        }
+        let cmsg: libc::cmsghdr =
+            unsafe { ptr::read_unaligned(self.0.as_ptr() as *const libc::cmsghdr) };
+        let hdr_len = unsafe { libc::CMSG_LEN(0) } as usize;
+        let len = cmsg.cmsg_len as usize;
+        if len < hdr_len || len > self.0.len() {
+            self.0 = &[];
+            return None;
+        }
+
+        let data = &self.0[hdr_len..len];
+        let space = unsafe { libc::CMSG_SPACE(data.len() as u32) } as usize;
+        self.0 = self.0.get(space..).unwrap_or_default();
+        Some(AncillaryMessage {
+            level: cmsg.cmsg_level,
+            ty: cmsg.cmsg_type,
+            data,
+        })
+    }
+}
+
+/// Options for how a socket is created when it's opened.
+///
+/// This is a builder for the flags given to `socket(2)`, for use with
+/// [`Socket::open_with()`] and [`Socket::open_addr_with()`]. By default,
+/// a socket is opened in blocking mode, with the close-on-exec flag set, so
+/// that it's not inherited by child processes. This is the same default
+/// as the standard library uses for its file descriptors, and is what the
+/// plain `open()` functions use.
+///
+/// There is no option for address reuse, like `SO_REUSEADDR`, since a raw
+/// CAN socket doesn't claim its address exclusively. On every kernel with
+/// SocketCAN support, any number of sockets, in any number of processes,
+/// can be bound to the same interface at once, so a restarted service can
+/// always bind again right away, even while the sockets of the old process
+/// are still being closed. When opening fails just after a restart, it's
+/// because the interface itself isn't there yet, such as a USB adapter that
+/// is still being enumerated, which shows up as an `ENODEV` error, and can
+/// be retried. An interface that exists, but is down, can still be opened,
+/// and a read from the socket then reports an `ENETDOWN` error.
+///
+/// ```no_run
+/// use socketcan::{CanSocket, OpenOptions, Socket};
+///
+/// // Let the socket be inherited by a child process
+/// let opts = OpenOptions::new().cloexec(false);
+/// let sock = CanSocket::open_with("can0", &opts).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenOptions {
+    cloexec: bool,
+    nonblocking: bool,
+}
+
+impl OpenOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self {
+            cloexec: true,
+            nonblocking: false,
+        }
+    }
+
+    /// Sets whether the socket is closed when the process calls `exec(2)`.
+    ///
+    /// This is on by default. Turn it off to pass the socket to another
+    /// program that the process runs.
+    pub fn cloexec(mut self, on: bool) -> Self {
+        self.cloexec = on;
+        self
+    }
+
+    /// Sets whether the socket is opened in nonblocking mode.
+    pub fn nonblocking(mut self, on: bool) -> Self {
+        self.nonblocking = on;
+        self
+    }
+
+    /// Gets the socket type, with the flags for these options.
+    fn socket_type(&self) -> socket2::Type {
+        let mut ty = socket2::Type::RAW;
+        if self.cloexec {
+            ty = ty.cloexec();
+        }
+        if self.nonblocking {
+            ty = ty.nonblocking();
+        }
+        ty
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How `write_frame_insist_with()` waits between attempts to send a frame
+/// when the transmit queue is full.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RetryPolicy {
+    /// Retry right away, in a busy loop.
+    ///
+    /// This has the lowest latency, but keeps a CPU busy for as long as
+    /// the bus is saturated.
+    #[default]
+    Spin,
+    /// Wait with `poll(2)` until the socket is writable.
+    Poll,
+    /// Sleep between attempts, starting with the `initial` delay, and
+    /// doubling it after each failed attempt, up to `max`.
+    Backoff {
+        /// The delay after the first failed attempt
+        initial: Duration,
+        /// The longest delay between attempts
+        max: Duration,
+    },
+}
+
 /// Check an error return value for timeouts.
 ///
 /// Due to the fact that timeouts are reported as errors, calling `read_frame`
@@ -75,14 +394,388 @@ impl<E: fmt::Debug> ShouldRetry for IoResult<E> {
 
 // ===== Private local helper functions =====
 
-/// Tries to open the CAN socket by the interface number.
-fn raw_open_socket(addr: &CanAddr) -> IoResult<socket2::Socket> {
+/// Tries to open the CAN socket by the interface number, with the
+/// specified options for creating it.
+fn raw_open_socket(addr: &CanAddr, opts: &OpenOptions) -> IoResult<socket2::Socket> {
+    let sock = raw_create_socket(opts)?;
+    raw_bind_socket(&sock, addr)?;
+    Ok(sock)
+}
+
+/// Creates a raw CAN socket, without binding it to an interface.
+fn raw_create_socket(opts: &OpenOptions) -> IoResult<socket2::Socket> {
     let af_can = socket2::Domain::from(AF_CAN);
     let can_raw = socket2::Protocol::from(CAN_RAW);
+    socket2::Socket::new_raw(af_can, opts.socket_type(), Some(can_raw))
+}
 
-    let sock = socket2::Socket::new_raw(af_can, socket2::Type::RAW, Some(can_raw))?;
-    sock.bind(&SockAddr::from(*addr))?;
-    Ok(sock)
+/// Binds a CAN socket to the interface with the specified address.
+fn raw_bind_socket(sock: &socket2::Socket, addr: &CanAddr) -> IoResult<()> {
+    sock.bind(&SockAddr::from(*addr))
+}
+
+/// Converts an `Instant` into a time in nanoseconds on the specified
+/// system clock, such as for use with `SO_TXTIME`.
+///
+/// Instants in the past are converted to the current time on the clock.
+fn raw_clock_time_ns(clockid: libc::clockid_t, when: Instant) -> IoResult<u64> {
+    let now = Instant::now();
+    let mut ts: libc::timespec = unsafe { mem::zeroed() };
+    if unsafe { libc::clock_gettime(clockid, &mut ts) } < 0 {
+        return Err(IoError::last_os_error());
+    }
+    let clock_now = Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32);
+    let t = clock_now + when.saturating_duration_since(now);
+    Ok(t.as_nanos() as u64)
+}
+
+/// Gets the receive timestamp from the control messages of a received
+/// message, if there is one.
+///
+/// This accepts either the microsecond `SCM_TIMESTAMP` or the nanosecond
+/// `SCM_TIMESTAMPNS` messages.
+fn raw_cmsg_timestamp(hdr: &libc::msghdr) -> Option<SystemTime> {
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(hdr) };
+    while !cmsg.is_null() {
+        let (level, ty) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+        let dur = match (level, ty) {
+            (libc::SOL_SOCKET, libc::SCM_TIMESTAMP) => {
+                let tv: libc::timeval =
+                    unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::timeval) };
+                Some(Duration::new(tv.tv_sec as u64, 1000 * tv.tv_usec as u32))
+            }
+            (libc::SOL_SOCKET, libc::SCM_TIMESTAMPNS) => {
+                let ts: libc::timespec =
+                    unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::timespec) };
+                Some(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+            }
+            _ => None,
+        };
+        if let Some(dur) = dur {
+            return Some(SystemTime::UNIX_EPOCH + dur);
+        }
+        cmsg = unsafe { libc::CMSG_NXTHDR(hdr, cmsg) };
+    }
+    None
+}
+
+/// Gets the name of a network interface from its path in sysfs, like
+/// `/sys/class/net/can0`.
+fn iface_name_from_path(path: &Path) -> IoResult<&str> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| {
+            IoError::new(
+                IoErrorKind::InvalidInput,
+                "path does not name a network interface",
+            )
+        })
+}
+
+/// Gets the software and hardware times from an `SCM_TIMESTAMPING`
+/// control message of a received message, if there is one.
+fn raw_cmsg_timestamping(hdr: &libc::msghdr) -> Option<(Option<SystemTime>, Option<SystemTime>)> {
+    let to_time = |ts: &libc::timespec| match (ts.tv_sec, ts.tv_nsec) {
+        (0, 0) => None,
+        (sec, nsec) => Some(SystemTime::UNIX_EPOCH + Duration::new(sec as u64, nsec as u32)),
+    };
+
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(hdr) };
+    while !cmsg.is_null() {
+        let (level, ty) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+        if level == libc::SOL_SOCKET && ty == libc::SCM_TIMESTAMPING {
+            // The kernel sends the software time in the first slot and the
+            // raw hardware time in the third; the second is unused.
+            let ts: [libc::timespec; 3] =
+                unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const [libc::timespec; 3]) };
+            return Some((to_time(&ts[0]), to_time(&ts[2])));
+        }
+        cmsg = unsafe { libc::CMSG_NXTHDR(hdr, cmsg) };
+    }
+    None
+}
+
+/// Gets the transmit timestamps from the control messages of a message
+/// that was read from the error queue of a socket, if there are any.
+fn raw_cmsg_tx_timestamp(hdr: &libc::msghdr) -> Option<TxTimestamp> {
+    raw_cmsg_timestamping(hdr).map(|(software, hardware)| TxTimestamp { software, hardware })
+}
+
+/// Gets the receive timestamps from the control messages of a received
+/// frame.
+///
+/// This takes the times from `SCM_TIMESTAMPING`, falling back to the older
+/// `SCM_TIMESTAMP` or `SCM_TIMESTAMPNS` messages for the software time.
+fn raw_cmsg_frame_timestamps(hdr: &libc::msghdr) -> FrameTimestamps {
+    let (software, hardware) = raw_cmsg_timestamping(hdr).unwrap_or_default();
+    FrameTimestamps {
+        software: software.or_else(|| raw_cmsg_timestamp(hdr)),
+        hardware,
+    }
+}
+
+/// Combines the receive and transmit `SO_TIMESTAMPING` flags, adding the
+/// flags that are needed to report the selected times.
+fn timestamping_flags(rx: c_uint, tx: c_uint) -> c_uint {
+    let mut flags = rx | tx;
+    if flags & (libc::SOF_TIMESTAMPING_RX_SOFTWARE | libc::SOF_TIMESTAMPING_TX_SOFTWARE) != 0 {
+        flags |= libc::SOF_TIMESTAMPING_SOFTWARE;
+    }
+    if flags & (libc::SOF_TIMESTAMPING_RX_HARDWARE | libc::SOF_TIMESTAMPING_TX_HARDWARE) != 0 {
+        flags |= libc::SOF_TIMESTAMPING_RAW_HARDWARE;
+    }
+    flags
+}
+
+/// The `SO_TIMESTAMPING` flags that select receive timestamps.
+const SOF_TIMESTAMPING_RX_FLAGS: c_uint =
+    libc::SOF_TIMESTAMPING_RX_SOFTWARE | libc::SOF_TIMESTAMPING_RX_HARDWARE;
+
+/// The `SO_TIMESTAMPING` flags that select transmit timestamps.
+const SOF_TIMESTAMPING_TX_FLAGS: c_uint = libc::SOF_TIMESTAMPING_TX_SOFTWARE
+    | libc::SOF_TIMESTAMPING_TX_HARDWARE
+    | libc::SOF_TIMESTAMPING_OPT_TSONLY;
+
+/// Gets the MTU of the named network interface with the `SIOCGIFMTU`
+/// ioctl, using an open socket.
+fn raw_iface_mtu(fd: RawFd, ifname: &str) -> IoResult<usize> {
+    let mut ifr: libc::ifreq = unsafe { mem::zeroed() };
+    let name = ifname.as_bytes();
+    if name.len() >= ifr.ifr_name.len() {
+        return Err(IoErrorKind::InvalidInput.into());
+    }
+    for (dst, src) in ifr.ifr_name.iter_mut().zip(name) {
+        *dst = *src as _;
+    }
+    if unsafe { libc::ioctl(fd, libc::SIOCGIFMTU as _, &mut ifr) } < 0 {
+        return Err(IoError::last_os_error());
+    }
+    Ok(unsafe { ifr.ifr_ifru.ifru_mtu } as usize)
+}
+
+/// Checks that the named interface can carry FD frames, by the size of
+/// its MTU.
+///
+/// Returns an `Unsupported` error, giving the MTU, if the interface can
+/// only carry classic frames.
+fn raw_check_fd_mtu(fd: RawFd, ifname: &str) -> IoResult<()> {
+    match raw_iface_mtu(fd, ifname)? {
+        mtu if mtu >= CANFD_MTU => Ok(()),
+        mtu => Err(IoError::new(
+            IoErrorKind::Unsupported,
+            format!("interface doesn't support FD frames (MTU {})", mtu),
+        )),
+    }
+}
+
+/// Adds context to an error from setting the filters on a socket.
+///
+/// The kernel rejects too many filters with a bare `EINVAL`, so this gives
+/// it a description of the limit.
+fn filter_error(err: IoError, n: usize) -> IoError {
+    match err.raw_os_error() {
+        Some(libc::EINVAL) if n > CAN_RAW_FILTER_MAX => IoError::new(
+            IoErrorKind::InvalidInput,
+            format!(
+                "too many CAN filters: {} given, but the kernel allows at most {} per socket",
+                n, CAN_RAW_FILTER_MAX
+            ),
+        ),
+        _ => err,
+    }
+}
+
+/// Adds the interface name, and a hint for the common cases, to an error
+/// from opening a socket on a named interface.
+///
+/// The kind of the error is kept, and the message includes the original
+/// error, with its OS error number, so it can still be diagnosed.
+pub(crate) fn open_error(err: IoError, ifname: &str) -> IoError {
+    let hint = match err.raw_os_error() {
+        Some(libc::ENODEV) => " (interface not found or not up)",
+        Some(libc::ENETDOWN) => " (interface not up)",
+        _ => "",
+    };
+    IoError::new(
+        err.kind(),
+        format!("can't open CAN interface '{}': {}{}", ifname, err, hint),
+    )
+}
+
+/// Reads an integer socket option.
+fn raw_get_socket_option_int(fd: RawFd, level: c_int, name: c_int) -> IoResult<c_int> {
+    let mut val: c_int = 0;
+    let mut len = size_of::<c_int>() as socklen_t;
+    let ret =
+        unsafe { libc::getsockopt(fd, level, name, &mut val as *mut _ as *mut c_void, &mut len) };
+    match ret {
+        0 => Ok(val),
+        _ => Err(IoError::last_os_error()),
+    }
+}
+
+/// Receives a single message from the socket into the buffer, using the
+/// specified `recv(2)` flags, like `MSG_PEEK`.
+///
+/// Returns the size of the message, along with the flags that the kernel
+/// set on the received message.
+fn raw_recv(fd: RawFd, buf: &mut [u8], flags: c_int) -> IoResult<(usize, RecvFlags)> {
+    raw_recv_timestamped(fd, buf, flags, None)
+}
+
+/// Receives a single message from the socket, like `raw_recv()`, and, if
+/// `ts` is given, fills it with any receive timestamps of the message.
+fn raw_recv_timestamped(
+    fd: RawFd,
+    buf: &mut [u8],
+    flags: c_int,
+    ts: Option<&mut FrameTimestamps>,
+) -> IoResult<(usize, RecvFlags)> {
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut c_void,
+        iov_len: buf.len(),
+    };
+    let mut control = [0u64; 16];
+    let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+    hdr.msg_iov = &mut iov;
+    hdr.msg_iovlen = 1;
+    if ts.is_some() {
+        hdr.msg_control = control.as_mut_ptr() as *mut c_void;
+        hdr.msg_controllen = size_of_val(&control) as _;
+    }
+
+    let ret = unsafe { libc::recvmsg(fd, &mut hdr, flags) };
+    if ret < 0 {
+        return Err(IoError::last_os_error());
+    }
+    if let Some(ts) = ts {
+        *ts = raw_cmsg_frame_timestamps(&hdr);
+    }
+    Ok((ret as usize, RecvFlags::from_bits_truncate(hdr.msg_flags)))
+}
+
+/// Receives a single message from the socket, like `raw_recv()`, along
+/// with its control messages, which are read into `cmsg_buf`.
+///
+/// The control messages are read into the part of the buffer that is
+/// aligned for a `cmsghdr`, which the returned data borrows.
+fn raw_recv_ancillary<'a>(
+    fd: RawFd,
+    buf: &mut [u8],
+    flags: c_int,
+    cmsg_buf: &'a mut [u8],
+) -> IoResult<(usize, AncillaryData<'a>)> {
+    let off = cmsg_buf
+        .as_ptr()
+        .align_offset(align_of::<libc::cmsghdr>())
+        .min(cmsg_buf.len());
+    let control = &mut cmsg_buf[off..];
+
+    let mut iov = libc::iovec {
+        iov_base: buf.as_mut_ptr() as *mut c_void,
+        iov_len: buf.len(),
+    };
+    let mut addr: libc::sockaddr_can = unsafe { mem::zeroed() };
+    let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+    hdr.msg_name = &mut addr as *mut _ as *mut c_void;
+    hdr.msg_namelen = size_of_val(&addr) as _;
+    hdr.msg_iov = &mut iov;
+    hdr.msg_iovlen = 1;
+    if !control.is_empty() {
+        hdr.msg_control = control.as_mut_ptr() as *mut c_void;
+        hdr.msg_controllen = control.len() as _;
+    }
+
+    let ret = unsafe { libc::recvmsg(fd, &mut hdr, flags) };
+    if ret < 0 {
+        return Err(IoError::last_os_error());
+    }
+
+    let ifindex = match addr.can_family as c_int {
+        AF_CAN if hdr.msg_namelen as usize >= size_of_val(&addr) => Some(addr.can_ifindex as u32),
+        _ => None,
+    };
+    let controllen = (hdr.msg_controllen as usize).min(control.len());
+    let data = AncillaryData {
+        control: &control[..controllen],
+        flags: RecvFlags::from_bits_truncate(hdr.msg_flags),
+        ifindex,
+    };
+    Ok((ret as usize, data))
+}
+
+/// Reads a batch of classic frames from the socket with one `recvmmsg` call.
+///
+/// This blocks until at least one frame is available, then reads as many
+/// as are queued, up to the length of `frames`. The `iovs` and `hdrs`
+/// vectors are scratch space that are cleared and refilled on each call,
+/// so that a caller can reuse them without further allocations.
+///
+/// Returns the number of frames read into the front of `frames`.
+fn raw_recv_mmsg(
+    fd: RawFd,
+    frames: &mut [libc::can_frame],
+    iovs: &mut Vec<libc::iovec>,
+    hdrs: &mut Vec<libc::mmsghdr>,
+) -> IoResult<usize> {
+    raw_recv_mmsg_with_control(fd, frames, iovs, hdrs, &mut [])
+}
+
+/// Reads a batch of classic frames from the socket with one `recvmmsg`
+/// call, also receiving the control messages for each frame.
+///
+/// This is the same as `raw_recv_mmsg()`, but the `control` buffer is split
+/// into equal chunks, one per frame, to receive the ancillary data for
+/// each message. An empty `control` buffer receives no ancillary data. On
+/// return, the control messages of each frame can be parsed from the
+/// corresponding `msg_hdr` in `hdrs`.
+fn raw_recv_mmsg_with_control(
+    fd: RawFd,
+    frames: &mut [libc::can_frame],
+    iovs: &mut Vec<libc::iovec>,
+    hdrs: &mut Vec<libc::mmsghdr>,
+    control: &mut [u64],
+) -> IoResult<usize> {
+    iovs.clear();
+    iovs.extend(frames.iter_mut().map(|frame| libc::iovec {
+        iov_base: frame as *mut _ as *mut c_void,
+        iov_len: size_of::<libc::can_frame>(),
+    }));
+
+    let stride = control.len() / frames.len().max(1);
+    let mut control = control.chunks_exact_mut(stride.max(1));
+
+    hdrs.clear();
+    hdrs.extend(iovs.iter_mut().map(|iov| {
+        let mut hdr: libc::mmsghdr = unsafe { mem::zeroed() };
+        hdr.msg_hdr.msg_iov = iov;
+        hdr.msg_hdr.msg_iovlen = 1;
+        if let Some(ctrl) = control.next().filter(|_| stride > 0) {
+            hdr.msg_hdr.msg_control = ctrl.as_mut_ptr() as *mut c_void;
+            hdr.msg_hdr.msg_controllen = size_of_val(ctrl) as _;
+        }
+        hdr
+    }));
+
+    let ret = unsafe {
+        libc::recvmmsg(
+            fd,
+            hdrs.as_mut_ptr(),
+            hdrs.len() as _,
+            libc::MSG_WAITFORONE as _,
+            ptr::null_mut(),
+        )
+    };
+
+    if ret < 0 {
+        return Err(IoError::last_os_error());
+    }
+
+    let n = ret as usize;
+    match hdrs[..n].iter().all(|hdr| hdr.msg_len as usize == CAN_MTU) {
+        true => Ok(n),
+        false => Err(IoError::from(IoErrorKind::InvalidData)),
+    }
 }
 
 /// `setsockopt` wrapper
@@ -162,12 +855,73 @@ pub trait Socket: AsRawFd {
     ///
     /// Usually the more common case, opens a socket can device by name, such
     /// as "can0", "vcan0", or "socan0".
+    ///
+    /// On failure, the error message includes the name of the interface,
+    /// and a hint if the interface doesn't seem to exist.
     fn open(ifname: &str) -> IoResult<Self>
     where
         Self: Sized,
     {
-        let addr = CanAddr::from_iface(ifname)?;
-        Self::open_addr(&addr)
+        Self::open_with(ifname, &OpenOptions::new())
+    }
+
+    /// Open a named CAN device, with the specified options for creating
+    /// the socket.
+    ///
+    /// This is the same as `open()`, but allows the close-on-exec and
+    /// nonblocking flags of the socket to be chosen explicitly.
+    fn open_with(ifname: &str, opts: &OpenOptions) -> IoResult<Self>
+    where
+        Self: Sized,
+    {
+        CanAddr::from_iface(ifname)
+            .and_then(|addr| Self::open_addr_with(&addr, opts))
+            .map_err(|err| open_error(err, ifname))
+    }
+
+    /// Open a CAN device from its path in sysfs.
+    ///
+    /// This takes the interface name from the last component of a path
+    /// such as `/sys/class/net/can0`, as is given by udev and other device
+    /// discovery tools, and opens the device with that name.
+    fn open_path<P: AsRef<Path>>(path: P) -> IoResult<Self>
+    where
+        Self: Sized,
+    {
+        Self::open(iface_name_from_path(path.as_ref())?)
+    }
+
+    /// Open a named CAN device in another network namespace.
+    ///
+    /// The `netns` is an open file descriptor of a network namespace, like
+    /// one of the files under `/var/run/netns/`, as created by
+    /// `ip netns add`, or the `/proc/<pid>/ns/net` of a process in it. The
+    /// interface is looked up by name in that namespace, and the socket is
+    /// created and bound in it, where it stays for the rest of its life,
+    /// while the calling thread stays in its own namespace. This lets a
+    /// test rig give each test its own isolated `vcan` interface.
+    ///
+    /// The socket is opened on a new, short-lived thread, which enters the
+    /// namespace with `setns(2)` and then exits, since the namespace of a
+    /// thread can't always be restored once it has changed. Entering a
+    /// network namespace requires the `CAP_SYS_ADMIN` capability, and fails
+    /// with a `PermissionDenied` error without it. A descriptor that isn't
+    /// a network namespace fails with `InvalidInput`.
+    fn open_in_netns(ifname: &str, netns: BorrowedFd<'_>) -> IoResult<Self>
+    where
+        Self: Sized + Send,
+    {
+        thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    if unsafe { libc::setns(netns.as_raw_fd(), libc::CLONE_NEWNET) } < 0 {
+                        return Err(IoError::last_os_error());
+                    }
+                    Self::open(ifname)
+                })
+                .join()
+                .unwrap_or_else(|_| Err(IoError::new(IoErrorKind::Other, "netns thread panicked")))
+        })
     }
 
     /// Open CAN device by interface number.
@@ -183,21 +937,45 @@ pub trait Socket: AsRawFd {
 
     /// Open a CAN socket by address.
     fn open_addr(addr: &CanAddr) -> IoResult<Self>
+    where
+        Self: Sized,
+    {
+        Self::open_addr_with(addr, &OpenOptions::new())
+    }
+
+    /// Open a CAN socket by address, with the specified options for
+    /// creating the socket.
+    fn open_addr_with(addr: &CanAddr, opts: &OpenOptions) -> IoResult<Self>
     where
         Self: Sized;
 
     /// Gets a shared reference to the underlying socket object
     fn as_raw_socket(&self) -> &socket2::Socket;
 
+    /// Gets the local address to which the socket is bound.
+    ///
+    /// This can be used to determine the interface that the socket was
+    /// opened on, such as when it was opened by index.
+    fn local_addr(&self) -> IoResult<CanAddr> {
+        CanAddr::try_from(self.as_raw_socket().local_addr()?)
+    }
+
     /// Gets a mutable reference to the underlying socket object
     fn as_raw_socket_mut(&mut self) -> &mut socket2::Socket;
 
     /// Determines if the socket is currently in nonblocking mode.
+    ///
+    /// This reads the `O_NONBLOCK` flag of the file descriptor with
+    /// `fcntl(2)`, so it reports the actual state of the socket, even if it
+    /// was changed outside of this crate, such as for a socket that was
+    /// created from a file descriptor.
     fn nonblocking(&self) -> IoResult<bool> {
         self.as_raw_socket().nonblocking()
     }
 
     /// Change socket to non-blocking mode or back to blocking mode.
+    ///
+    /// This can be called at any time after the socket is opened.
     fn set_nonblocking(&self, nonblocking: bool) -> IoResult<()> {
         self.as_raw_socket().set_nonblocking(nonblocking)
     }
@@ -220,6 +998,12 @@ pub trait Socket: AsRawFd {
     ///
     /// If the duration is set to `None` then write calls will block
     /// indefinitely.
+    ///
+    /// A raw CAN socket can't be shut down, as the kernel rejects
+    /// `shutdown(2)` on it, so there's no way for another thread to wake a
+    /// reader that's blocked in `read_frame()`. To stop a reader, read with
+    /// `read_frame_with_shutdown()` instead, or set a read timeout and
+    /// check for a stop request between reads.
     fn set_read_timeout<D>(&self, duration: D) -> IoResult<()>
     where
         D: Into<Option<Duration>>,
@@ -244,58 +1028,627 @@ pub trait Socket: AsRawFd {
     }
 
     /// Blocking read a single can frame.
+    ///
+    /// A quiet bus never looks like the end of a stream. If no frame
+    /// arrives, this blocks, or, if a read timeout is set or the socket is
+    /// non-blocking, returns an error for which
+    /// [`should_retry()`](ShouldRetry::should_retry) is `true`.
+    ///
+    /// Other errors are:
+    ///
+    /// - `UnexpectedEof` for a read of zero bytes. A raw CAN socket never
+    ///   returns one, as the kernel doesn't end the stream or let the
+    ///   socket be shut down, so this only comes from a descriptor of some
+    ///   other kind. Reconnect logic can't wait for it; use a read timeout
+    ///   to notice a quiet bus, and `ENETDOWN` or the interface state to
+    ///   notice that the interface went away.
+    /// - `InvalidData` if the kernel returned a message that is not the
+    ///   size of a frame of the socket's type.
+    /// - Any other OS error, like `ENETDOWN` if the interface went down.
     fn read_frame(&self) -> IoResult<Self::FrameType>;
 
-    /// Blocking read a single can frame with timeout.
-    fn read_frame_timeout(&self, timeout: Duration) -> IoResult<Self::FrameType> {
+    /// Blocking read a single can frame, unless the reads are shut down.
+    ///
+    /// This is the same as `read_frame()`, honoring a read timeout and
+    /// non-blocking mode, but it also returns, with an error of the kind
+    /// `UnexpectedEof`, as soon as `shutdown()` is called on the handle.
+    /// That can be done from another thread to unblock a reader that's
+    /// waiting on a quiet bus. Once the handle is shut down, every read
+    /// with it returns `UnexpectedEof`, even if there are frames queued.
+    fn read_frame_with_shutdown(&self, shutdown: &ReadShutdown) -> IoResult<Self::FrameType> {
         use nix::poll::{poll, PollFd, PollFlags};
-        let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLIN);
+        let mut pollfds = [
+            PollFd::new(self.as_raw_fd(), PollFlags::POLLIN),
+            PollFd::new(shutdown.as_raw_fd(), PollFlags::POLLIN),
+        ];
+
+        let timeout = match self.read_timeout()? {
+            _ if self.nonblocking()? => 0,
+            Some(dur) => dur.as_millis().clamp(1, c_int::MAX as u128) as c_int,
+            None => -1,
+        };
 
-        match poll(&mut [pollfd], timeout.as_millis() as c_int)? {
-            0 => Err(IoErrorKind::TimedOut.into()),
-            _ => self.read_frame(),
+        if poll(&mut pollfds, timeout)? == 0 {
+            return Err(IoErrorKind::WouldBlock.into());
         }
+        if pollfds[1].revents().is_some_and(|ev| !ev.is_empty()) {
+            return Err(IoError::new(
+                IoErrorKind::UnexpectedEof,
+                "reads on the socket were shut down",
+            ));
+        }
+        self.read_frame()
     }
 
-    /// Write a single can frame.
+    /// Blocking read a single can frame, along with the flags that tell
+    /// where it came from.
     ///
-    /// Note that this function can fail with an `EAGAIN` error or similar.
-    /// Use `write_frame_insist` if you need to be sure that the message got
-    /// sent or failed.
-    //fn write_frame(&self, frame: &Self::FrameType) -> IoResult<()>;
+    /// When loopback is enabled, which it is by default, frames sent by
+    /// other sockets on the host are received as if they had come from the
+    /// bus, and with `set_recv_own_msgs()`, so are a socket's own frames.
+    /// The flags distinguish these from genuine bus traffic. This returns
+    /// the same errors as `read_frame()`.
+    fn read_frame_with_flags(&self) -> IoResult<(Self::FrameType, RecvFlags)>;
+
+    /// Blocking read of the raw bytes of a single frame into the buffer.
+    ///
+    /// This returns the actual size of the frame, even if the buffer is too
+    /// small to hold all of it. In that case, the buffer holds the start of
+    /// the frame, the rest is discarded, and the flags include `TRUNCATED`.
+    /// This lets a caller reading into a small buffer detect frames that
+    /// didn't fit, and learn how large they were.
+    fn read_frame_bytes(&self, buf: &mut [u8]) -> IoResult<(usize, RecvFlags)> {
+        raw_recv(self.as_raw_fd(), buf, libc::MSG_TRUNC)
+    }
 
-    /// Writes a normal CAN 2.0 frame to the socket.
-    fn write_frame<F>(&self, frame: &F) -> IoResult<()>
+    /// Gets a blocking iterator over the error frames received by the
+    /// socket, decoded into errors.
+    ///
+    /// Data and remote frames are read and discarded, so this is best used
+    /// on a socket dedicated to monitoring errors. Error frames are only
+    /// received after they are enabled with `set_error_filter()`.
+    ///
+    /// The iterator yields any I/O error from a read, including timeouts,
+    /// after which it can continue to be used. It never ends on a raw CAN
+    /// socket, so a read timeout is the way to regain control.
+    fn errors(&self) -> Errors<'_, Self>
     where
-        F: Into<Self::FrameType> + AsPtr;
+        Self: Sized,
+    {
+        Errors { sock: self }
+    }
 
-    /// Blocking write a single can frame, retrying until it gets sent
-    /// successfully.
-    fn write_frame_insist<F>(&self, frame: &F) -> IoResult<()>
+    /// Reads from the socket for a period of time, counting the error
+    /// frames that are received by the class of error.
+    ///
+    /// This gathers link quality statistics, rather than individual events.
+    /// Like `errors()`, data and remote frames are read and discarded, and
+    /// error frames are only received for the classes enabled with
+    /// `set_error_filter()` or `set_error_classes()`. It returns once the
+    /// window has elapsed, or with the first I/O error, other than an
+    /// interrupted read.
+    fn count_errors(&self, window: Duration) -> IoResult<ErrorCounter>
     where
-        F: Into<Self::FrameType> + AsPtr,
+        Self::FrameType: Into<CanAnyFrame>,
     {
+        let deadline = Instant::now() + window;
+        let mut counter = ErrorCounter::new();
+
         loop {
-            match self.write_frame(frame) {
-                Ok(v) => return Ok(v),
+            match self.read_frame_deadline(deadline).map(Into::into) {
+                Ok(CanAnyFrame::Error(frame)) => counter.add(&frame),
+                Ok(_) => (),
+                Err(e) if e.kind() == IoErrorKind::TimedOut => return Ok(counter),
                 Err(e) if e.should_retry() => (),
                 Err(e) => return Err(e),
             }
         }
     }
-}
 
-/// Traits for setting CAN socket options.
-///
-/// These are blocking calls, even when implemented on asynchronous sockets.
-pub trait SocketOptions: AsRawFd {
-    /// Sets an option on the socket.
+    /// Blocking read of a single frame, along with the times that it was
+    /// received.
     ///
-    /// The libc `setsockopt` function is set to set various options on a socket.
-    /// `set_socket_option` offers a somewhat type-safe wrapper that does not
-    /// require messing around with `*const c_void`s.
+    /// The sources of the times are selected for the socket with
+    /// `set_timestamp_config()`. Any that are not available for the frame
+    /// are returned as `None`.
+    fn read_frame_with_timestamps(&self) -> IoResult<(Self::FrameType, FrameTimestamps)>;
+
+    /// Blocking read of a single frame, along with all of the ancillary
+    /// data that the kernel sent with it.
     ///
-    /// A proper `std::io::Error` will be returned on failure.
+    /// This is the general form of the functions that read a frame along
+    /// with a particular kind of control message, such as the timestamps.
+    /// The control messages are read into `cmsg_buf`, which the returned
+    /// data borrows, so they can be parsed in full by the caller. A buffer
+    /// that is too small to hold them all is reported by
+    /// `AncillaryData::is_truncated()`. A buffer of 128 bytes is plenty for
+    /// the timestamps and the drop counter together.
+    fn read_frame_with_ancillary<'a>(
+        &self,
+        cmsg_buf: &'a mut [u8],
+    ) -> IoResult<(Self::FrameType, AncillaryData<'a>)>;
+
+    /// Blocking read of the next frame, without removing it from the
+    /// receive queue.
+    ///
+    /// This blocks and honors a read timeout, just like `read_frame()`,
+    /// but the frame remains queued on the socket, so that the next read
+    /// or peek returns the same frame again.
+    fn peek_frame(&self) -> IoResult<Self::FrameType>;
+
+    /// Blocking read a single can frame with timeout.
+    fn read_frame_timeout(&self, timeout: Duration) -> IoResult<Self::FrameType> {
+        use nix::poll::{poll, PollFd, PollFlags};
+        let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLIN);
+
+        match poll(&mut [pollfd], timeout.as_millis() as c_int)? {
+            0 => Err(IoErrorKind::TimedOut.into()),
+            _ => self.read_frame(),
+        }
+    }
+
+    /// Blocking read a single can frame, waiting until an absolute deadline.
+    ///
+    /// This is the same as `read_frame_timeout()` with the time remaining
+    /// until the deadline, which is convenient in a loop that keeps
+    /// polling until a fixed time. If the deadline has already passed, a
+    /// frame that is already queued is still returned, but it does not
+    /// wait, and returns an error of the kind `TimedOut` if there is none.
+    fn read_frame_deadline(&self, deadline: Instant) -> IoResult<Self::FrameType> {
+        self.read_frame_timeout(deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Determines if there is at least one frame queued to be read.
+    ///
+    /// This checks the socket with a zero-timeout `poll(2)`, so it never
+    /// blocks. Raw CAN sockets don't support `FIONREAD`, so there is no
+    /// way to tell how many bytes or frames are queued, only whether a
+    /// read would return one right away.
+    fn has_pending(&self) -> IoResult<bool> {
+        use nix::poll::{poll, PollFd, PollFlags};
+        let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLIN);
+        Ok(poll(&mut [pollfd], 0)? != 0)
+    }
+
+    /// Blocking read of up to `n` frames, within a single overall timeout.
+    ///
+    /// This keeps polling and reading until either `n` frames have been
+    /// received or the timeout expires, which suits a request/response
+    /// exchange that expects a fixed number of replies. A timeout is not an
+    /// error: whatever frames were gathered by then are returned, so the
+    /// result may be shorter than `n`, or empty.
+    fn read_n_frames(&self, n: usize, timeout: Duration) -> IoResult<Vec<Self::FrameType>> {
+        use nix::poll::{poll, PollFd, PollFlags};
+        let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLIN);
+        let deadline = Instant::now() + timeout;
+
+        let mut frames = Vec::with_capacity(n);
+        while frames.len() < n {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let ms = remaining.as_millis().min(c_int::MAX as u128) as c_int;
+            if poll(&mut [pollfd], ms)? == 0 {
+                break;
+            }
+            match self.read_frame() {
+                Ok(frame) => frames.push(frame),
+                Err(e) if e.should_retry() => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(frames)
+    }
+
+    /// Blocking read a single can frame with timeout, returning `None` if
+    /// the timeout expired.
+    ///
+    /// This is the same as `read_frame_timeout()`, but reports a timeout as
+    /// `Ok(None)`, rather than an error, which is often easier to handle in
+    /// a polling loop.
+    fn try_read_frame_timeout(&self, timeout: Duration) -> IoResult<Option<Self::FrameType>> {
+        match self.read_frame_timeout(timeout) {
+            Ok(frame) => Ok(Some(frame)),
+            Err(err) if err.kind() == IoErrorKind::TimedOut || err.should_retry() => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Reads a single frame if one is queued on the socket, without
+    /// blocking.
+    ///
+    /// This reads with `MSG_DONTWAIT`, so it doesn't block, even if the
+    /// socket is in blocking mode, and it doesn't change the mode of the
+    /// socket. It returns `None` if there is no frame to read.
+    fn try_read_frame(&self) -> IoResult<Option<Self::FrameType>>;
+
+    /// Discards all of the frames that are currently queued on the socket,
+    /// returning the number of frames that were dropped.
+    ///
+    /// This doesn't block, even if the socket is in blocking mode, and it
+    /// doesn't change the mode of the socket. It's useful to start from a
+    /// clean slate, such as at the start of a test, without any stale
+    /// frames that were received before.
+    fn drain(&self) -> IoResult<usize> {
+        let mut buf = [0u8; CANFD_MTU];
+        let mut n = 0;
+
+        loop {
+            match raw_recv(self.as_raw_fd(), &mut buf, libc::MSG_DONTWAIT) {
+                Ok((0, _)) => return Ok(n),
+                Ok(_) => n += 1,
+                Err(err) if err.kind() == IoErrorKind::WouldBlock => return Ok(n),
+                Err(err) if err.kind() == IoErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Write a single can frame.
+    ///
+    /// Note that this function can fail with an `EAGAIN` error or similar.
+    /// Use `write_frame_insist` if you need to be sure that the message got
+    /// sent or failed.
+    fn write_frame<F>(&self, frame: &F) -> IoResult<()>
+    where
+        F: Into<Self::FrameType> + AsPtr;
+
+    /// Reads the transmit timestamps of a frame that was sent on the socket,
+    /// if any are available.
+    ///
+    /// Once transmit timestamps are enabled with `set_tx_timestamping()`,
+    /// the kernel queues the times for each frame that is sent on the
+    /// error queue of the socket. This reads the next one from the queue,
+    /// without blocking, and returns `None` if the queue is empty. They
+    /// are read in the order that the frames were sent.
+    ///
+    /// Whether the times are reported depends on the driver, and, for the
+    /// hardware time, on the controller. Many CAN drivers, including vcan,
+    /// produce none.
+    fn read_tx_timestamp(&self) -> IoResult<Option<TxTimestamp>> {
+        let mut buf = [0u8; CANFD_MTU];
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut c_void,
+            iov_len: buf.len(),
+        };
+        let mut control = [0u64; 32];
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        hdr.msg_iov = &mut iov;
+        hdr.msg_iovlen = 1;
+        hdr.msg_control = control.as_mut_ptr() as *mut c_void;
+        hdr.msg_controllen = size_of_val(&control) as _;
+
+        let flags = libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT;
+        if unsafe { libc::recvmsg(self.as_raw_fd(), &mut hdr, flags) } < 0 {
+            let err = IoError::last_os_error();
+            return match err.kind() {
+                IoErrorKind::WouldBlock => Ok(None),
+                _ => Err(err),
+            };
+        }
+        Ok(raw_cmsg_tx_timestamp(&hdr))
+    }
+
+    /// Writes a single frame to the interface with the specified address.
+    ///
+    /// This uses `sendto(2)` to address the frame to a specific interface,
+    /// which is useful for a socket that was opened to receive from all
+    /// interfaces (interface index zero), and thus has no default
+    /// interface on which to send.
+    ///
+    /// The kernel rejects `connect(2)` on a raw CAN socket, so the only
+    /// default destination it knows is the interface that the socket is
+    /// bound to. `CanSocket::connect()` and `CanFdSocket::connect()` give
+    /// a socket a default destination of its own, which `write_frame()`
+    /// passes here.
+    fn write_frame_to<F>(&self, frame: &F, addr: &CanAddr) -> IoResult<()>
+    where
+        F: Into<Self::FrameType> + AsPtr,
+    {
+        let buf = frame.as_bytes();
+        match self.as_raw_socket().send_to(buf, &SockAddr::from(*addr))? {
+            n if n == buf.len() => Ok(()),
+            _ => Err(IoErrorKind::WriteZero.into()),
+        }
+    }
+
+    /// Writes a single frame, scheduled for transmission at a specific time.
+    ///
+    /// This attaches an `SCM_TXTIME` control message to the frame, which
+    /// tells the kernel when the frame should leave the interface. The time
+    /// is converted to the clock that was selected for the socket with
+    /// `set_txtime()`, which must be called before using this function.
+    /// The scheduling is performed by a time-aware queuing discipline, like
+    /// ETF, which must be configured on the interface. Without one, the
+    /// frame is sent immediately.
+    ///
+    /// A time in the past is converted to the current time.
+    fn write_frame_at<F>(&self, frame: &F, when: Instant) -> IoResult<()>
+    where
+        F: Into<Self::FrameType> + AsPtr,
+    {
+        use nix::sys::socket::{
+            getsockopt, sendmsg, sockopt, ControlMessage, MsgFlags, SockaddrStorage,
+        };
+        use std::io::IoSlice;
+
+        let fd = self.as_raw_fd();
+        let clockid = getsockopt(fd, sockopt::TxTime)?.clockid;
+        let txtime = raw_clock_time_ns(clockid, when)?;
+
+        let buf = frame.as_bytes();
+        let iov = [IoSlice::new(buf)];
+        let cmsgs = [ControlMessage::TxTime(&txtime)];
+
+        match sendmsg::<SockaddrStorage>(fd, &iov, &cmsgs, MsgFlags::empty(), None)? {
+            n if n == buf.len() => Ok(()),
+            _ => Err(IoErrorKind::WriteZero.into()),
+        }
+    }
+
+    /// Blocking write a single can frame, waiting up to the timeout for the
+    /// socket to become writable.
+    ///
+    /// This polls the socket for writability, and retries the write if the
+    /// transmit queue is full, until either the frame is sent or the timeout
+    /// expires. On timeout, an error of the kind `TimedOut` is returned.
+    /// This is useful to keep a transmitter from hanging when the bus is
+    /// jammed, as `write_frame_insist` would.
+    fn write_frame_timeout<F>(&self, frame: &F, timeout: Duration) -> IoResult<()>
+    where
+        F: Into<Self::FrameType> + AsPtr,
+    {
+        use nix::poll::{poll, PollFd, PollFlags};
+        let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLOUT);
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let ms = remaining.as_millis().min(c_int::MAX as u128) as c_int;
+            match poll(&mut [pollfd], ms)? {
+                0 => return Err(IoErrorKind::TimedOut.into()),
+                _ => match self.write_frame(frame) {
+                    Ok(()) => return Ok(()),
+                    Err(e) if e.should_retry() && !remaining.is_zero() => (),
+                    Err(e) if e.should_retry() => return Err(IoErrorKind::TimedOut.into()),
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+    }
+
+    /// Writes a single frame, and waits for confirmation that it was sent
+    /// on the bus.
+    ///
+    /// The kernel echoes a frame back to the socket that sent it once the
+    /// driver reports that it was transmitted, provided that
+    /// `set_recv_own_msgs()` is enabled. This sends the frame, then reads
+    /// from the socket until it sees the echo of the same frame, flagged
+    /// as being its own, or the timeout expires, in which case it returns
+    /// an error of the kind `TimedOut`. It always times out if receiving
+    /// own messages is not enabled.
+    ///
+    /// Note that any other frames that are received while waiting for the
+    /// echo are discarded, so this is best used on a socket that is only
+    /// used for sending, or that has filters which only pass its own
+    /// frames.
+    fn write_frame_confirmed<F>(&self, frame: &F, timeout: Duration) -> IoResult<()>
+    where
+        F: Into<Self::FrameType> + AsPtr + Clone,
+        Self::FrameType: PartialEq,
+    {
+        use nix::poll::{poll, PollFd, PollFlags};
+        let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLIN);
+        let deadline = Instant::now() + timeout;
+
+        let expected: Self::FrameType = frame.clone().into();
+        self.write_frame_timeout(frame, timeout)?;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let ms = remaining.as_millis().min(c_int::MAX as u128) as c_int;
+            if poll(&mut [pollfd], ms)? == 0 {
+                return Err(IoErrorKind::TimedOut.into());
+            }
+            match self.read_frame_with_flags() {
+                Ok((echo, flags)) if flags.contains(RecvFlags::OWN) && echo == expected => {
+                    return Ok(())
+                }
+                Ok(_) => (),
+                Err(e) if e.should_retry() => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Writes a single frame, waits for its echo, and returns the time
+    /// that the frame took to come back.
+    ///
+    /// This is like `write_frame_confirmed()`, but it enables
+    /// `set_recv_own_msgs()` itself, which is left on afterwards, and
+    /// measures the round trip from just before the frame is written to
+    /// when the echo was received. That's the loopback latency through the
+    /// driver and, for real hardware, the time that it took to win the bus
+    /// and send the frame.
+    ///
+    /// The echo is timed with the software receive timestamp of the kernel,
+    /// if it's enabled with `set_timestamp_config()`, which is the most
+    /// accurate. Otherwise, it's the time that the echo was read, which
+    /// includes any scheduling delay of the calling thread. If the echo is
+    /// not received before the timeout expires, an error of the kind
+    /// `TimedOut` is returned. As with `write_frame_confirmed()`, any other
+    /// frames received while waiting are discarded.
+    fn write_frame_echo_latency<F>(&self, frame: &F, timeout: Duration) -> IoResult<Duration>
+    where
+        F: Into<Self::FrameType> + AsPtr,
+        Self: SocketOptions,
+    {
+        use nix::poll::{poll, PollFd, PollFlags};
+        let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLIN);
+        let deadline = Instant::now() + timeout;
+
+        self.set_recv_own_msgs(true)?;
+        let sent = SystemTime::now();
+        self.write_frame_timeout(frame, timeout)?;
+
+        let mut buf = [0u8; CANFD_MTU];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let ms = remaining.as_millis().min(c_int::MAX as u128) as c_int;
+            if poll(&mut [pollfd], ms)? == 0 {
+                return Err(IoErrorKind::TimedOut.into());
+            }
+            let mut ts = FrameTimestamps::default();
+            match raw_recv_timestamped(self.as_raw_fd(), &mut buf, 0, Some(&mut ts)) {
+                Ok((n, flags))
+                    if flags.contains(RecvFlags::OWN) && buf[..n] == *frame.as_bytes() =>
+                {
+                    let echoed = ts.software.unwrap_or_else(SystemTime::now);
+                    return Ok(echoed.duration_since(sent).unwrap_or_default());
+                }
+                Ok(_) => (),
+                Err(e) if e.should_retry() => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Sends a request frame, then waits for the first reply with the
+    /// specified ID.
+    ///
+    /// This is the common pattern of a diagnostic request, like UDS over
+    /// raw CAN, which sends on one ID and expects the reply on another. The
+    /// `match_id` is compared with the raw ID of each received frame,
+    /// without the EFF/RTR/ERR flags, and error frames are skipped. Any
+    /// other frames that are received while waiting are discarded.
+    ///
+    /// The timeout covers both sending the request and waiting for the
+    /// reply, and if no reply is received by then, an error of the kind
+    /// `TimedOut` is returned. The reply is picked out as the frames are
+    /// read, so this installs no filter on the socket, and the filters in
+    /// place still apply. A reply blocked by them is never seen.
+    fn request_response<F>(
+        &self,
+        tx: &F,
+        match_id: canid_t,
+        timeout: Duration,
+    ) -> IoResult<Self::FrameType>
+    where
+        F: Into<Self::FrameType> + AsPtr,
+        Self::FrameType: Frame,
+    {
+        let deadline = Instant::now() + timeout;
+        self.write_frame_timeout(tx, timeout)?;
+
+        loop {
+            match self.read_frame_deadline(deadline) {
+                Ok(frame) if !frame.is_error_frame() && frame.raw_id() == match_id => {
+                    return Ok(frame)
+                }
+                Ok(_) => (),
+                Err(e) if e.kind() == IoErrorKind::TimedOut => return Err(e),
+                Err(e) if e.should_retry() => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Blocking write a single can frame, retrying until it gets sent
+    /// successfully.
+    ///
+    /// This retries in a busy loop. See `write_frame_insist_with()` to wait
+    /// between attempts instead.
+    fn write_frame_insist<F>(&self, frame: &F) -> IoResult<()>
+    where
+        F: Into<Self::FrameType> + AsPtr,
+    {
+        self.write_frame_insist_with(frame, RetryPolicy::Spin)
+    }
+
+    /// Blocking write a single can frame, retrying until it gets sent
+    /// successfully, and waiting between attempts according to the policy.
+    ///
+    /// When the bus is saturated, the transmit queue fills up and writes
+    /// fail until there's room. Polling or backing off, rather than
+    /// spinning, keeps this from using a whole CPU while it waits.
+    fn write_frame_insist_with<F>(&self, frame: &F, policy: RetryPolicy) -> IoResult<()>
+    where
+        F: Into<Self::FrameType> + AsPtr,
+    {
+        use nix::{
+            errno::Errno,
+            poll::{poll, PollFd, PollFlags},
+        };
+
+        let mut delay = match policy {
+            RetryPolicy::Backoff { initial, .. } => initial,
+            _ => Duration::ZERO,
+        };
+
+        loop {
+            match self.write_frame(frame) {
+                Ok(v) => return Ok(v),
+                Err(e) if e.should_retry() => (),
+                Err(e) => return Err(e),
+            }
+
+            match policy {
+                RetryPolicy::Spin => (),
+                RetryPolicy::Poll => {
+                    let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLOUT);
+                    match poll(&mut [pollfd], -1) {
+                        Ok(_) | Err(Errno::EINTR) => (),
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                RetryPolicy::Backoff { max, .. } => {
+                    thread::sleep(delay);
+                    delay = (delay * 2).min(max);
+                }
+            }
+        }
+    }
+
+    /// Blocking write of a sequence of frames, in order.
+    ///
+    /// Each frame is sent with `write_frame_insist`, and this stops on the
+    /// first hard error, reporting the index of the frame that failed. On
+    /// success it returns the number of frames that were sent.
+    ///
+    /// If a write timeout is set on the socket, a frame that can not be
+    /// sent before the timeout expires is considered a failure, rather
+    /// than being retried indefinitely.
+    fn write_all_frames<I>(&self, frames: I) -> Result<usize, WriteFramesError>
+    where
+        I: IntoIterator,
+        I::Item: Into<Self::FrameType> + AsPtr,
+    {
+        let timeout = self
+            .write_timeout()
+            .map_err(|error| WriteFramesError { index: 0, error })?;
+
+        let mut n = 0;
+        for frame in frames {
+            let res = match timeout {
+                Some(_) => self.write_frame(&frame),
+                None => self.write_frame_insist(&frame),
+            };
+            res.map_err(|error| WriteFramesError { index: n, error })?;
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+/// Traits for setting CAN socket options.
+///
+/// These are blocking calls, even when implemented on asynchronous sockets.
+pub trait SocketOptions: AsRawFd {
+    /// Sets an option on the socket.
+    ///
+    /// The libc `setsockopt` function is set to set various options on a socket.
+    /// `set_socket_option` offers a somewhat type-safe wrapper that does not
+    /// require messing around with `*const c_void`s.
+    ///
+    /// A proper `std::io::Error` will be returned on failure.
     ///
     /// Example use:
     ///
@@ -353,12 +1706,19 @@ pub trait SocketOptions: AsRawFd {
     ///
     /// See `CanFilter` for details on how filtering works. By default, all
     /// single filter matching all incoming frames is installed.
+    ///
+    /// The kernel accepts at most [`CAN_RAW_FILTER_MAX`] filters on a
+    /// socket. If more are given, the error returned has the kind
+    /// `InvalidInput`, and describes the limit. A larger set of IDs may
+    /// need to be covered with fewer, wider masks, and then filtered
+    /// further in user space.
     fn set_filters<F>(&self, filters: &[F]) -> IoResult<()>
     where
         F: Into<CanFilter> + Copy,
     {
         let filters: Vec<CanFilter> = filters.iter().map(|f| (*f).into()).collect();
         self.set_socket_option_mult(SOL_CAN_RAW, CAN_RAW_FILTER, &filters)
+            .map_err(|err| filter_error(err, filters.len()))
     }
 
     /// Disable reception of CAN frames.
@@ -378,6 +1738,29 @@ pub trait SocketOptions: AsRawFd {
         self.set_filters(&[(0, 0)])
     }
 
+    /// Accept only data frames, rejecting remote and error frames.
+    ///
+    /// This replaces the current filter with one that matches on the RTR
+    /// flag of the ID, so that remote requests are dropped by the kernel,
+    /// and also clears the error mask. Error frames are only ever delivered
+    /// through the error mask.
+    ///
+    /// The same flag can be used to restrict any other filter to data
+    /// frames, by adding `CAN_RTR_FLAG` to its mask.
+    fn set_filter_accept_data_only(&self) -> IoResult<()> {
+        self.set_filters(&[(0, CAN_RTR_FLAG)])?;
+        self.set_error_filter_drop_all()
+    }
+
+    /// Accept only remote frames, rejecting data and error frames.
+    ///
+    /// This is the converse of
+    /// [`set_filter_accept_data_only()`](Self::set_filter_accept_data_only).
+    fn set_filter_accept_remote_only(&self) -> IoResult<()> {
+        self.set_filters(&[(CAN_RTR_FLAG, CAN_RTR_FLAG)])?;
+        self.set_error_filter_drop_all()
+    }
+
     /// Sets the error mask on the socket.
     ///
     /// By default (`ERR_MASK_NONE`) no error conditions are reported as
@@ -400,6 +1783,15 @@ pub trait SocketOptions: AsRawFd {
         self.set_error_filter(CAN_ERR_MASK)
     }
 
+    /// Sets the error mask on the socket to accept the specified classes
+    /// of errors.
+    ///
+    /// This is the same as `set_error_filter()`, with the mask composed
+    /// from the classes. An empty list drops all errors.
+    fn set_error_classes(&self, classes: &[ErrClass]) -> IoResult<()> {
+        self.set_error_filter(ErrClass::mask_of(classes))
+    }
+
     /// Sets the error mask on the socket.
     ///
     /// By default (`ERR_MASK_NONE`) no error conditions are reported as
@@ -431,42 +1823,172 @@ pub trait SocketOptions: AsRawFd {
 
     /// Enable or disable join filters.
     ///
-    /// By default a frame is accepted if it matches any of the filters set
-    /// with `set_filters`. If join filters is enabled, a frame has to match
-    /// _all_ filters to be accepted.
+    /// By default join filters is disabled, and a frame is accepted if it
+    /// matches _any_ of the filters set with `set_filters`. If join filters
+    /// is enabled, a frame has to match _all_ filters to be accepted.
     fn set_join_filters(&self, enabled: bool) -> IoResult<()> {
         let join_filters = c_int::from(enabled);
         self.set_socket_option(SOL_CAN_RAW, CAN_RAW_JOIN_FILTERS, &join_filters)
     }
-}
 
-// TODO: We need to restore this, but preferably with TIMESTAMPING
+    /// Determines whether join filters is enabled on the socket.
+    ///
+    /// This is `false` for a newly opened socket, meaning that a frame is
+    /// accepted if it matches any of the filters.
+    /// See [`set_join_filters()`](Self::set_join_filters).
+    fn join_filters(&self) -> IoResult<bool> {
+        let join_filters =
+            raw_get_socket_option_int(self.as_raw_fd(), SOL_CAN_RAW, CAN_RAW_JOIN_FILTERS)?;
+        Ok(join_filters != 0)
+    }
 
-/*
-impl CanSocket {
+    /// Sets the priority of the frames sent on the socket with `SO_PRIORITY`.
+    ///
+    /// The priority is used by the queuing discipline on the interface to
+    /// order outgoing frames, such as with the `prio` qdisc, and has no
+    /// effect on the priority of the frames on the bus itself, which is set
+    /// by their IDs. Priorities outside the range 0-6 require
+    /// the `CAP_NET_ADMIN` capability.
+    fn set_priority(&self, prio: i32) -> IoResult<()> {
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_PRIORITY, &(prio as c_int))
+    }
 
-    /// Blocking read a single can frame with timestamp
+    /// Gets the priority of the frames sent on the socket.
     ///
-    /// Note that reading a frame and retrieving the timestamp requires two
-    /// consecutive syscalls. To avoid race conditions, exclusive access
-    /// to the socket is enforce through requiring a `mut &self`.
-    pub fn read_frame_with_timestamp(&mut self) -> IoResult<(CanFrame, time::SystemTime)> {
-        let frame = self.read_frame()?;
+    /// This is zero for a new socket.
+    /// See [`set_priority()`](Self::set_priority).
+    fn priority(&self) -> IoResult<i32> {
+        raw_get_socket_option_int(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_PRIORITY)
+    }
 
-        let mut ts = timespec { tv_sec: 0, tv_nsec: 0 };
-        let ret = unsafe {
-            libc::ioctl(self.fd, SIOCGSTAMPNS as c_ulong, &mut ts as *mut timespec)
-        };
+    /// Sets the receive low-water mark of the socket with `SO_RCVLOWAT`.
+    ///
+    /// This is the minimum number of bytes that should be queued before a
+    /// read, or a poll for input, reports the socket as ready. The default
+    /// is one byte. Note that Linux only applies this fully to stream
+    /// sockets, so for a CAN socket, which receives each frame as a whole
+    /// datagram, it may have no effect on when a read returns.
+    fn set_recv_lowat(&self, bytes: usize) -> IoResult<()> {
+        let bytes = c_int::try_from(bytes).unwrap_or(c_int::MAX);
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_RCVLOWAT, &bytes)
+    }
 
-        if ret == -1 {
-            return Err(IoError::last_os_error());
+    /// Gets the receive low-water mark of the socket, in bytes.
+    /// See [`set_recv_lowat()`](Self::set_recv_lowat).
+    fn recv_lowat(&self) -> IoResult<usize> {
+        raw_get_socket_option_int(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_RCVLOWAT)
+            .map(|n| n as usize)
+    }
+
+    /// Determines whether FD mode is enabled on the socket, by reading back
+    /// the `CAN_RAW_FD_FRAMES` option.
+    ///
+    /// A `CanFdSocket` opened by this library always has FD mode enabled,
+    /// but one wrapped around an existing file descriptor, such as with
+    /// `From<OwnedFd>`, might not. Code adopting an external descriptor can
+    /// use this to verify that it can send and receive FD frames.
+    fn is_fd_enabled(&self) -> IoResult<bool> {
+        raw_get_socket_option_int(self.as_raw_fd(), SOL_CAN_RAW, CAN_RAW_FD_FRAMES).map(|n| n != 0)
+    }
+
+    /// Enables time-based transmission of frames with `SO_TXTIME`.
+    ///
+    /// This selects the system clock used to interpret the transmit times
+    /// given to `write_frame_at()`, which should match the clock used by
+    /// the queuing discipline on the interface. The ETF qdisc typically
+    /// uses `CLOCK_TAI`. Only `CLOCK_MONOTONIC` can be selected without the
+    /// `CAP_NET_ADMIN` capability.
+    ///
+    /// The kernel does not allow this option to be cleared once it is set,
+    /// so attempting to disable it returns an `Unsupported` error.
+    fn set_txtime(&self, enabled: bool, clockid: libc::clockid_t) -> IoResult<()> {
+        if !enabled {
+            return Err(IoErrorKind::Unsupported.into());
         }
+        let txtime = libc::sock_txtime { clockid, flags: 0 };
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_TXTIME, &txtime)
+    }
+
+    /// Enable or disable receive timestamps with `SO_TIMESTAMP`.
+    ///
+    /// When enabled, the kernel attaches the time that each frame was
+    /// received, as a control message, which can be read with functions
+    /// like `CanSocket::read_frames_with_timestamps()`.
+    fn set_timestamp(&self, enabled: bool) -> IoResult<()> {
+        let timestamp = c_int::from(enabled);
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_TIMESTAMP, &timestamp)
+    }
+
+    /// Enable or disable nanosecond receive timestamps with `SO_TIMESTAMPNS`.
+    ///
+    /// This is the same as `set_timestamp()`, but the kernel reports the
+    /// receive times with nanosecond, rather than microsecond, resolution.
+    /// The times are returned by the same functions, and can be converted
+    /// to a `Duration` since the epoch with `SystemTime::duration_since()`.
+    fn set_timestamp_ns(&self, enabled: bool) -> IoResult<()> {
+        let timestamp = c_int::from(enabled);
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_TIMESTAMPNS, &timestamp)
+    }
 
-        Ok((frame, system_time_from_timespec(ts)))
+    /// Enable or disable reporting of dropped frames with `SO_RXQ_OVFL`.
+    ///
+    /// When enabled, the kernel attaches the total number of frames that
+    /// the socket has dropped, because its receive queue was full, to each
+    /// received frame. This is read with `AncillaryData::dropped()`.
+    fn set_rxq_overflow(&self, enabled: bool) -> IoResult<()> {
+        let rxq_ovfl = c_int::from(enabled);
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_RXQ_OVFL, &rxq_ovfl)
+    }
+
+    /// Enable or disable transmit timestamps with `SO_TIMESTAMPING`.
+    ///
+    /// When enabled, the kernel reports the software and hardware times at
+    /// which each frame sent on the socket was transmitted, which can be
+    /// read back with `read_tx_timestamp()`. This is useful for profiling
+    /// the latency of the transmit path.
+    ///
+    /// This shares the `SO_TIMESTAMPING` option with
+    /// `set_timestamp_config()`, and keeps any receive timestamps that were
+    /// selected with it.
+    fn set_tx_timestamping(&self, enabled: bool) -> IoResult<()> {
+        let cur =
+            raw_get_socket_option_int(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_TIMESTAMPING)?;
+        let tx = match enabled {
+            true => SOF_TIMESTAMPING_TX_FLAGS,
+            false => 0,
+        };
+        let flags = timestamping_flags(cur as c_uint & SOF_TIMESTAMPING_RX_FLAGS, tx);
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_TIMESTAMPING, &flags)
+    }
+
+    /// Selects the sources of the receive timestamps for the socket, with
+    /// `SO_TIMESTAMPING`.
+    ///
+    /// This allows the software and hardware times to be chosen at
+    /// runtime, such as for systems with a mix of controllers, not all of
+    /// which can report hardware times. The times are then read along with
+    /// each frame by `read_frame_with_timestamps()`. Any transmit
+    /// timestamps enabled by `set_tx_timestamping()` are kept.
+    fn set_timestamp_config(&self, config: TimestampConfig) -> IoResult<()> {
+        let cur =
+            raw_get_socket_option_int(self.as_raw_fd(), libc::SOL_SOCKET, libc::SO_TIMESTAMPING)?;
+        let flags =
+            timestamping_flags(config.rx_flags(), cur as c_uint & SOF_TIMESTAMPING_TX_FLAGS);
+        self.set_socket_option(libc::SOL_SOCKET, libc::SO_TIMESTAMPING, &flags)
     }
 
+    /// Enable or disable the reception of CAN XL frames.
+    ///
+    /// This is analogous to the FD mode of a socket, allowing CAN XL frames
+    /// to be received alongside classic and FD frames. It requires a kernel
+    /// with CAN XL support, and fails on older ones. Note that the frame
+    /// types in this crate do not yet cover CAN XL, so an application that
+    /// enables this must read the XL frames from the raw socket itself.
+    fn set_xl_frames(&self, enabled: bool) -> IoResult<()> {
+        let xl_frames = c_int::from(enabled);
+        self.set_socket_option(SOL_CAN_RAW, CAN_RAW_XL_FRAMES, &xl_frames)
+    }
 }
-*/
 
 // ===== CanSocket =====
 
@@ -481,43 +2003,237 @@ impl CanSocket {
 /// (file) descriptor.
 #[allow(missing_copy_implementations)]
 #[derive(Debug)]
-pub struct CanSocket(socket2::Socket);
+pub struct CanSocket(socket2::Socket, AtomicU32);
 
 impl CanSocket {
-    /// Reads a low-level libc `can_frame` from the socket.
-    pub fn read_raw_frame(&self) -> IoResult<libc::can_frame> {
-        let mut frame = can_frame_default();
-        self.as_raw_socket().read_exact(as_bytes_mut(&mut frame))?;
-        Ok(frame)
+    // Wraps a raw socket, which isn't connected.
+    fn from_socket(sock: socket2::Socket) -> Self {
+        Self(sock, AtomicU32::new(0))
     }
-}
 
-impl Socket for CanSocket {
-    /// CanSocket reads/writes classic CAN 2.0 frames.
-    type FrameType = CanFrame;
+    /// Creates a new handle to the same socket.
+    ///
+    /// The new handle has a duplicate of the file descriptor, so it shares
+    /// the receive queue, filters, and other options of the original. It
+    /// starts out connected to the same interface as the original, if
+    /// any, but connecting either one afterwards doesn't affect the other.
+    pub fn try_clone(&self) -> IoResult<Self> {
+        let sock = self.0.try_clone()?;
+        Ok(Self(sock, AtomicU32::new(self.1.load(Ordering::Relaxed))))
+    }
 
-    /// Opens the socket by interface index.
-    fn open_addr(addr: &CanAddr) -> IoResult<Self> {
-        let sock = raw_open_socket(addr)?;
-        Ok(Self(sock))
+    /// Connects the socket to the interface with the specified index.
+    ///
+    /// Like a connected UDP socket, this gives the socket a default
+    /// destination, so that later calls to `write_frame()`, and the other
+    /// writes built on it, are sent to that interface without any per-call
+    /// addressing. Reads are not affected; a socket opened on all
+    /// interfaces (index zero) continues to receive from all of them.
+    ///
+    /// The kernel rejects `connect(2)` on a raw CAN socket, so the
+    /// destination is kept in this handle, and each write addresses the
+    /// frame with `sendto(2)`, as `write_frame_to()` does. An index of
+    /// zero disconnects the socket, so that frames are sent on the
+    /// interface that it's bound to again.
+    pub fn connect(&self, ifindex: u32) {
+        self.1.store(ifindex, Ordering::Relaxed);
     }
 
-    /// Gets a shared reference to the underlying socket object
-    fn as_raw_socket(&self) -> &socket2::Socket {
-        &self.0
+    /// Gets the index of the interface the socket is connected to, if any.
+    pub fn peer_ifindex(&self) -> Option<u32> {
+        match self.1.load(Ordering::Relaxed) {
+            0 => None,
+            ifindex => Some(ifindex),
+        }
     }
 
-    /// Gets a mutable reference to the underlying socket object
-    fn as_raw_socket_mut(&mut self) -> &mut socket2::Socket {
-        &mut self.0
+    /// Splits the socket into a reader and a writer, which can be used
+    /// independently, such as from separate threads.
+    ///
+    /// The writer gets a duplicate of the file descriptor, so both halves
+    /// refer to the same socket.
+    pub fn into_split(self) -> IoResult<(CanReader, CanWriter)> {
+        let writer = self.try_clone()?;
+        Ok((CanReader(self), CanWriter(writer)))
     }
 
-    /// Writes a normal CAN 2.0 frame to the socket.
+    /// Creates a reader and a writer for the socket, leaving this handle
+    /// in place.
+    ///
+    /// This is like `into_split()`, but both halves get duplicates of the
+    /// file descriptor.
+    pub fn split(&self) -> IoResult<(CanReader, CanWriter)> {
+        self.try_clone()?.into_split()
+    }
+
+    /// Creates a socket from an owned file descriptor, checking that it
+    /// is actually a raw CAN socket.
+    ///
+    /// Unlike the `From<OwnedFd>` conversion, which wraps any descriptor,
+    /// this queries the domain and type of the socket, and returns an
+    /// error of the kind `InvalidInput` if it's not an `AF_CAN`, `SOCK_RAW`
+    /// socket. This is useful when adopting a descriptor from elsewhere,
+    /// such as one passed in from another process. The descriptor is
+    /// closed if it's rejected.
+    pub fn try_from_fd(fd: OwnedFd) -> IoResult<Self> {
+        let raw_fd = fd.as_raw_fd();
+        let domain = raw_get_socket_option_int(raw_fd, libc::SOL_SOCKET, libc::SO_DOMAIN)?;
+        let ty = raw_get_socket_option_int(raw_fd, libc::SOL_SOCKET, libc::SO_TYPE)?;
+
+        if domain != AF_CAN || ty != libc::SOCK_RAW {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "file descriptor is not a raw CAN socket",
+            ));
+        }
+        Ok(Self::from(fd))
+    }
+
+    /// Reads a low-level libc `can_frame` from the socket.
+    ///
+    /// This returns the same errors as `read_frame()`.
+    pub fn read_raw_frame(&self) -> IoResult<libc::can_frame> {
+        self.recv_raw_frame(0, None).map(|(frame, _)| frame)
+    }
+
+    /// Reads a raw CAN frame from the socket, as a `CanRawFrame`.
+    ///
+    /// This is the same as `read_raw_frame()`, but returns the same type as
+    /// `CanFdSocket::read_raw()`, which can simplify code, like FFI
+    /// bridges, that handles the raw frames from either type of socket.
+    /// It is always a classic frame.
+    pub fn read_raw(&self) -> IoResult<CanRawFrame> {
+        self.read_raw_frame().map(CanRawFrame::Classic)
+    }
+
+    /// Receives a low-level libc `can_frame` from the socket with the
+    /// specified `recv(2)` flags.
+    ///
+    /// Each read from the socket returns a single whole frame, so any other
+    /// size is invalid. A read with zero bytes would be the end of the
+    /// stream, though a raw CAN socket never returns one.
+    fn recv_raw_frame(
+        &self,
+        flags: c_int,
+        ts: Option<&mut FrameTimestamps>,
+    ) -> IoResult<(libc::can_frame, RecvFlags)> {
+        let mut frame = can_frame_default();
+        match raw_recv_timestamped(self.as_raw_fd(), as_bytes_mut(&mut frame), flags, ts)? {
+            (CAN_MTU, rflags) => Ok((frame, rflags)),
+            (0, _) => Err(IoErrorKind::UnexpectedEof.into()),
+            _ => Err(IoErrorKind::InvalidData.into()),
+        }
+    }
+
+    /// Blocking read of a batch of frames, along with the time that each
+    /// one was received.
+    ///
+    /// This reads up to `max` frames that are queued on the socket with a
+    /// single `recvmmsg(2)` call, blocking until at least one is available,
+    /// and appends them to `buf`. It returns the number of frames that were
+    /// read.
+    ///
+    /// Receive timestamps must first be enabled on the socket with
+    /// `set_timestamp()` or `set_timestamp_ns()`. If a frame is received
+    /// without a timestamp, an error with the kind `InvalidData` is
+    /// returned.
+    pub fn read_frames_with_timestamps(
+        &self,
+        buf: &mut Vec<(CanFrame, SystemTime)>,
+        max: usize,
+    ) -> IoResult<usize> {
+        let max = max.max(1);
+        let stride = unsafe { libc::CMSG_SPACE(size_of::<libc::timespec>() as u32) } as usize;
+        let stride = (stride + 7) / 8;
+
+        let mut frames = vec![can_frame_default(); max];
+        let mut control = vec![0u64; max * stride];
+        let (mut iovs, mut hdrs) = (Vec::with_capacity(max), Vec::with_capacity(max));
+
+        let n = raw_recv_mmsg_with_control(
+            self.as_raw_fd(),
+            &mut frames,
+            &mut iovs,
+            &mut hdrs,
+            &mut control,
+        )?;
+
+        buf.reserve(n);
+        for (frame, hdr) in frames[..n].iter().zip(&hdrs) {
+            let ts = raw_cmsg_timestamp(&hdr.msg_hdr).ok_or_else(|| {
+                IoError::new(IoErrorKind::InvalidData, "missing receive timestamp")
+            })?;
+            buf.push((CanFrame::from(*frame), ts));
+        }
+        Ok(n)
+    }
+
+    /// Blocking read of a single frame, with its receive time from the
+    /// legacy `SIOCGSTAMPNS` ioctl.
+    ///
+    /// This is a fallback for old kernels that can't report timestamps
+    /// in control messages. Prefer `read_frame_with_timestamps()` where
+    /// they are available.
+    ///
+    /// Note that reading a frame and retrieving the timestamp requires two
+    /// consecutive syscalls. To avoid race conditions, exclusive access
+    /// to the socket is enforced through requiring a `&mut self`. This
+    /// doesn't stop another handle to the same socket, such as from
+    /// `try_clone()`, from reading in between.
+    pub fn read_frame_with_timestamp_ioctl(&mut self) -> IoResult<(CanFrame, SystemTime)> {
+        let frame = self.read_frame()?;
+
+        let mut ts: libc::timespec = unsafe { mem::zeroed() };
+        if unsafe { libc::ioctl(self.as_raw_fd(), SIOCGSTAMPNS as _, &mut ts) } < 0 {
+            return Err(IoError::last_os_error());
+        }
+
+        let t = SystemTime::UNIX_EPOCH + Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32);
+        Ok((frame, t))
+    }
+
+    /// Writes a low-level libc `can_frame` to the socket.
+    ///
+    /// This is a lower-level escape hatch for applications, such as those
+    /// sharing frames with C code, that already have the libc struct. The
+    /// bytes of the frame are written as-is, without any of the checks or
+    /// conversions of the higher-level frame types.
+    pub fn write_raw_frame(&self, frame: &libc::can_frame) -> IoResult<()> {
+        self.as_raw_socket().write_all(as_bytes(frame))
+    }
+}
+
+impl Socket for CanSocket {
+    /// CanSocket reads/writes classic CAN 2.0 frames.
+    type FrameType = CanFrame;
+
+    /// Opens the socket by interface index.
+    fn open_addr_with(addr: &CanAddr, opts: &OpenOptions) -> IoResult<Self> {
+        let sock = raw_open_socket(addr, opts)?;
+        Ok(Self::from_socket(sock))
+    }
+
+    /// Gets a shared reference to the underlying socket object
+    fn as_raw_socket(&self) -> &socket2::Socket {
+        &self.0
+    }
+
+    /// Gets a mutable reference to the underlying socket object
+    fn as_raw_socket_mut(&mut self) -> &mut socket2::Socket {
+        &mut self.0
+    }
+
+    /// Writes a normal CAN 2.0 frame to the socket.
+    ///
+    /// If the socket is connected, the frame is sent to that interface.
     fn write_frame<F>(&self, frame: &F) -> IoResult<()>
     where
         F: Into<CanFrame> + AsPtr,
     {
-        self.as_raw_socket().write_all(frame.as_bytes())
+        match self.peer_ifindex() {
+            Some(ifindex) => self.write_frame_to(frame, &CanAddr::new(ifindex)),
+            None => self.as_raw_socket().write_all(frame.as_bytes()),
+        }
     }
 
     /// Reads a normal CAN 2.0 frame from the socket.
@@ -525,6 +2241,48 @@ impl Socket for CanSocket {
         let frame = self.read_raw_frame()?;
         Ok(frame.into())
     }
+
+    /// Reads a normal CAN 2.0 frame from the socket, with its flags.
+    fn read_frame_with_flags(&self) -> IoResult<(CanFrame, RecvFlags)> {
+        let (frame, flags) = self.recv_raw_frame(0, None)?;
+        Ok((frame.into(), flags))
+    }
+
+    /// Reads a normal CAN 2.0 frame from the socket, with its receive times.
+    fn read_frame_with_timestamps(&self) -> IoResult<(CanFrame, FrameTimestamps)> {
+        let mut ts = FrameTimestamps::default();
+        let (frame, _) = self.recv_raw_frame(0, Some(&mut ts))?;
+        Ok((frame.into(), ts))
+    }
+
+    /// Reads a normal CAN 2.0 frame from the socket, with its ancillary
+    /// data.
+    fn read_frame_with_ancillary<'a>(
+        &self,
+        cmsg_buf: &'a mut [u8],
+    ) -> IoResult<(CanFrame, AncillaryData<'a>)> {
+        let mut frame = can_frame_default();
+        match raw_recv_ancillary(self.as_raw_fd(), as_bytes_mut(&mut frame), 0, cmsg_buf)? {
+            (CAN_MTU, data) => Ok((frame.into(), data)),
+            (0, _) => Err(IoErrorKind::UnexpectedEof.into()),
+            _ => Err(IoErrorKind::InvalidData.into()),
+        }
+    }
+
+    /// Reads a normal CAN 2.0 frame from the socket, if one is queued.
+    fn try_read_frame(&self) -> IoResult<Option<CanFrame>> {
+        match self.recv_raw_frame(libc::MSG_DONTWAIT, None) {
+            Ok((frame, _)) => Ok(Some(frame.into())),
+            Err(err) if err.kind() == IoErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Peeks at the next normal CAN 2.0 frame on the socket.
+    fn peek_frame(&self) -> IoResult<CanFrame> {
+        let (frame, _) = self.recv_raw_frame(libc::MSG_PEEK, None)?;
+        Ok(frame.into())
+    }
 }
 
 impl SocketOptions for CanSocket {}
@@ -538,7 +2296,7 @@ impl AsRawFd for CanSocket {
 
 impl From<OwnedFd> for CanSocket {
     fn from(fd: OwnedFd) -> Self {
-        Self(socket2::Socket::from(fd))
+        Self::from_socket(socket2::Socket::from(fd))
     }
 }
 
@@ -570,17 +2328,126 @@ impl Write for CanSocket {
     }
 }
 
+// ===== CanReader / CanWriter =====
+
+/// The reading half of a split [`CanSocket`].
+///
+/// This is created by [`CanSocket::into_split()`] or [`CanSocket::split()`],
+/// and can only be used to receive frames. Options, such as filters, can
+/// still be set on it, and apply to the whole socket.
+#[derive(Debug)]
+pub struct CanReader(CanSocket);
+
+impl CanReader {
+    /// Blocking read of a single frame.
+    pub fn read_frame(&self) -> IoResult<CanFrame> {
+        self.0.read_frame()
+    }
+
+    /// Blocking read of a single frame with a timeout.
+    pub fn read_frame_timeout(&self, timeout: Duration) -> IoResult<CanFrame> {
+        self.0.read_frame_timeout(timeout)
+    }
+
+    /// Reads a single frame, if one is queued, without blocking.
+    pub fn try_read_frame(&self) -> IoResult<Option<CanFrame>> {
+        self.0.try_read_frame()
+    }
+}
+
+impl AsRawFd for CanReader {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl SocketOptions for CanReader {}
+
+/// The writing half of a split [`CanSocket`].
+///
+/// This is created by [`CanSocket::into_split()`] or [`CanSocket::split()`],
+/// and can only be used to send frames.
+#[derive(Debug)]
+pub struct CanWriter(CanSocket);
+
+impl CanWriter {
+    /// Writes a single frame.
+    pub fn write_frame<F>(&self, frame: &F) -> IoResult<()>
+    where
+        F: Into<CanFrame> + AsPtr,
+    {
+        self.0.write_frame(frame)
+    }
+
+    /// Writes a single frame, retrying until it is sent or fails.
+    pub fn write_frame_insist<F>(&self, frame: &F) -> IoResult<()>
+    where
+        F: Into<CanFrame> + AsPtr,
+    {
+        self.0.write_frame_insist(frame)
+    }
+}
+
+impl AsRawFd for CanWriter {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
 // ===== CanFdSocket =====
 
 /// A socket for CAN FD devices.
 ///
 /// This can transmit and receive CAN 2.0 frames with up to 8-bytes of data,
 /// or CAN Flexible Data (FD) frames with up to 64-bytes of data.
+///
+/// Opening the socket on an interface that can't carry FD frames, as
+/// determined by its MTU, fails with an `Unsupported` error. Use `CanBus`
+/// to fall back to a classic socket on such an interface.
 #[allow(missing_copy_implementations)]
 #[derive(Debug)]
-pub struct CanFdSocket(socket2::Socket);
+pub struct CanFdSocket(socket2::Socket, AtomicU32);
 
 impl CanFdSocket {
+    // Wraps a raw socket, which isn't connected.
+    fn from_socket(sock: socket2::Socket) -> Self {
+        Self(sock, AtomicU32::new(0))
+    }
+
+    /// Creates a new handle to the same socket.
+    ///
+    /// The new handle has a duplicate of the file descriptor, so it shares
+    /// the receive queue, filters, and other options of the original. It
+    /// starts out connected to the same interface as the original, if
+    /// any, but connecting either one afterwards doesn't affect the other.
+    pub fn try_clone(&self) -> IoResult<Self> {
+        let sock = self.0.try_clone()?;
+        Ok(Self(sock, AtomicU32::new(self.1.load(Ordering::Relaxed))))
+    }
+
+    /// Connects the socket to the interface with the specified index.
+    ///
+    /// This gives the socket a default destination, so that later calls
+    /// to `write_frame()`, and the other writes built on it, send both
+    /// classic and FD frames to that interface without any per-call
+    /// addressing. Reads are not affected.
+    ///
+    /// As with `CanSocket::connect()`, the destination is kept in this
+    /// handle, and each write addresses the frame with `sendto(2)`. No
+    /// socket options are changed, so the socket stays in FD mode. An
+    /// index of zero disconnects the socket.
+    pub fn connect(&self, ifindex: u32) {
+        self.1.store(ifindex, Ordering::Relaxed);
+    }
+
+    /// Gets the index of the interface the socket is connected to, if any.
+    pub fn peer_ifindex(&self) -> Option<u32> {
+        match self.1.load(Ordering::Relaxed) {
+            0 => None,
+            ifindex => Some(ifindex),
+        }
+    }
+
     // Enable or disable FD mode on a socket.
     fn set_fd_mode(sock: socket2::Socket, enable: bool) -> IoResult<socket2::Socket> {
         let enable = enable as c_int;
@@ -606,21 +2473,60 @@ impl CanFdSocket {
     /// This might be either type of CAN frame, a classic CAN 2.0 frame
     /// or an FD frame.
     pub fn read_raw_frame(&self) -> IoResult<CanRawFrame> {
+        self.recv_raw_frame(0, None).map(|(frame, _)| frame)
+    }
+
+    /// Reads a raw CAN frame from the socket.
+    ///
+    /// This is the same as `read_raw_frame()`, and matches
+    /// `CanSocket::read_raw()`, for code that handles the raw frames from
+    /// either type of socket.
+    pub fn read_raw(&self) -> IoResult<CanRawFrame> {
+        self.read_raw_frame()
+    }
+
+    /// Receives a raw CAN frame from the socket with the specified
+    /// `recv(2)` flags.
+    fn recv_raw_frame(
+        &self,
+        flags: c_int,
+        ts: Option<&mut FrameTimestamps>,
+    ) -> IoResult<(CanRawFrame, RecvFlags)> {
         let mut fdframe = canfd_frame_default();
 
-        match self.as_raw_socket().read(as_bytes_mut(&mut fdframe))? {
+        match raw_recv_timestamped(self.as_raw_fd(), as_bytes_mut(&mut fdframe), flags, ts)? {
             // If we only get 'can_frame' number of bytes, then the return is,
             // by definition, a can_frame, so we just copy the bytes into the
             // proper type.
-            CAN_MTU => {
+            (CAN_MTU, rflags) => {
                 let mut frame = can_frame_default();
                 as_bytes_mut(&mut frame)[..CAN_MTU].copy_from_slice(&as_bytes(&fdframe)[..CAN_MTU]);
-                Ok(frame.into())
+                Ok((frame.into(), rflags))
             }
-            CANFD_MTU => Ok(fdframe.into()),
-            _ => Err(IoError::last_os_error()),
+            (CANFD_MTU, rflags) => Ok((fdframe.into(), rflags)),
+            (0, _) => Err(IoErrorKind::UnexpectedEof.into()),
+            _ => Err(IoErrorKind::InvalidData.into()),
         }
     }
+
+    /// Writes a low-level libc classic `can_frame` to the socket.
+    ///
+    /// This is a lower-level escape hatch for applications, such as those
+    /// sharing frames with C code, that already have the libc struct. The
+    /// bytes of the frame are written as-is, without any of the checks or
+    /// conversions of the higher-level frame types.
+    pub fn write_raw_frame(&self, frame: &libc::can_frame) -> IoResult<()> {
+        self.as_raw_socket().write_all(as_bytes(frame))
+    }
+
+    /// Writes a low-level libc `canfd_frame` to the socket.
+    ///
+    /// Like `write_raw_frame()`, this writes the bytes of the libc struct
+    /// as-is, so it is up to the caller to make sure that the frame is
+    /// valid, such as having a proper length for the data.
+    pub fn write_raw_fd_frame(&self, frame: &libc::canfd_frame) -> IoResult<()> {
+        self.as_raw_socket().write_all(as_bytes(frame))
+    }
 }
 
 impl Socket for CanFdSocket {
@@ -628,10 +2534,17 @@ impl Socket for CanFdSocket {
     type FrameType = CanAnyFrame;
 
     /// Opens the FD socket by interface index.
-    fn open_addr(addr: &CanAddr) -> IoResult<Self> {
-        raw_open_socket(addr)
-            .and_then(|sock| Self::set_fd_mode(sock, true))
-            .map(Self)
+    ///
+    /// This checks the MTU of the interface first, and fails with an
+    /// `Unsupported` error if the interface can't carry FD frames, rather
+    /// than leaving writes of FD frames to fail later. A socket bound to
+    /// all interfaces, with an index of zero, isn't checked.
+    fn open_addr_with(addr: &CanAddr, opts: &OpenOptions) -> IoResult<Self> {
+        let sock = raw_open_socket(addr, opts)?;
+        if addr.ifindex() != 0 {
+            raw_check_fd_mtu(sock.as_raw_fd(), &addr.iface_name()?)?;
+        }
+        Self::set_fd_mode(sock, true).map(Self::from_socket)
     }
 
     /// Gets a shared reference to the underlying socket object
@@ -645,30 +2558,70 @@ impl Socket for CanFdSocket {
     }
 
     /// Writes any type of CAN frame to the socket.
+    ///
+    /// If the socket is connected, the frame is sent to that interface.
     fn write_frame<F>(&self, frame: &F) -> IoResult<()>
     where
         F: Into<Self::FrameType> + AsPtr,
     {
-        self.as_raw_socket().write_all(frame.as_bytes())
+        match self.peer_ifindex() {
+            Some(ifindex) => self.write_frame_to(frame, &CanAddr::new(ifindex)),
+            None => self.as_raw_socket().write_all(frame.as_bytes()),
+        }
     }
 
     /// Reads either type of CAN frame from the socket.
     fn read_frame(&self) -> IoResult<CanAnyFrame> {
-        let mut fdframe = canfd_frame_default();
+        self.read_raw_frame().map(CanAnyFrame::from)
+    }
 
-        match self.as_raw_socket().read(as_bytes_mut(&mut fdframe))? {
-            // If we only get 'can_frame' number of bytes, then the return is,
-            // by definition, a can_frame, so we just copy the bytes into the
-            // proper type.
-            CAN_MTU => {
+    /// Reads either type of CAN frame from the socket, with its flags.
+    fn read_frame_with_flags(&self) -> IoResult<(CanAnyFrame, RecvFlags)> {
+        let (frame, flags) = self.recv_raw_frame(0, None)?;
+        Ok((frame.into(), flags))
+    }
+
+    /// Reads either type of CAN frame from the socket, with its receive
+    /// times.
+    fn read_frame_with_timestamps(&self) -> IoResult<(CanAnyFrame, FrameTimestamps)> {
+        let mut ts = FrameTimestamps::default();
+        let (frame, _) = self.recv_raw_frame(0, Some(&mut ts))?;
+        Ok((frame.into(), ts))
+    }
+
+    /// Reads either type of CAN frame from the socket, with its ancillary
+    /// data.
+    fn read_frame_with_ancillary<'a>(
+        &self,
+        cmsg_buf: &'a mut [u8],
+    ) -> IoResult<(CanAnyFrame, AncillaryData<'a>)> {
+        let mut fdframe = canfd_frame_default();
+        match raw_recv_ancillary(self.as_raw_fd(), as_bytes_mut(&mut fdframe), 0, cmsg_buf)? {
+            (CAN_MTU, data) => {
                 let mut frame = can_frame_default();
-                as_bytes_mut(&mut frame)[..CAN_MTU].copy_from_slice(&as_bytes(&fdframe)[..CAN_MTU]);
-                Ok(CanFrame::from(frame).into())
+                as_bytes_mut(&mut frame).copy_from_slice(&as_bytes(&fdframe)[..CAN_MTU]);
+                Ok((frame.into(), data))
             }
-            CANFD_MTU => Ok(CanFdFrame::from(fdframe).into()),
-            _ => Err(IoError::last_os_error()),
+            (CANFD_MTU, data) => Ok((fdframe.into(), data)),
+            (0, _) => Err(IoErrorKind::UnexpectedEof.into()),
+            _ => Err(IoErrorKind::InvalidData.into()),
+        }
+    }
+
+    /// Reads either type of CAN frame from the socket, if one is queued.
+    fn try_read_frame(&self) -> IoResult<Option<CanAnyFrame>> {
+        match self.recv_raw_frame(libc::MSG_DONTWAIT, None) {
+            Ok((frame, _)) => Ok(Some(frame.into())),
+            Err(err) if err.kind() == IoErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err),
         }
     }
+
+    /// Peeks at the next frame of either type on the socket.
+    fn peek_frame(&self) -> IoResult<CanAnyFrame> {
+        let (frame, _) = self.recv_raw_frame(libc::MSG_PEEK, None)?;
+        Ok(frame.into())
+    }
 }
 
 impl SocketOptions for CanFdSocket {}
@@ -682,7 +2635,7 @@ impl AsRawFd for CanFdSocket {
 
 impl From<OwnedFd> for CanFdSocket {
     fn from(fd: OwnedFd) -> CanFdSocket {
-        Self(socket2::Socket::from(fd))
+        Self::from_socket(socket2::Socket::from(fd))
     }
 }
 
@@ -714,6 +2667,338 @@ impl Write for CanFdSocket {
     }
 }
 
+// ===== UnboundSocket =====
+
+/// A raw CAN socket that is created, but not yet bound to an interface.
+///
+/// This splits opening a socket into two steps, so that the socket can
+/// be created in one context and bound in another, such as a daemon that
+/// creates its sockets while it has elevated privileges, and binds them
+/// after dropping them. Options, like filters, can also be set on the
+/// socket before it's bound, so that it never receives any unwanted
+/// frames.
+///
+/// ```no_run
+/// use socketcan::{CanAddr, SocketOptions, UnboundSocket};
+///
+/// let sock = UnboundSocket::new().unwrap();
+/// sock.set_error_filter_accept_all().unwrap();
+/// // ...later...
+/// let sock = sock.bind(&CanAddr::from_iface("can0").unwrap()).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct UnboundSocket(socket2::Socket);
+
+impl UnboundSocket {
+    /// Creates an unbound raw CAN socket with the default options.
+    pub fn new() -> IoResult<Self> {
+        Self::with_options(&OpenOptions::new())
+    }
+
+    /// Creates an unbound raw CAN socket with the specified options.
+    pub fn with_options(opts: &OpenOptions) -> IoResult<Self> {
+        raw_create_socket(opts).map(Self)
+    }
+
+    /// Binds the socket to the interface with the specified address,
+    /// giving a socket for classic CAN 2.0 frames.
+    pub fn bind(self, addr: &CanAddr) -> IoResult<CanSocket> {
+        raw_bind_socket(&self.0, addr)?;
+        Ok(CanSocket::from_socket(self.0))
+    }
+
+    /// Binds the socket to the interface with the specified address,
+    /// giving a socket for classic and FD frames.
+    pub fn bind_fd(self, addr: &CanAddr) -> IoResult<CanFdSocket> {
+        let sock = CanFdSocket::set_fd_mode(self.0, true)?;
+        raw_bind_socket(&sock, addr)?;
+        Ok(CanFdSocket::from_socket(sock))
+    }
+}
+
+impl AsRawFd for UnboundSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl SocketOptions for UnboundSocket {}
+
+// ===== CanBus =====
+
+/// A socket on a CAN bus, which uses FD frames if the interface supports
+/// them.
+///
+/// This hides the split between the classic `CanSocket` and the
+/// `CanFdSocket` for applications that just want to send and receive
+/// frames, whatever the bus. When it is opened, it checks the MTU of the
+/// interface, and opens an FD socket if the interface can carry FD frames,
+/// or a classic socket otherwise. Either way, frames are sent and received
+/// as `CanAnyFrame`.
+#[derive(Debug)]
+pub enum CanBus {
+    /// A bus that only supports classic CAN 2.0 frames
+    Classic(CanSocket),
+    /// A bus that supports FD frames, as well as classic ones
+    Fd(CanFdSocket),
+}
+
+impl CanBus {
+    /// Opens a socket on the named CAN interface, such as "can0".
+    pub fn open(ifname: &str) -> IoResult<Self> {
+        // Probe and upgrade the one socket, rather than reopening it, so
+        // that no frames are missed in between.
+        let sock = CanSocket::open(ifname)?;
+        match raw_iface_mtu(sock.as_raw_fd(), ifname)? {
+            mtu if mtu >= CANFD_MTU => CanFdSocket::set_fd_mode(sock.0, true)
+                .map(|sock| Self::Fd(CanFdSocket::from_socket(sock))),
+            _ => Ok(Self::Classic(sock)),
+        }
+    }
+
+    /// Determines if the bus supports FD frames.
+    pub fn is_fd(&self) -> bool {
+        matches!(self, Self::Fd(_))
+    }
+
+    /// Blocking read of a single frame from the bus.
+    pub fn recv(&self) -> IoResult<CanAnyFrame> {
+        match self {
+            Self::Classic(sock) => sock.read_frame().map(CanAnyFrame::from),
+            Self::Fd(sock) => sock.read_frame(),
+        }
+    }
+
+    /// Writes a single frame to the bus.
+    ///
+    /// Trying to send an FD frame on a classic bus returns an error with
+    /// the kind `InvalidInput`.
+    pub fn send(&self, frame: &CanAnyFrame) -> IoResult<()> {
+        match (self, frame) {
+            (Self::Fd(sock), frame) => sock.write_frame(frame),
+            (Self::Classic(sock), CanAnyFrame::Normal(frame)) => sock.write_frame(frame),
+            (Self::Classic(sock), CanAnyFrame::Remote(frame)) => sock.write_frame(frame),
+            (Self::Classic(sock), CanAnyFrame::Error(frame)) => sock.write_frame(frame),
+            (Self::Classic(_), CanAnyFrame::Fd(_)) => Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "FD frames are not supported by the interface",
+            )),
+        }
+    }
+}
+
+impl AsRawFd for CanBus {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Self::Classic(sock) => sock.as_raw_fd(),
+            Self::Fd(sock) => sock.as_raw_fd(),
+        }
+    }
+}
+
+impl SocketOptions for CanBus {}
+
+// ===== ReadShutdown =====
+
+/// A handle to shut down the reads on sockets from another thread.
+///
+/// A raw CAN socket can't be shut down with `shutdown(2)`, so this stands
+/// in for it. Reads done with [`Socket::read_frame_with_shutdown()`] wait
+/// on the handle as well as the socket, and return an error of the kind
+/// `UnexpectedEof` once it is shut down. The handle can be cloned, and
+/// shared by any number of sockets and threads.
+///
+/// ```no_run
+/// use socketcan::{CanSocket, ReadShutdown, Socket};
+/// use std::thread;
+///
+/// let sock = CanSocket::open("vcan0").unwrap();
+/// let shutdown = ReadShutdown::new().unwrap();
+///
+/// let reader = thread::spawn({
+///     let shutdown = shutdown.clone();
+///     move || {
+///         while let Ok(frame) = sock.read_frame_with_shutdown(&shutdown) {
+///             println!("{:X}", frame);
+///         }
+///     }
+/// });
+///
+/// // ...later...
+/// shutdown.shutdown().unwrap();
+/// reader.join().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReadShutdown(Arc<OwnedFd>);
+
+impl ReadShutdown {
+    /// Creates a new handle, which is not yet shut down.
+    ///
+    /// This is backed by an `eventfd(2)`.
+    pub fn new() -> IoResult<Self> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(IoError::last_os_error());
+        }
+        Ok(Self(Arc::new(unsafe { OwnedFd::from_raw_fd(fd) })))
+    }
+
+    /// Shuts down the reads waiting on this handle.
+    ///
+    /// This wakes every reader that's blocked on the handle, and makes all
+    /// later reads with it fail. It can't be undone.
+    pub fn shutdown(&self) -> IoResult<()> {
+        let val = 1u64;
+        let ret = unsafe {
+            libc::write(
+                self.as_raw_fd(),
+                &val as *const _ as *const c_void,
+                size_of::<u64>(),
+            )
+        };
+        match ret {
+            n if n < 0 => Err(IoError::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Determines if the handle has been shut down.
+    pub fn is_shutdown(&self) -> IoResult<bool> {
+        use nix::poll::{poll, PollFd, PollFlags};
+        let pollfd = PollFd::new(self.as_raw_fd(), PollFlags::POLLIN);
+        Ok(poll(&mut [pollfd], 0)? != 0)
+    }
+}
+
+impl AsRawFd for ReadShutdown {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+// ===== FrameBuf =====
+
+/// A reusable buffer for reading batches of classic CAN frames.
+///
+/// This reads all of the frames that are queued on a socket, up to the
+/// capacity of the buffer, with a single `recvmmsg(2)` system call. The
+/// buffer is allocated once, when it's created, and then reused on every
+/// read, so that an application can receive frames in a steady state
+/// without any further allocations.
+///
+/// ```no_run
+/// use socketcan::{CanSocket, FrameBuf, Socket};
+///
+/// let sock = CanSocket::open("vcan0").unwrap();
+/// let mut buf = FrameBuf::new(64);
+///
+/// loop {
+///     for frame in buf.fill(&sock).unwrap() {
+///         println!("{:X}", frame);
+///     }
+/// }
+/// ```
+pub struct FrameBuf {
+    raw: Vec<libc::can_frame>,
+    frames: Vec<CanFrame>,
+    iovs: Vec<libc::iovec>,
+    hdrs: Vec<libc::mmsghdr>,
+}
+
+// The buffer only contains raw pointers into its own storage, which are
+// rewritten on every call to `fill()`, and never dereferenced outside of it.
+unsafe impl Send for FrameBuf {}
+
+impl FrameBuf {
+    /// Creates a new buffer that can hold up to `capacity` frames.
+    ///
+    /// The buffer will always hold at least one frame.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            raw: vec![can_frame_default(); capacity],
+            frames: Vec::with_capacity(capacity),
+            iovs: Vec::with_capacity(capacity),
+            hdrs: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Gets the maximum number of frames that can be read in one call.
+    pub fn capacity(&self) -> usize {
+        self.raw.len()
+    }
+
+    /// Blocking read of a batch of frames from the socket.
+    ///
+    /// This blocks until at least one frame is received, then returns all
+    /// of the frames that were queued on the socket, up to the capacity of
+    /// the buffer. The read honors any read timeout set on the socket.
+    ///
+    /// The returned slice borrows the internal buffer, and is valid until
+    /// the next call to `fill()`.
+    pub fn fill(&mut self, sock: &CanSocket) -> IoResult<&[CanFrame]> {
+        self.frames.clear();
+        let n = raw_recv_mmsg(
+            sock.as_raw_fd(),
+            &mut self.raw,
+            &mut self.iovs,
+            &mut self.hdrs,
+        )?;
+        self.frames
+            .extend(self.raw[..n].iter().map(|frame| CanFrame::from(*frame)));
+        Ok(&self.frames)
+    }
+
+    /// Gets the frames from the most recent call to `fill()`.
+    pub fn frames(&self) -> &[CanFrame] {
+        &self.frames
+    }
+
+    /// Consumes the buffer, giving the frames from the most recent call
+    /// to `fill()`, without copying them.
+    pub fn into_frames(self) -> Vec<CanFrame> {
+        self.frames
+    }
+}
+
+impl fmt::Debug for FrameBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FrameBuf")
+            .field("capacity", &self.capacity())
+            .field("frames", &self.frames)
+            .finish()
+    }
+}
+
+// ===== Errors =====
+
+/// A blocking iterator over the errors reported by a socket.
+///
+/// This is created with [`Socket::errors()`].
+#[derive(Debug)]
+pub struct Errors<'a, S> {
+    sock: &'a S,
+}
+
+impl<S> Iterator for Errors<'_, S>
+where
+    S: Socket,
+    S::FrameType: Into<CanAnyFrame>,
+{
+    type Item = IoResult<CanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.sock.read_frame().map(Into::into) {
+                Ok(CanAnyFrame::Error(frame)) => return Some(Ok(frame.into())),
+                Ok(_) => (),
+                Err(err) if err.kind() == IoErrorKind::UnexpectedEof => return None,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
 // ===== CanFilter =====
 
 /// The CAN filter defines which ID's can be accepted on a socket.
@@ -740,6 +3025,20 @@ impl CanFilter {
     pub fn new_inverted(id: canid_t, mask: canid_t) -> Self {
         Self::new(id | libc::CAN_INV_FILTER, mask)
     }
+
+    /// Determines if the filter is inverted, so that it matches the frames
+    /// that don't match its ID and mask.
+    pub fn is_inverted(&self) -> bool {
+        self.0.can_id & libc::CAN_INV_FILTER != 0
+    }
+
+    /// Toggles the inversion of the filter.
+    ///
+    /// An inverted filter becomes a normal one, and vice versa, keeping
+    /// the same ID and mask.
+    pub fn invert(&mut self) {
+        self.0.can_id ^= libc::CAN_INV_FILTER;
+    }
 }
 
 impl From<libc::can_filter> for CanFilter {
@@ -759,3 +3058,614 @@ impl AsRef<libc::can_filter> for CanFilter {
         &self.0
     }
 }
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::net::Shutdown;
+
+    // Creates a connected pair of datagram sockets that stand in for a CAN
+    // socket and the kernel side of it.
+    pub(crate) fn socket_pair() -> (CanSocket, socket2::Socket) {
+        let (a, b) =
+            socket2::Socket::pair(socket2::Domain::UNIX, socket2::Type::SEQPACKET, None).unwrap();
+        (CanSocket::from(OwnedFd::from(a)), b)
+    }
+
+    #[test]
+    fn test_read_eof_vs_error() {
+        let frame = CanFrame::from(can_frame_default());
+
+        let (sock, peer) = socket_pair();
+        peer.send(frame.as_bytes()).unwrap();
+        peer.send(&[0u8; 5]).unwrap();
+
+        assert_eq!(frame, sock.read_frame().unwrap());
+        let err = sock.read_frame().unwrap_err();
+        assert_eq!(IoErrorKind::InvalidData, err.kind());
+
+        // A quiet socket is not the end of the stream
+        sock.set_nonblocking(true).unwrap();
+        assert!(sock.read_frame().should_retry());
+
+        // ...but a read of zero bytes is. A raw CAN socket never returns
+        // one, so this only checks the mapping, using a stand-in socket.
+        peer.shutdown(Shutdown::Write).unwrap();
+        let err = sock.read_frame().unwrap_err();
+        assert_eq!(IoErrorKind::UnexpectedEof, err.kind());
+        assert!(!err.should_retry());
+    }
+
+    #[test]
+    fn test_clock_time() {
+        const SEC: Duration = Duration::from_secs(1);
+
+        let now = raw_clock_time_ns(libc::CLOCK_MONOTONIC, Instant::now()).unwrap();
+        let t = raw_clock_time_ns(libc::CLOCK_MONOTONIC, Instant::now() + SEC).unwrap();
+        assert!(t >= now + SEC.as_nanos() as u64);
+        assert!(t < now + 2 * SEC.as_nanos() as u64);
+
+        // Instants in the past are clamped to the current time.
+        let past = Instant::now().checked_sub(SEC).unwrap_or_else(Instant::now);
+        assert!(raw_clock_time_ns(libc::CLOCK_MONOTONIC, past).unwrap() >= now);
+    }
+
+    #[test]
+    fn test_cmsg_timestamp() {
+        let tv = libc::timeval {
+            tv_sec: 1_700_000_000,
+            tv_usec: 123_456,
+        };
+
+        let mut control = [0u64; 8];
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        assert!(raw_cmsg_timestamp(&hdr).is_none());
+
+        hdr.msg_control = control.as_mut_ptr() as *mut c_void;
+        hdr.msg_controllen = unsafe { libc::CMSG_SPACE(size_of_val(&tv) as u32) } as _;
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&hdr);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_TIMESTAMP;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(size_of_val(&tv) as u32) as _;
+            ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::timeval, tv);
+        }
+
+        let ts = raw_cmsg_timestamp(&hdr).unwrap();
+        let expected = SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_000);
+        assert_eq!(expected, ts);
+
+        let tspec = libc::timespec {
+            tv_sec: 1_700_000_000,
+            tv_nsec: 123_456_789,
+        };
+        hdr.msg_controllen = unsafe { libc::CMSG_SPACE(size_of_val(&tspec) as u32) } as _;
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&hdr);
+            (*cmsg).cmsg_type = libc::SCM_TIMESTAMPNS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(size_of_val(&tspec) as u32) as _;
+            ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::timespec, tspec);
+        }
+
+        let ts = raw_cmsg_timestamp(&hdr).unwrap();
+        let expected = SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+        assert_eq!(expected, ts);
+    }
+
+    #[test]
+    fn test_iface_name_from_path() {
+        let name = iface_name_from_path(Path::new("/sys/class/net/can0")).unwrap();
+        assert_eq!("can0", name);
+
+        let name = iface_name_from_path(Path::new("/sys/class/net/vcan1/")).unwrap();
+        assert_eq!("vcan1", name);
+
+        assert!(iface_name_from_path(Path::new("/")).is_err());
+        assert!(iface_name_from_path(Path::new("/sys/class/net/..")).is_err());
+    }
+
+    #[test]
+    fn test_open_options() {
+        let ty = c_int::from(OpenOptions::default().socket_type());
+        assert_eq!(libc::SOCK_RAW | libc::SOCK_CLOEXEC, ty);
+
+        let opts = OpenOptions::new().cloexec(false).nonblocking(true);
+        let ty = c_int::from(opts.socket_type());
+        assert_eq!(libc::SOCK_RAW | libc::SOCK_NONBLOCK, ty);
+    }
+
+    #[test]
+    fn test_try_from_fd() {
+        let (a, _b) =
+            socket2::Socket::pair(socket2::Domain::UNIX, socket2::Type::SEQPACKET, None).unwrap();
+        let err = CanSocket::try_from_fd(OwnedFd::from(a)).unwrap_err();
+        assert_eq!(IoErrorKind::InvalidInput, err.kind());
+
+        // Not a socket at all
+        let file = std::fs::File::open("/dev/null").unwrap();
+        assert!(CanSocket::try_from_fd(OwnedFd::from(file)).is_err());
+    }
+
+    #[test]
+    fn test_try_read_frame() {
+        let frame = CanFrame::from(can_frame_default());
+
+        let (sock, peer) = socket_pair();
+        assert!(sock.try_read_frame().unwrap().is_none());
+
+        peer.send(frame.as_bytes()).unwrap();
+        assert_eq!(Some(frame), sock.try_read_frame().unwrap());
+        assert!(sock.try_read_frame().unwrap().is_none());
+        assert!(!sock.nonblocking().unwrap());
+    }
+
+    #[test]
+    fn test_split() {
+        let frame = CanFrame::from(can_frame_default());
+
+        let (sock, peer) = socket_pair();
+        let (rdr, wtr) = sock.into_split().unwrap();
+        assert_ne!(rdr.as_raw_fd(), wtr.as_raw_fd());
+
+        peer.send(frame.as_bytes()).unwrap();
+        assert_eq!(frame, rdr.read_frame().unwrap());
+
+        wtr.write_frame(&frame).unwrap();
+        let mut buf = [0u8; CAN_MTU];
+        assert_eq!(CAN_MTU, (&peer).read(&mut buf).unwrap());
+        assert_eq!(frame.as_bytes(), &buf[..]);
+    }
+
+    #[test]
+    fn test_recv_lowat() {
+        let (sock, _peer) = socket_pair();
+        assert_eq!(1, sock.recv_lowat().unwrap());
+        sock.set_recv_lowat(4 * CAN_MTU).unwrap();
+        assert_eq!(4 * CAN_MTU, sock.recv_lowat().unwrap());
+    }
+
+    #[test]
+    fn test_read_raw() {
+        let mut frame = can_frame_default();
+        frame.can_id = 0x123;
+        frame.can_dlc = 1;
+
+        let (sock, peer) = socket_pair();
+        peer.send(as_bytes(&frame)).unwrap();
+        match sock.read_raw().unwrap() {
+            CanRawFrame::Classic(rx) => assert_eq!(as_bytes(&frame), as_bytes(&rx)),
+            CanRawFrame::Fd(_) => panic!("expected a classic frame"),
+        }
+    }
+
+    #[test]
+    fn test_write_frame_insist_with() {
+        let frame = CanFrame::from(can_frame_default());
+
+        let (sock, peer) = socket_pair();
+        sock.set_nonblocking(true).unwrap();
+        peer.set_nonblocking(true).unwrap();
+
+        let policies = [
+            RetryPolicy::Poll,
+            RetryPolicy::Backoff {
+                initial: Duration::from_millis(1),
+                max: Duration::from_millis(4),
+            },
+        ];
+        for policy in policies {
+            while sock.write_frame(&frame).is_ok() {}
+
+            // Empty the queue a little while after we start
+            let reader = thread::spawn({
+                let peer = peer.try_clone().unwrap();
+                move || {
+                    thread::sleep(Duration::from_millis(20));
+                    let mut buf = [0u8; CAN_MTU];
+                    while (&peer).read(&mut buf).is_ok() {}
+                }
+            });
+            sock.write_frame_insist_with(&frame, policy).unwrap();
+            reader.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_read_frame_bytes() {
+        let (sock, peer) = socket_pair();
+
+        (&peer).write_all(&[0xAA; CANFD_MTU]).unwrap();
+        let mut buf = [0u8; CAN_MTU];
+        let (n, flags) = sock.read_frame_bytes(&mut buf).unwrap();
+        assert_eq!(CANFD_MTU, n);
+        assert!(flags.contains(RecvFlags::TRUNCATED));
+        assert_eq!([0xAA; CAN_MTU], buf);
+
+        (&peer).write_all(&[0x55; CAN_MTU]).unwrap();
+        let mut buf = [0u8; CANFD_MTU];
+        let (n, flags) = sock.read_frame_bytes(&mut buf).unwrap();
+        assert_eq!(CAN_MTU, n);
+        assert!(!flags.contains(RecvFlags::TRUNCATED));
+    }
+
+    #[test]
+    fn test_filter_invert() {
+        let mut filter = CanFilter::new(0x123, 0x7FF);
+        assert!(!filter.is_inverted());
+
+        filter.invert();
+        assert!(filter.is_inverted());
+        assert_eq!(CanFilter::new_inverted(0x123, 0x7FF), filter);
+
+        filter.invert();
+        assert!(!filter.is_inverted());
+        assert_eq!(CanFilter::new(0x123, 0x7FF), filter);
+    }
+
+    #[test]
+    fn test_read_n_frames() {
+        let (sock, peer) = socket_pair();
+        let timeout = Duration::from_millis(20);
+        let frame = CanFrame::from(can_frame_default());
+
+        for _ in 0..3 {
+            peer.send(frame.as_bytes()).unwrap();
+        }
+        assert_eq!(vec![frame; 2], sock.read_n_frames(2, timeout).unwrap());
+
+        // Times out with only what's left
+        assert_eq!(vec![frame], sock.read_n_frames(2, timeout).unwrap());
+        assert!(sock.read_n_frames(2, timeout).unwrap().is_empty());
+        assert!(sock.read_n_frames(0, timeout).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_count_errors() {
+        let (sock, peer) = socket_pair();
+        let bus_off = crate::CanErrorFrame::new_error(0x0040, &[]).unwrap();
+        let no_ack = crate::CanErrorFrame::new_error(0x0060, &[]).unwrap();
+
+        peer.send(bus_off.as_bytes()).unwrap();
+        peer.send(CanFrame::from(can_frame_default()).as_bytes())
+            .unwrap();
+        peer.send(no_ack.as_bytes()).unwrap();
+
+        let counter = sock.count_errors(Duration::from_millis(20)).unwrap();
+        assert_eq!(2, counter.frames());
+        assert_eq!(2, counter[ErrClass::BusOff]);
+        assert_eq!(1, counter[ErrClass::NoAck]);
+        assert_eq!(0, counter[ErrClass::BusError]);
+    }
+
+    #[test]
+    fn test_request_response() {
+        let (sock, peer) = socket_pair();
+        let timeout = Duration::from_millis(20);
+        let request = CanFrame::from_raw_id(0x7E0, &[0x02, 0x10, 0x01]).unwrap();
+
+        // Unrelated traffic is skipped, up to the reply
+        let other = CanFrame::from_raw_id(0x100, &[1]).unwrap();
+        let reply = CanFrame::from_raw_id(0x7E8, &[0x02, 0x50, 0x01]).unwrap();
+        peer.send(other.as_bytes()).unwrap();
+        peer.send(reply.as_bytes()).unwrap();
+        assert_eq!(
+            reply,
+            sock.request_response(&request, 0x7E8, timeout).unwrap()
+        );
+
+        let mut buf = [0u8; CAN_MTU];
+        assert_eq!(CAN_MTU, (&peer).read(&mut buf).unwrap());
+        assert_eq!(request.as_bytes(), &buf[..]);
+
+        peer.send(other.as_bytes()).unwrap();
+        let err = sock.request_response(&request, 0x7E8, timeout).unwrap_err();
+        assert_eq!(IoErrorKind::TimedOut, err.kind());
+    }
+
+    #[test]
+    fn test_drain() {
+        let frame = CanFrame::from(can_frame_default());
+
+        let (sock, peer) = socket_pair();
+        assert_eq!(0, sock.drain().unwrap());
+
+        for _ in 0..3 {
+            peer.send(frame.as_bytes()).unwrap();
+        }
+        assert_eq!(3, sock.drain().unwrap());
+        assert_eq!(0, sock.drain().unwrap());
+        assert!(!sock.nonblocking().unwrap());
+
+        peer.send(frame.as_bytes()).unwrap();
+        assert_eq!(frame, sock.read_frame().unwrap());
+    }
+
+    #[test]
+    fn test_read_frame_with_shutdown() {
+        let frame = CanFrame::from(can_frame_default());
+
+        let (sock, peer) = socket_pair();
+        let shutdown = ReadShutdown::new().unwrap();
+        assert!(!shutdown.is_shutdown().unwrap());
+
+        peer.send(frame.as_bytes()).unwrap();
+        assert_eq!(frame, sock.read_frame_with_shutdown(&shutdown).unwrap());
+
+        // A blocked reader is woken from another thread
+        let reader = thread::spawn({
+            let shutdown = shutdown.clone();
+            move || sock.read_frame_with_shutdown(&shutdown)
+        });
+        thread::sleep(Duration::from_millis(20));
+        shutdown.shutdown().unwrap();
+
+        let err = reader.join().unwrap().unwrap_err();
+        assert_eq!(IoErrorKind::UnexpectedEof, err.kind());
+        assert!(!err.should_retry());
+        assert!(shutdown.is_shutdown().unwrap());
+    }
+
+    #[test]
+    fn test_connect() {
+        let frame = CanFrame::from(can_frame_default());
+
+        let (sock, peer) = socket_pair();
+        assert_eq!(None, sock.peer_ifindex());
+
+        sock.connect(3);
+        assert_eq!(Some(3), sock.peer_ifindex());
+        let clone = sock.try_clone().unwrap();
+        assert_eq!(Some(3), clone.peer_ifindex());
+
+        // The stand-in socket ignores the address on each frame
+        let mut buf = [0u8; CAN_MTU];
+        sock.write_frame(&frame).unwrap();
+        assert_eq!(CAN_MTU, (&peer).read(&mut buf).unwrap());
+        assert_eq!(frame.as_bytes(), &buf[..]);
+
+        sock.connect(0);
+        assert_eq!(None, sock.peer_ifindex());
+        assert_eq!(Some(3), clone.peer_ifindex());
+    }
+
+    #[test]
+    fn test_has_pending() {
+        let frame = CanFrame::from(can_frame_default());
+
+        let (sock, peer) = socket_pair();
+        assert!(!sock.has_pending().unwrap());
+
+        peer.send(frame.as_bytes()).unwrap();
+        assert!(sock.has_pending().unwrap());
+        assert_eq!(frame, sock.read_frame().unwrap());
+        assert!(!sock.has_pending().unwrap());
+    }
+
+    #[test]
+    fn test_errors_iter() {
+        use crate::CanErrorFrame;
+
+        let data = CanFrame::from(can_frame_default());
+        let err = CanFrame::from(CanErrorFrame::from(CanError::NoAck));
+
+        let (sock, peer) = socket_pair();
+        peer.send(data.as_bytes()).unwrap();
+        peer.send(err.as_bytes()).unwrap();
+        peer.shutdown(Shutdown::Write).unwrap();
+
+        let mut errors = sock.errors();
+        assert!(matches!(errors.next(), Some(Ok(CanError::NoAck))));
+        assert!(errors.next().is_none());
+    }
+
+    #[test]
+    fn test_filter_error() {
+        let err = filter_error(IoError::from_raw_os_error(libc::EINVAL), 600);
+        assert_eq!(IoErrorKind::InvalidInput, err.kind());
+        assert!(err.to_string().contains("600"));
+
+        let err = filter_error(IoError::from_raw_os_error(libc::EINVAL), 10);
+        assert_eq!(Some(libc::EINVAL), err.raw_os_error());
+
+        let err = filter_error(IoError::from_raw_os_error(libc::ENOMEM), 600);
+        assert_eq!(Some(libc::ENOMEM), err.raw_os_error());
+    }
+
+    #[test]
+    fn test_open_error() {
+        // The name lookup fails before any CAN socket is created.
+        let err = CanSocket::open("nosuchcan0").unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("'nosuchcan0'"));
+        assert!(msg.contains("not found"));
+        assert!(msg.contains(&format!("os error {}", libc::ENODEV)));
+
+        let err = open_error(IoError::from_raw_os_error(libc::EPERM), "can0");
+        assert_eq!(IoErrorKind::PermissionDenied, err.kind());
+        assert!(!err.to_string().contains("not found"));
+    }
+
+    #[test]
+    fn test_cmsg_tx_timestamp() {
+        let ts = [
+            libc::timespec {
+                tv_sec: 1_700_000_000,
+                tv_nsec: 123_456_789,
+            },
+            libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+        ];
+
+        let mut control = [0u64; 16];
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        assert!(raw_cmsg_tx_timestamp(&hdr).is_none());
+
+        hdr.msg_control = control.as_mut_ptr() as *mut c_void;
+        hdr.msg_controllen = unsafe { libc::CMSG_SPACE(size_of_val(&ts) as u32) } as _;
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&hdr);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_TIMESTAMPING;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(size_of_val(&ts) as u32) as _;
+            ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut [libc::timespec; 3], ts);
+        }
+
+        let tx = raw_cmsg_tx_timestamp(&hdr).unwrap();
+        let expected = SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+        assert_eq!(Some(expected), tx.software);
+        assert_eq!(None, tx.hardware);
+    }
+
+    #[test]
+    fn test_cmsg_frame_timestamps() {
+        let ts = [
+            libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            },
+            libc::timespec {
+                tv_sec: 1_700_000_000,
+                tv_nsec: 5_000,
+            },
+        ];
+
+        let mut control = [0u64; 16];
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        assert_eq!(FrameTimestamps::default(), raw_cmsg_frame_timestamps(&hdr));
+
+        hdr.msg_control = control.as_mut_ptr() as *mut c_void;
+        hdr.msg_controllen = unsafe { libc::CMSG_SPACE(size_of_val(&ts) as u32) } as _;
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&hdr);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_TIMESTAMPING;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(size_of_val(&ts) as u32) as _;
+            ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut [libc::timespec; 3], ts);
+        }
+
+        let rx = raw_cmsg_frame_timestamps(&hdr);
+        let expected = SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 5_000);
+        assert_eq!(None, rx.software);
+        assert_eq!(Some(expected), rx.hardware);
+    }
+
+    #[test]
+    fn test_timestamping_flags() {
+        use libc::{
+            SOF_TIMESTAMPING_RAW_HARDWARE as RAW_HW, SOF_TIMESTAMPING_RX_HARDWARE as RX_HW,
+            SOF_TIMESTAMPING_RX_SOFTWARE as RX_SW, SOF_TIMESTAMPING_SOFTWARE as SW,
+        };
+
+        assert_eq!(
+            0,
+            timestamping_flags(TimestampConfig::Disabled.rx_flags(), 0)
+        );
+        assert_eq!(
+            RX_SW | SW,
+            timestamping_flags(TimestampConfig::Software.rx_flags(), 0)
+        );
+        assert_eq!(
+            RX_HW | RAW_HW,
+            timestamping_flags(TimestampConfig::Hardware.rx_flags(), 0)
+        );
+
+        let flags = timestamping_flags(0, SOF_TIMESTAMPING_TX_FLAGS);
+        assert_eq!(0, flags & SOF_TIMESTAMPING_RX_FLAGS);
+        assert_eq!(SW | RAW_HW, flags & (SW | RAW_HW));
+    }
+
+    #[test]
+    fn test_read_frame_with_timestamps() {
+        let frame = CanFrame::from(can_frame_default());
+
+        // A UNIX socket doesn't report CAN receive times
+        let (sock, peer) = socket_pair();
+        peer.send(frame.as_bytes()).unwrap();
+        let (rx, ts) = sock.read_frame_with_timestamps().unwrap();
+        assert_eq!(frame, rx);
+        assert_eq!(FrameTimestamps::default(), ts);
+    }
+
+    #[test]
+    fn test_read_frame_with_ancillary() {
+        let frame = CanFrame::from(can_frame_default());
+        let (sock, peer) = socket_pair();
+        sock.set_timestamp_ns(true).unwrap();
+
+        // A UNIX socket reports the receive time, but no CAN source
+        let mut cmsg_buf = [0u8; 128];
+        peer.send(frame.as_bytes()).unwrap();
+        let (rx, data) = sock.read_frame_with_ancillary(&mut cmsg_buf).unwrap();
+        assert_eq!(frame, rx);
+        assert!(!data.is_truncated());
+        assert!(data.ifindex().is_none());
+        assert!(data.dropped().is_none());
+        assert!(data.timestamps().software.is_some());
+
+        let msgs: Vec<_> = data.messages().collect();
+        assert_eq!(1, msgs.len());
+        assert_eq!(libc::SOL_SOCKET, msgs[0].level);
+        assert_eq!(libc::SCM_TIMESTAMPNS, msgs[0].ty);
+        assert_eq!(size_of::<libc::timespec>(), msgs[0].data.len());
+
+        // The control messages don't fit in a tiny buffer
+        let mut cmsg_buf = [0u8; 4];
+        peer.send(frame.as_bytes()).unwrap();
+        let (rx, data) = sock.read_frame_with_ancillary(&mut cmsg_buf).unwrap();
+        assert_eq!(frame, rx);
+        assert!(data.is_truncated());
+        assert_eq!(0, data.messages().count());
+    }
+
+    #[test]
+    fn test_ancillary_dropped() {
+        let mut control = [0u64; 8];
+        let hdr_len = unsafe { libc::CMSG_LEN(size_of::<u32>() as u32) } as usize;
+        let space = unsafe { libc::CMSG_SPACE(size_of::<u32>() as u32) } as usize;
+
+        let mut hdr: libc::msghdr = unsafe { mem::zeroed() };
+        hdr.msg_control = control.as_mut_ptr() as *mut c_void;
+        hdr.msg_controllen = space as _;
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&hdr);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SO_RXQ_OVFL;
+            (*cmsg).cmsg_len = hdr_len as _;
+            ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut u32, 42);
+        }
+
+        let data = AncillaryData {
+            control: &as_bytes(&control)[..space],
+            flags: RecvFlags::empty(),
+            ifindex: Some(3),
+        };
+        assert_eq!(Some(42), data.dropped());
+        assert_eq!(Some(3), data.ifindex());
+        assert_eq!(FrameTimestamps::default(), data.timestamps());
+        assert_eq!(1, data.messages().count());
+    }
+
+    #[test]
+    fn test_iface_mtu() {
+        let sock = socket2::Socket::new(socket2::Domain::UNIX, socket2::Type::DGRAM, None).unwrap();
+        let fd = sock.as_raw_fd();
+        assert!(raw_iface_mtu(fd, "lo").unwrap() > 0);
+
+        assert!(raw_check_fd_mtu(fd, "lo").is_ok());
+
+        let err = raw_iface_mtu(fd, "an_overly_long_ifname").unwrap_err();
+        assert_eq!(IoErrorKind::InvalidInput, err.kind());
+    }
+}
@@ -0,0 +1,167 @@
+// socketcan/src/stats.rs
+//
+// Implements traffic counters for CAN sockets.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Traffic counters for CAN sockets.
+
+use crate::{frame::AsPtr, Frame, IoErrorKind, IoResult, ShouldRetry, Socket, SocketOptions};
+use std::{
+    os::unix::io::{AsRawFd, RawFd},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// A snapshot of the traffic counters of a [`Stats`] socket.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    /// The number of frames received, including error frames
+    pub rx_frames: u64,
+    /// The number of data bytes received
+    pub rx_bytes: u64,
+    /// The number of error frames received
+    pub rx_error_frames: u64,
+    /// The number of reads that failed, not counting timeouts
+    pub rx_failed: u64,
+    /// The number of frames sent
+    pub tx_frames: u64,
+    /// The number of data bytes sent
+    pub tx_bytes: u64,
+    /// The number of frames that could not be sent
+    pub tx_dropped: u64,
+}
+
+/// A socket wrapper that keeps counts of the traffic through it.
+///
+/// This is opt-in: the counters are only updated for frames that are read
+/// and written through the wrapper, so the plain sockets carry no
+/// overhead. The counters are atomic, so the wrapper can be shared across
+/// threads if the socket can be.
+///
+/// Note that frames dropped by the kernel, such as when the receive buffer
+/// of the socket overflows, are not visible to the socket, and are not
+/// counted.
+#[derive(Debug)]
+pub struct Stats<S> {
+    sock: S,
+    rx_frames: AtomicU64,
+    rx_bytes: AtomicU64,
+    rx_error_frames: AtomicU64,
+    rx_failed: AtomicU64,
+    tx_frames: AtomicU64,
+    tx_bytes: AtomicU64,
+    tx_dropped: AtomicU64,
+}
+
+impl<S: Socket> Stats<S> {
+    /// Wraps the socket, with all the counters at zero.
+    pub fn new(sock: S) -> Self {
+        Self {
+            sock,
+            rx_frames: AtomicU64::new(0),
+            rx_bytes: AtomicU64::new(0),
+            rx_error_frames: AtomicU64::new(0),
+            rx_failed: AtomicU64::new(0),
+            tx_frames: AtomicU64::new(0),
+            tx_bytes: AtomicU64::new(0),
+            tx_dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Gets a reference to the underlying socket.
+    ///
+    /// Traffic through the socket itself is not counted.
+    pub fn get_ref(&self) -> &S {
+        &self.sock
+    }
+
+    /// Unwraps the underlying socket.
+    pub fn into_inner(self) -> S {
+        self.sock
+    }
+
+    /// Gets a snapshot of the current values of the counters.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            rx_frames: self.rx_frames.load(Ordering::Relaxed),
+            rx_bytes: self.rx_bytes.load(Ordering::Relaxed),
+            rx_error_frames: self.rx_error_frames.load(Ordering::Relaxed),
+            rx_failed: self.rx_failed.load(Ordering::Relaxed),
+            tx_frames: self.tx_frames.load(Ordering::Relaxed),
+            tx_bytes: self.tx_bytes.load(Ordering::Relaxed),
+            tx_dropped: self.tx_dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Resets all the counters to zero.
+    pub fn reset(&self) {
+        for counter in [
+            &self.rx_frames,
+            &self.rx_bytes,
+            &self.rx_error_frames,
+            &self.rx_failed,
+            &self.tx_frames,
+            &self.tx_bytes,
+            &self.tx_dropped,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Blocking read of a single frame, updating the receive counters.
+    ///
+    /// A timeout is not counted as a failed read.
+    pub fn read_frame(&self) -> IoResult<S::FrameType>
+    where
+        S::FrameType: Frame,
+    {
+        let res = self.sock.read_frame();
+        match &res {
+            Ok(frame) => {
+                self.rx_frames.fetch_add(1, Ordering::Relaxed);
+                self.rx_bytes
+                    .fetch_add(frame.len() as u64, Ordering::Relaxed);
+                if frame.is_error_frame() {
+                    self.rx_error_frames.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Err(err) if err.should_retry() || err.kind() == IoErrorKind::TimedOut => (),
+            Err(_) => {
+                self.rx_failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        res
+    }
+
+    /// Writes a single frame, updating the transmit counters.
+    pub fn write_frame<F>(&self, frame: &F) -> IoResult<()>
+    where
+        F: Into<S::FrameType> + AsPtr + Frame,
+    {
+        let res = self.sock.write_frame(frame);
+        match res {
+            Ok(_) => {
+                self.tx_frames.fetch_add(1, Ordering::Relaxed);
+                self.tx_bytes
+                    .fetch_add(frame.len() as u64, Ordering::Relaxed);
+            }
+            Err(_) => {
+                self.tx_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        res
+    }
+}
+
+impl<S: AsRawFd> AsRawFd for Stats<S> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sock.as_raw_fd()
+    }
+}
+
+impl<S: SocketOptions> SocketOptions for Stats<S> {}
@@ -27,13 +27,15 @@
 //! }
 //! ```
 use crate::{
-    CanAddr, CanAnyFrame, CanFdFrame, CanFrame, Error, IoResult, Result, Socket, SocketOptions,
+    frame::CANFD_FDF, CanAddr, CanAnyFrame, CanFdFrame, CanFrame, Error, FrameBuf, IoResult,
+    Result, Socket, SocketOptions,
 };
 use futures::{prelude::*, ready, task::Context};
+use libc::{CANFD_MTU, CAN_MTU};
 use std::{
     io::{Read, Write},
     os::unix::{
-        io::{AsRawFd, OwnedFd},
+        io::{AsFd, AsRawFd, BorrowedFd, OwnedFd},
         prelude::RawFd,
     },
     pin::Pin,
@@ -44,29 +46,107 @@ use tokio::io::Interest;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
 /// An asynchronous I/O wrapped CanSocket
+///
+/// This implements `AsRawFd` and `AsFd`, so the socket options can be set
+/// through the `SocketOptions` trait, just as on a blocking socket. Note
+/// that these calls are blocking, even on an asynchronous socket, but
+/// setting an option doesn't wait on the bus, so they return right away.
 #[derive(Debug)]
-pub struct AsyncCanSocket<T: Socket>(AsyncFd<T>);
+pub struct AsyncCanSocket<T: Socket>(AsyncFd<T>, Unread);
+
+/// The offset of the flags in a libc `canfd_frame`, which is the `__pad`
+/// byte of a `can_frame`.
+const FLAGS_OFFSET: usize = 5;
+
+/// A frame that was read from the socket, but not yet passed on through
+/// `AsyncRead`, because it didn't fit in the caller's buffer.
+#[derive(Debug)]
+struct Unread {
+    buf: [u8; CANFD_MTU],
+    pos: usize,
+    len: usize,
+}
+
+impl Unread {
+    fn new() -> Self {
+        Self {
+            buf: [0; CANFD_MTU],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos == self.len
+    }
+}
 
 impl<T: Socket + From<OwnedFd>> AsyncCanSocket<T> {
     /// Open a named CAN device such as "can0, "vcan0", etc
     pub fn open(ifname: &str) -> IoResult<Self> {
         let sock = T::open(ifname)?;
-        sock.set_nonblocking(true)?;
-        Ok(Self(AsyncFd::new(sock)?))
+        Self::from_socket(sock)
     }
 
     /// Open CAN device by kernel interface number
     pub fn open_if(ifindex: u32) -> IoResult<Self> {
         let sock = T::open_iface(ifindex)?;
-        sock.set_nonblocking(true)?;
-        Ok(Self(AsyncFd::new(sock)?))
+        Self::from_socket(sock)
     }
 
     /// Open a CAN socket by address
     pub fn open_addr(addr: &CanAddr) -> IoResult<Self> {
         let sock = T::open_addr(addr)?;
+        Self::from_socket(sock)
+    }
+}
+
+impl<T: Socket> AsyncCanSocket<T> {
+    // Wraps an open socket, putting it into nonblocking mode.
+    fn from_socket(sock: T) -> IoResult<Self> {
         sock.set_nonblocking(true)?;
-        Ok(Self(AsyncFd::new(sock)?))
+        Ok(Self(AsyncFd::new(sock)?, Unread::new()))
+    }
+}
+
+impl<T: Socket + Read> AsyncCanSocket<T> {
+    // Reads the next frame as bytes into the buffer. If the whole frame
+    // doesn't fit, the rest of it is kept back for the following reads,
+    // so that the bytes of the stream are never lost or reordered. The
+    // `mark` function can tidy up the frame when it's first read, before
+    // any of it is passed on.
+    fn poll_read_unread(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+        mark: fn(&mut [u8]),
+    ) -> Poll<IoResult<()>> {
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        while self.1.is_empty() {
+            let mut guard = ready!(self.0.poll_read_ready_mut(cx))?;
+            let unread = &mut self.1;
+
+            match guard.try_io(|inner| inner.get_mut().read(&mut unread.buf)) {
+                // The end of the stream, which a CAN socket never reports
+                Ok(Ok(0)) => return Poll::Ready(Ok(())),
+                Ok(Ok(len)) => {
+                    mark(&mut unread.buf[..len]);
+                    unread.pos = 0;
+                    unread.len = len;
+                }
+                Ok(Err(err)) => return Poll::Ready(Err(err)),
+                Err(_would_block) => continue,
+            }
+        }
+
+        let unread = &mut self.1;
+        let n = buf.remaining().min(unread.len - unread.pos);
+        buf.put_slice(&unread.buf[unread.pos..unread.pos + n]);
+        unread.pos += n;
+        Poll::Ready(Ok(()))
     }
 }
 
@@ -78,6 +158,12 @@ impl<T: Socket> AsRawFd for AsyncCanSocket<T> {
     }
 }
 
+impl<T: Socket + AsFd> AsFd for AsyncCanSocket<T> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.get_ref().as_fd()
+    }
+}
+
 /// Asynchronous Can Socket
 pub type CanSocket = AsyncCanSocket<crate::CanSocket>;
 
@@ -95,6 +181,37 @@ impl CanSocket {
             .async_io(Interest::READABLE, |inner| inner.read_frame())
             .await
     }
+
+    /// Read a batch of CAN frames from the socket asynchronously
+    ///
+    /// This waits until the socket is readable, then reads all of the
+    /// frames that are queued, up to `max` of them, with a single
+    /// `recvmmsg` call. It always returns at least one frame, and reads at
+    /// least one even if `max` is zero. For a high-rate bus, this needs
+    /// far fewer await points than reading the frames one at a time.
+    ///
+    /// This allocates a new buffer on every call. A receive loop should
+    /// use `read_frames_into()` with a buffer that it keeps, instead.
+    pub async fn read_frames(&self, max: usize) -> IoResult<Vec<CanFrame>> {
+        let mut buf = FrameBuf::new(max);
+        self.read_frames_into(&mut buf).await?;
+        Ok(buf.into_frames())
+    }
+
+    /// Read a batch of CAN frames from the socket asynchronously, into a
+    /// reusable buffer
+    ///
+    /// This is the same as `read_frames()`, reading up to the capacity of
+    /// the buffer, but the buffer is reused from one call to the next, so
+    /// that frames can be received without any further allocations. The
+    /// returned slice borrows the buffer, and is valid until it's filled
+    /// again.
+    pub async fn read_frames_into<'a>(&self, buf: &'a mut FrameBuf) -> IoResult<&'a [CanFrame]> {
+        self.0
+            .async_io(Interest::READABLE, |inner| buf.fill(inner).map(|_| ()))
+            .await?;
+        Ok(buf.frames())
+    }
 }
 
 impl Stream for CanSocket {
@@ -135,28 +252,29 @@ impl Sink<CanFrame> for CanSocket {
     }
 }
 
+/// Reads frames from the socket as a stream of bytes.
+///
+/// Each frame is the bytes of a libc `can_frame` struct (`CAN_MTU`
+/// bytes). A read never returns more than one frame, but if the buffer
+/// doesn't have room for a whole frame, it gets as much as fits, and the
+/// rest of the frame is returned by the following reads. So the stream
+/// can be split into frames every `CAN_MTU` bytes, as `CanFrameCodec`
+/// does, whatever the size of the reads.
 impl AsyncRead for CanSocket {
     fn poll_read(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut ReadBuf<'_>,
     ) -> Poll<IoResult<()>> {
-        loop {
-            let mut guard = ready!(self.0.poll_read_ready_mut(cx))?;
-
-            let unfilled = buf.initialize_unfilled();
-            match guard.try_io(|inner| inner.get_mut().read(unfilled)) {
-                Ok(Ok(len)) => {
-                    buf.advance(len);
-                    return Poll::Ready(Ok(()));
-                }
-                Ok(Err(err)) => return Poll::Ready(Err(err)),
-                Err(_would_block) => continue,
-            }
-        }
+        self.poll_read_unread(cx, buf, |_| ())
     }
 }
 
+/// Writes frames to the socket as a stream of bytes.
+///
+/// Each write must contain exactly one whole frame, as the bytes of a libc
+/// `can_frame` struct (`CAN_MTU` bytes). The kernel rejects writes of any
+/// other size.
 impl AsyncWrite for CanSocket {
     fn poll_write(
         mut self: Pin<&mut Self>,
@@ -239,28 +357,34 @@ impl Sink<CanFdFrame> for CanFdSocket {
     }
 }
 
+/// Reads frames from the socket as a stream of bytes.
+///
+/// Each frame is either the bytes of a libc `can_frame` (`CAN_MTU` bytes)
+/// or a `canfd_frame` (`CANFD_MTU` bytes). To mark which, the `CANFD_FDF`
+/// flag is set in each FD frame, and the padding byte in the same place
+/// is cleared in each classic one. A read never returns more than one
+/// frame, but if the buffer doesn't have room for a whole frame, it gets
+/// as much as fits, and the rest of the frame is returned by the
+/// following reads. `CanFdFrameCodec` uses the mark to split the stream
+/// back into frames, whatever the size of the reads.
 impl AsyncRead for CanFdSocket {
     fn poll_read(
         mut self: Pin<&mut Self>,
         cx: &mut Context<'_>,
         buf: &mut ReadBuf<'_>,
     ) -> Poll<IoResult<()>> {
-        loop {
-            let mut guard = ready!(self.0.poll_read_ready_mut(cx))?;
-
-            let unfilled = buf.initialize_unfilled();
-            match guard.try_io(|inner| inner.get_mut().read(unfilled)) {
-                Ok(Ok(len)) => {
-                    buf.advance(len);
-                    return Poll::Ready(Ok(()));
-                }
-                Ok(Err(err)) => return Poll::Ready(Err(err)),
-                Err(_would_block) => continue,
-            }
-        }
+        self.poll_read_unread(cx, buf, |frame| match frame.len() {
+            CANFD_MTU => frame[FLAGS_OFFSET] |= CANFD_FDF,
+            _ => frame[FLAGS_OFFSET] = 0,
+        })
     }
 }
 
+/// Writes frames to the socket as a stream of bytes.
+///
+/// Each write must contain exactly one whole frame, as the bytes of either
+/// a libc `can_frame` (`CAN_MTU` bytes) or a `canfd_frame` (`CANFD_MTU`
+/// bytes). The kernel rejects writes of any other size.
 impl AsyncWrite for CanFdSocket {
     fn poll_write(
         mut self: Pin<&mut Self>,
@@ -286,14 +410,216 @@ impl AsyncWrite for CanFdSocket {
     }
 }
 
+// ===== Codecs =====
+
+/// A `tokio-util` codec for classic CAN 2.0 frames.
+///
+/// This maps the fixed 16-byte layout of a libc `can_frame` to and from a
+/// `CanFrame`, so that a byte-oriented transport can be turned into a
+/// stream and sink of frames with `tokio_util::codec::Framed`.
+#[cfg(feature = "tokio-util")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CanFrameCodec;
+
+#[cfg(feature = "tokio-util")]
+impl tokio_util::codec::Decoder for CanFrameCodec {
+    type Item = CanFrame;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, src: &mut bytes::BytesMut) -> IoResult<Option<CanFrame>> {
+        if src.len() < CAN_MTU {
+            return Ok(None);
+        }
+        let mut frame = crate::frame::can_frame_default();
+        crate::as_bytes_mut(&mut frame).copy_from_slice(&src.split_to(CAN_MTU));
+        Ok(Some(frame.into()))
+    }
+}
+
+#[cfg(feature = "tokio-util")]
+impl tokio_util::codec::Encoder<CanFrame> for CanFrameCodec {
+    type Error = std::io::Error;
+
+    fn encode(&mut self, frame: CanFrame, dst: &mut bytes::BytesMut) -> IoResult<()> {
+        use crate::frame::AsPtr;
+        dst.extend_from_slice(frame.as_bytes());
+        Ok(())
+    }
+}
+
+/// A `tokio-util` codec for classic and FD frames.
+///
+/// This maps the layout of a libc `can_frame` (16 bytes) or `canfd_frame`
+/// (72 bytes) to and from a `CanAnyFrame`. The type of each frame is
+/// marked by the `CANFD_FDF` flag, which is set in FD frames, and must be
+/// clear in the padding byte of classic frames, as it is in the byte
+/// stream of an FD socket. The encoder sets or clears the flag to match
+/// the frame type.
+#[cfg(feature = "tokio-util")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CanFdFrameCodec;
+
+#[cfg(feature = "tokio-util")]
+impl tokio_util::codec::Decoder for CanFdFrameCodec {
+    type Item = CanAnyFrame;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, src: &mut bytes::BytesMut) -> IoResult<Option<CanAnyFrame>> {
+        match src.get(FLAGS_OFFSET) {
+            Some(flags) if flags & CANFD_FDF != 0 => {
+                if src.len() < CANFD_MTU {
+                    return Ok(None);
+                }
+                let mut frame = crate::frame::canfd_frame_default();
+                crate::as_bytes_mut(&mut frame).copy_from_slice(&src.split_to(CANFD_MTU));
+                Ok(Some(frame.into()))
+            }
+            _ => CanFrameCodec.decode(src).map(|f| f.map(CanAnyFrame::from)),
+        }
+    }
+}
+
+#[cfg(feature = "tokio-util")]
+impl tokio_util::codec::Encoder<CanAnyFrame> for CanFdFrameCodec {
+    type Error = std::io::Error;
+
+    fn encode(&mut self, frame: CanAnyFrame, dst: &mut bytes::BytesMut) -> IoResult<()> {
+        use crate::frame::AsPtr;
+        let start = dst.len();
+        dst.extend_from_slice(frame.as_bytes());
+        match frame {
+            CanAnyFrame::Fd(_) => dst[start + FLAGS_OFFSET] |= CANFD_FDF,
+            _ => dst[start + FLAGS_OFFSET] = 0,
+        }
+        Ok(())
+    }
+}
+
 /////////////////////////////////////////////////////////////////////////////
 
+#[cfg(feature = "tokio-util")]
+#[cfg(test)]
+mod codec_tests {
+    use super::*;
+    use crate::{EmbeddedFrame, Frame, StandardId};
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    #[test]
+    fn test_can_frame_codec() {
+        let id = StandardId::new(0x123).unwrap();
+        let frame1 = CanFrame::new(id, &[1, 2, 3]).unwrap();
+        let frame2 = CanFrame::new_remote(id, 2).unwrap();
+
+        let mut buf = BytesMut::new();
+        CanFrameCodec.encode(frame1, &mut buf).unwrap();
+        CanFrameCodec.encode(frame2, &mut buf).unwrap();
+        assert_eq!(2 * CAN_MTU, buf.len());
+
+        let mut partial = buf.split_to(CAN_MTU - 1);
+        assert!(CanFrameCodec.decode(&mut partial).unwrap().is_none());
+        partial.unsplit(buf);
+
+        assert_eq!(Some(frame1), CanFrameCodec.decode(&mut partial).unwrap());
+        assert_eq!(Some(frame2), CanFrameCodec.decode(&mut partial).unwrap());
+        assert!(CanFrameCodec.decode(&mut partial).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_can_fd_frame_codec() {
+        let id = StandardId::new(0x123).unwrap();
+        let frame1 = CanAnyFrame::from(CanFrame::new(id, &[1, 2, 3]).unwrap());
+        let frame2 = CanAnyFrame::from(CanFdFrame::new(id, &[0xAA; 12]).unwrap());
+
+        let mut buf = BytesMut::new();
+        CanFdFrameCodec.encode(frame1, &mut buf).unwrap();
+        assert_eq!(CAN_MTU, buf.len());
+        assert_eq!(Some(frame1), CanFdFrameCodec.decode(&mut buf).unwrap());
+
+        CanFdFrameCodec.encode(frame2, &mut buf).unwrap();
+        assert_eq!(CANFD_MTU, buf.len());
+        assert_eq!(Some(frame2), CanFdFrameCodec.decode(&mut buf).unwrap());
+        assert!(CanFdFrameCodec.decode(&mut buf).unwrap().is_none());
+    }
+
+    // Creates an async socket, paired with a datagram socket that stands
+    // in for the kernel side of it.
+    fn socket_pair<T: Socket + From<OwnedFd>>() -> (AsyncCanSocket<T>, socket2::Socket) {
+        let (a, b) =
+            socket2::Socket::pair(socket2::Domain::UNIX, socket2::Type::SEQPACKET, None).unwrap();
+        let sock = AsyncCanSocket::from_socket(T::from(OwnedFd::from(a))).unwrap();
+        (sock, b)
+    }
+
+    // Sends the frames from the stand-in kernel socket, on a thread, so
+    // that it doesn't block when the socket buffer fills up.
+    fn send_frames<F>(peer: socket2::Socket, frames: Vec<F>) -> std::thread::JoinHandle<()>
+    where
+        F: crate::frame::AsPtr + Send + 'static,
+    {
+        std::thread::spawn(move || {
+            for frame in &frames {
+                peer.send(frame.as_bytes()).unwrap();
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_framed_can() {
+        use futures::StreamExt;
+
+        let frames: Vec<_> = (0..100u8)
+            .map(|i| CanFrame::from_raw_id(i.into(), &[i; 8]).unwrap())
+            .collect();
+
+        let (sock, peer) = socket_pair::<crate::CanSocket>();
+        let sender = send_frames(peer, frames.clone());
+
+        // A capacity that isn't a multiple of the frame size, so that some
+        // of the reads only have room for part of a frame.
+        let framed = tokio_util::codec::Framed::with_capacity(sock, CanFrameCodec, 100);
+        let recvd: Vec<_> = framed
+            .take(frames.len())
+            .map(|f| f.unwrap())
+            .collect()
+            .await;
+        assert_eq!(frames, recvd);
+        sender.join().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_framed_can_fd() {
+        use futures::StreamExt;
+
+        let id = StandardId::new(0x123).unwrap();
+        let frames: Vec<_> = (0..300u16)
+            .map(|i| match i % 3 {
+                0 => CanAnyFrame::from(CanFrame::new(id, &[i as u8; 4]).unwrap()),
+                _ => CanAnyFrame::from(CanFdFrame::new(id, &[i as u8; 64]).unwrap()),
+            })
+            .collect();
+
+        let (sock, peer) = socket_pair::<crate::CanFdSocket>();
+        let sender = send_frames(peer, frames.clone());
+
+        // The default buffer runs out part way through the 114th FD frame
+        let framed = tokio_util::codec::Framed::new(sock, CanFdFrameCodec);
+        let recvd: Vec<_> = framed
+            .take(frames.len())
+            .map(|f| f.unwrap())
+            .collect()
+            .await;
+        assert_eq!(frames, recvd);
+        sender.join().unwrap();
+    }
+}
+
 #[cfg(feature = "vcan_tests")]
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        frame::{can_frame_default, AsPtr},
+        frame::{can_frame_default, canfd_frame_default, AsPtr},
         CanFrame, Frame, IoErrorKind, StandardId,
     };
     use embedded_can::Frame as EmbeddedFrame;
@@ -360,9 +686,9 @@ mod tests {
         )
     }
 
-    /// Receive a frame from the CanFdSocket using the `tokio::io::AsyncWrite` trait
+    /// Receive a frame from the CanFdSocket using the `tokio::io::AsyncRead` trait
     async fn recv_frame_fd_with_async_read(mut socket: CanFdSocket) -> Result<CanFdSocket> {
-        let mut frame = can_frame_default();
+        let mut frame = canfd_frame_default();
         select!(
             frame = socket.read_exact(crate::as_bytes_mut(&mut frame)).fuse() => if let Ok(_bytes_read) = frame { Ok(socket) } else { panic!("unexpected") },
             _timeout = Delay::new(TIMEOUT).fuse() => Err(IoErrorKind::TimedOut.into()),
@@ -569,4 +895,65 @@ mod tests {
 
         Ok(())
     }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_read_frames() -> Result<()> {
+        let socket1 = CanSocket::open("vcan0").unwrap();
+        let socket2 = CanSocket::open("vcan0").unwrap();
+
+        for id in 1..=3 {
+            let frame = CanFrame::from_raw_id(id, &[0u8]).unwrap();
+            socket1.write_frame(frame).await?;
+        }
+
+        let mut frames = Vec::new();
+        while frames.len() < 3 {
+            frames.extend(socket2.read_frames(8).await?);
+        }
+        let ids: Vec<_> = frames.iter().map(|frame| frame.raw_id()).collect();
+        assert_eq!(vec![1, 2, 3], ids);
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_read_frames_into() -> Result<()> {
+        let socket1 = CanSocket::open("vcan0").unwrap();
+        let socket2 = CanSocket::open("vcan0").unwrap();
+
+        for id in 1..=3 {
+            let frame = CanFrame::from_raw_id(id, &[0u8]).unwrap();
+            socket1.write_frame(frame).await?;
+        }
+
+        let mut buf = FrameBuf::new(2);
+        let mut ids = Vec::new();
+        while ids.len() < 3 {
+            let frames = socket2.read_frames_into(&mut buf).await?;
+            assert!(frames.len() <= 2);
+            ids.extend(frames.iter().map(|frame| frame.raw_id()));
+        }
+        assert_eq!(vec![1, 2, 3], ids);
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_socket_options() -> Result<()> {
+        use std::os::unix::io::AsFd;
+
+        let socket1 = CanSocket::open("vcan0").unwrap();
+        let socket2 = CanSocket::open("vcan0").unwrap();
+        assert_eq!(socket2.as_raw_fd(), socket2.as_fd().as_raw_fd());
+
+        // The blocking options apply to the async socket
+        socket2.set_filter_drop_all()?;
+        write_frame(&socket1).await?;
+        assert!(recv_frame(socket2).await.is_err());
+
+        Ok(())
+    }
 }
@@ -12,11 +12,13 @@
 #[cfg(feature = "vcan_tests")]
 use socketcan::{
     frame::{ERR_MASK_ALL, ERR_MASK_NONE},
-    CanFrame, CanSocket, EmbeddedFrame, ShouldRetry, Socket, SocketOptions, StandardId,
+    CanAddr, CanAnyFrame, CanBus, CanFdFrame, CanFdSocket, CanFrame, CanSocket, EmbeddedFrame,
+    FrameBuf, MultiSocket, OpenOptions, RecvFlags, ShouldRetry, Socket, SocketOptions, StandardId,
+    Stats, TimestampConfig,
 };
 
 #[cfg(feature = "vcan_tests")]
-use std::time;
+use std::{io, time};
 
 // The virtual CAN interface to use for tests.
 #[cfg(feature = "vcan_tests")]
@@ -28,6 +30,14 @@ fn test_nonexistant_device() {
     assert!(CanSocket::open("invalid").is_err());
 }
 
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_local_addr() {
+    let addr = CanAddr::from_iface(VCAN).unwrap();
+    let sock = CanSocket::open_addr(&addr).unwrap();
+    assert_eq!(addr.ifindex(), sock.local_addr().unwrap().ifindex());
+}
+
 #[test]
 #[cfg(feature = "vcan_tests")]
 fn vcan_timeout() {
@@ -40,6 +50,17 @@ fn vcan_timeout() {
     assert!(sock.read_frame().should_retry());
 }
 
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_try_read_frame_timeout() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    // Filter out _any_ traffic
+    sock.set_filter_drop_all().unwrap();
+
+    let res = sock.try_read_frame_timeout(time::Duration::from_millis(100));
+    assert!(res.unwrap().is_none());
+}
+
 #[test]
 #[cfg(feature = "vcan_tests")]
 fn vcan_set_error_mask() {
@@ -74,10 +95,627 @@ fn vcan_test_nonblocking() {
     let sock = CanSocket::open(VCAN).unwrap();
     // Filter out _any_ traffic
     sock.set_filter_drop_all().unwrap();
+    assert!(!sock.nonblocking().unwrap());
     sock.set_nonblocking(true).unwrap();
+    assert!(sock.nonblocking().unwrap());
 
     // no timeout set, but should return immediately
     assert!(sock.read_frame().should_retry());
+
+    sock.set_nonblocking(false).unwrap();
+    assert!(!sock.nonblocking().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_frame_buf() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    for i in 0..3u8 {
+        let frame = CanFrame::new(id, &[i]).unwrap();
+        sock_tx.write_frame(&frame).unwrap();
+    }
+
+    let mut buf = FrameBuf::new(8);
+    let mut n = 0;
+    while n < 3 {
+        let frames = buf.fill(&sock_rx).unwrap();
+        assert!(!frames.is_empty());
+        for frame in frames {
+            assert_eq!(&[n as u8], frame.data());
+            n += 1;
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_write_all_frames() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    let frames: Vec<_> = (0..3u8).map(|i| CanFrame::new(id, &[i]).unwrap()).collect();
+
+    assert_eq!(3, sock_tx.write_all_frames(frames).unwrap());
+    for i in 0..3u8 {
+        assert_eq!(&[i], sock_rx.read_frame().unwrap().data());
+    }
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_raw_frame() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanFrame::new(id, &[1, 2, 3]).unwrap();
+    sock_tx.write_raw_frame(frame.as_ref()).unwrap();
+
+    let raw = sock_rx.read_raw_frame().unwrap();
+    assert_eq!(0x123, raw.can_id);
+    assert_eq!(&[1, 2, 3], &raw.data[..raw.can_dlc as usize]);
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_peek_frame() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanFrame::new(id, &[1, 2, 3]).unwrap();
+    sock_tx.write_frame(&frame).unwrap();
+
+    assert_eq!(frame, sock_rx.peek_frame().unwrap());
+    assert_eq!(frame, sock_rx.peek_frame().unwrap());
+    assert_eq!(frame, sock_rx.read_frame().unwrap());
+    assert!(sock_rx.peek_frame().should_retry());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_read_frames_with_timestamps() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx.set_timestamp(true).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let start = time::SystemTime::now();
+    let id = StandardId::new(0x123).unwrap();
+    for i in 0..3u8 {
+        let frame = CanFrame::new(id, &[i]).unwrap();
+        sock_tx.write_frame(&frame).unwrap();
+    }
+
+    let mut buf = Vec::new();
+    while buf.len() < 3 {
+        sock_rx.read_frames_with_timestamps(&mut buf, 8).unwrap();
+    }
+
+    for (i, (frame, ts)) in buf.iter().enumerate() {
+        assert_eq!(&[i as u8], frame.data());
+        assert!(*ts >= start - time::Duration::from_secs(1));
+    }
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_multi_socket() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let mut socks = MultiSocket::open([VCAN]).unwrap();
+    let ifindex = CanAddr::from_iface(VCAN).unwrap().ifindex();
+
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanFrame::new(id, &[1, 2, 3]).unwrap();
+    sock_tx.write_frame(&frame).unwrap();
+
+    let timeout = time::Duration::from_millis(100);
+    assert_eq!((ifindex, frame), socks.read_frame_timeout(timeout).unwrap());
+
+    let err = socks.read_frame_timeout(timeout).unwrap_err();
+    assert_eq!(io::ErrorKind::TimedOut, err.kind());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_stats() {
+    let sock_tx = Stats::new(CanSocket::open(VCAN).unwrap());
+    let sock_rx = Stats::new(CanSocket::open(VCAN).unwrap());
+    sock_rx
+        .get_ref()
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanFrame::new(id, &[1, 2, 3]).unwrap();
+    sock_tx.write_frame(&frame).unwrap();
+    sock_rx.read_frame().unwrap();
+    assert!(sock_rx.read_frame().should_retry());
+
+    let stats = sock_tx.snapshot();
+    assert_eq!((1, 3), (stats.tx_frames, stats.tx_bytes));
+
+    let stats = sock_rx.snapshot();
+    assert_eq!(
+        (1, 3, 0),
+        (stats.rx_frames, stats.rx_bytes, stats.rx_failed)
+    );
+
+    sock_rx.reset();
+    assert_eq!(0, sock_rx.snapshot().rx_frames);
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_join_filters() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    assert!(!sock.join_filters().unwrap());
+
+    sock.set_join_filters(true).unwrap();
+    assert!(sock.join_filters().unwrap());
+
+    sock.set_join_filters(false).unwrap();
+    assert!(!sock.join_filters().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_filter_data_only() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+    sock_rx.set_filter_accept_data_only().unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    let remote = CanFrame::new_remote(id, 2).unwrap();
+    let data = CanFrame::new(id, &[1, 2]).unwrap();
+    sock_tx.write_frame(&remote).unwrap();
+    sock_tx.write_frame(&data).unwrap();
+
+    let frame = sock_rx.read_frame().unwrap();
+    assert!(frame.is_data_frame());
+    assert!(sock_rx.read_frame().should_retry());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_write_frame_timeout() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanFrame::new(id, &[1, 2, 3]).unwrap();
+    sock_tx
+        .write_frame_timeout(&frame, time::Duration::from_millis(100))
+        .unwrap();
+    assert_eq!(frame, sock_rx.read_frame().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_open_path() {
+    let path = format!("/sys/class/net/{}", VCAN);
+    let sock = CanSocket::open_path(path).unwrap();
+    assert_eq!(VCAN, sock.local_addr().unwrap().iface_name().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_read_frame_deadline() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    sock.set_filter_drop_all().unwrap();
+
+    let start = time::Instant::now();
+    let err = sock
+        .read_frame_deadline(start + time::Duration::from_millis(50))
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::TimedOut, err.kind());
+
+    let err = sock.read_frame_deadline(start).unwrap_err();
+    assert_eq!(io::ErrorKind::TimedOut, err.kind());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_read_frame_with_flags() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_tx.set_recv_own_msgs(true).unwrap();
+    sock_tx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanFrame::new(id, &[1, 2, 3]).unwrap();
+    sock_tx.write_frame(&frame).unwrap();
+
+    let (_, flags) = sock_rx.read_frame_with_flags().unwrap();
+    assert!(flags.contains(RecvFlags::LOOPBACK));
+    assert!(!flags.contains(RecvFlags::OWN));
+
+    let (_, flags) = sock_tx.read_frame_with_flags().unwrap();
+    assert!(flags.contains(RecvFlags::LOOPBACK | RecvFlags::OWN));
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_priority() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    assert_eq!(0, sock.priority().unwrap());
+
+    sock.set_priority(3).unwrap();
+    assert_eq!(3, sock.priority().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_tx_timestamp() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    sock.set_tx_timestamping(true).unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanFrame::new(id, &[1, 2, 3]).unwrap();
+    sock.write_frame(&frame).unwrap();
+
+    // vcan doesn't report transmit times, but reading the queue still works
+    assert!(sock.read_tx_timestamp().is_ok());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_can_bus() {
+    let bus_tx = CanBus::open(VCAN).unwrap();
+    let bus_rx = CanBus::open(VCAN).unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanAnyFrame::from(CanFrame::new(id, &[1, 2, 3]).unwrap());
+    bus_tx.send(&frame).unwrap();
+    assert_eq!(frame, bus_rx.recv().unwrap());
+
+    let fdframe = CanAnyFrame::from(CanFdFrame::new(id, &[0u8; 12]).unwrap());
+    match bus_tx.send(&fdframe) {
+        Ok(()) => {
+            assert!(bus_tx.is_fd());
+            assert_eq!(fdframe, bus_rx.recv().unwrap());
+        }
+        Err(err) => {
+            assert!(!bus_tx.is_fd());
+            assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_write_frame_confirmed() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanFrame::new(id, &[1, 2, 3]).unwrap();
+    let timeout = time::Duration::from_millis(100);
+
+    // No echo without receiving our own messages
+    let err = sock.write_frame_confirmed(&frame, timeout).unwrap_err();
+    assert_eq!(io::ErrorKind::TimedOut, err.kind());
+
+    sock.set_recv_own_msgs(true).unwrap();
+    sock.write_frame_confirmed(&frame, timeout).unwrap();
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_connect() {
+    let addr = CanAddr::from_iface(VCAN).unwrap();
+
+    // Opened on all interfaces, and connected to one for writes
+    let sock_tx = CanSocket::open_iface(0).unwrap();
+    sock_tx.connect(addr.ifindex());
+    assert_eq!(Some(addr.ifindex()), sock_tx.peer_ifindex());
+
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanFrame::new(id, &[1, 2, 3]).unwrap();
+    sock_tx.write_frame(&frame).unwrap();
+    assert_eq!(frame, sock_rx.read_frame().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_fd_connect() {
+    let addr = CanAddr::from_iface(VCAN).unwrap();
+
+    let sock_tx = CanFdSocket::open_iface(0).unwrap();
+    sock_tx.connect(addr.ifindex());
+    assert_eq!(Some(addr.ifindex()), sock_tx.peer_ifindex());
+
+    let sock_rx = CanFdSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    // The socket is still in FD mode, and sends both types of frame
+    let id = StandardId::new(0x123).unwrap();
+    let frames = [
+        CanAnyFrame::from(CanFdFrame::new(id, &[0xAA; 16]).unwrap()),
+        CanAnyFrame::from(CanFrame::new(id, &[1, 2, 3]).unwrap()),
+    ];
+    for frame in frames {
+        sock_tx.write_frame(&frame).unwrap();
+        assert_eq!(frame, sock_rx.read_frame().unwrap());
+    }
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_fd_write_frame_to() {
+    let addr = CanAddr::from_iface(VCAN).unwrap();
+
+    // Opened on all interfaces, so writes need an address
+    let sock_tx = CanFdSocket::open_iface(0).unwrap();
+
+    let sock_rx = CanFdSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    // Raw CAN sockets can't be connected, so FD frames are addressed per call
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanFdFrame::new(id, &[0xAA; 16]).unwrap();
+    sock_tx.write_frame_to(&frame, &addr).unwrap();
+    assert_eq!(CanAnyFrame::from(frame), sock_rx.read_frame().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_timestamp_config() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+    sock_rx
+        .set_timestamp_config(TimestampConfig::Software)
+        .unwrap();
+
+    let frame = CanFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+    sock_tx.write_frame(&frame).unwrap();
+
+    let (rx, ts) = sock_rx.read_frame_with_timestamps().unwrap();
+    assert_eq!(frame, rx);
+    assert!(ts.software.is_some());
+    // vcan has no hardware clock
+    assert!(ts.hardware.is_none());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_open_with() {
+    use std::os::unix::io::AsRawFd;
+
+    let is_cloexec = |sock: &CanSocket| {
+        let flags = unsafe { libc::fcntl(sock.as_raw_fd(), libc::F_GETFD) };
+        flags & libc::FD_CLOEXEC != 0
+    };
+
+    let sock = CanSocket::open(VCAN).unwrap();
+    assert!(is_cloexec(&sock));
+    assert!(!sock.nonblocking().unwrap());
+
+    let opts = OpenOptions::new().cloexec(false).nonblocking(true);
+    let sock = CanSocket::open_with(VCAN, &opts).unwrap();
+    assert!(!is_cloexec(&sock));
+    assert!(sock.nonblocking().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_fd_blocking_can() {
+    use socketcan::BlockingCan;
+
+    let mut sock_tx = CanFdSocket::open(VCAN).unwrap();
+    let mut sock_rx = CanFdSocket::open(VCAN).unwrap();
+
+    let id = StandardId::new(0x123).unwrap();
+    let frames = [
+        CanAnyFrame::from(CanFrame::new(id, &[1, 2, 3]).unwrap()),
+        CanAnyFrame::from(CanFdFrame::new(id, &[0x55; 24]).unwrap()),
+    ];
+    for frame in &frames {
+        sock_tx.transmit(frame).unwrap();
+        assert_eq!(*frame, sock_rx.receive().unwrap());
+    }
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_fd_nonblocking_can() {
+    use socketcan::NonBlockingCan;
+
+    let mut sock_tx = CanFdSocket::open(VCAN).unwrap();
+    let mut sock_rx = CanFdSocket::open(VCAN).unwrap();
+    sock_rx.set_nonblocking(true).unwrap();
+
+    assert!(matches!(sock_rx.receive(), Err(nb::Error::WouldBlock)));
+
+    let id = StandardId::new(0x123).unwrap();
+    let frame = CanAnyFrame::from(CanFdFrame::new(id, &[0x55; 24]).unwrap());
+    assert!(matches!(sock_tx.transmit(&frame), Ok(None)));
+
+    // vcan delivers right away, but allow a moment for it to loop back
+    std::thread::sleep(time::Duration::from_millis(10));
+    assert_eq!(frame, sock_rx.receive().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_unbound_socket() {
+    use socketcan::UnboundSocket;
+
+    let addr = CanAddr::from_iface(VCAN).unwrap();
+
+    let sock = UnboundSocket::new().unwrap();
+    sock.set_filter_drop_all().unwrap();
+    let sock = sock.bind(&addr).unwrap();
+    assert_eq!(addr.ifindex(), sock.local_addr().unwrap().ifindex());
+
+    let sock = UnboundSocket::new().unwrap().bind_fd(&addr).unwrap();
+    assert_eq!(addr.ifindex(), sock.local_addr().unwrap().ifindex());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_timestamp_ioctl() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let mut sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let frame = CanFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+    let before = time::SystemTime::now();
+    sock_tx.write_frame(&frame).unwrap();
+
+    let (rx, ts) = sock_rx.read_frame_with_timestamp_ioctl().unwrap();
+    assert_eq!(frame, rx);
+    assert!(ts >= before - time::Duration::from_secs(1));
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_write_frame_insist_with() {
+    use socketcan::RetryPolicy;
+
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+
+    let frame = CanFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+    let policies = [
+        RetryPolicy::Spin,
+        RetryPolicy::Poll,
+        RetryPolicy::Backoff {
+            initial: time::Duration::from_millis(1),
+            max: time::Duration::from_millis(10),
+        },
+    ];
+    for policy in policies {
+        sock_tx.write_frame_insist_with(&frame, policy).unwrap();
+        assert_eq!(frame, sock_rx.read_frame().unwrap());
+    }
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_is_fd_enabled() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    assert!(!sock.is_fd_enabled().unwrap());
+
+    let sock = CanFdSocket::open(VCAN).unwrap();
+    assert!(sock.is_fd_enabled().unwrap());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_multi_socket_hotplug() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let mut socks = MultiSocket::from_sockets([]);
+
+    let ifindex = socks.add_interface(VCAN).unwrap();
+    assert_eq!(CanAddr::from_iface(VCAN).unwrap().ifindex(), ifindex);
+    assert_eq!(ifindex, socks.add_interface(VCAN).unwrap());
+    assert_eq!(1, socks.len());
+
+    let frame = CanFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+    sock_tx.write_frame(&frame).unwrap();
+    let timeout = time::Duration::from_millis(100);
+    assert_eq!((ifindex, frame), socks.read_frame_timeout(timeout).unwrap());
+
+    socks.remove_interface(VCAN).unwrap();
+    assert!(socks.is_empty());
+    let err = socks.remove_interface(VCAN).unwrap_err();
+    assert_eq!(io::ErrorKind::NotFound, err.kind());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_write_frame_echo_latency() {
+    let sock = CanSocket::open(VCAN).unwrap();
+    sock.set_timestamp_config(TimestampConfig::Software)
+        .unwrap();
+
+    let frame = CanFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+    let timeout = time::Duration::from_millis(100);
+    let latency = sock.write_frame_echo_latency(&frame, timeout).unwrap();
+    assert!(latency < timeout);
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_open_in_netns() {
+    use std::{fs::File, os::unix::io::AsFd};
+
+    // Entering our own namespace finds the same interface
+    let netns = File::open("/proc/self/ns/net").unwrap();
+    let sock = CanSocket::open_in_netns(VCAN, netns.as_fd()).unwrap();
+    let addr = CanAddr::from_iface(VCAN).unwrap();
+    assert_eq!(addr.ifindex(), sock.local_addr().unwrap().ifindex());
+}
+
+#[test]
+#[cfg(feature = "vcan_tests")]
+fn vcan_test_read_frame_with_ancillary() {
+    let sock_tx = CanSocket::open(VCAN).unwrap();
+    let sock_rx = CanSocket::open(VCAN).unwrap();
+    sock_rx
+        .set_read_timeout(time::Duration::from_millis(100))
+        .unwrap();
+    sock_rx.set_timestamp(true).unwrap();
+    sock_rx.set_rxq_overflow(true).unwrap();
+
+    let frame = CanFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+    sock_tx.write_frame(&frame).unwrap();
+
+    let mut cmsg_buf = [0u8; 128];
+    let (rx, data) = sock_rx.read_frame_with_ancillary(&mut cmsg_buf).unwrap();
+    assert_eq!(frame, rx);
+    assert!(!data.is_truncated());
+    assert_eq!(
+        Some(CanAddr::from_iface(VCAN).unwrap().ifindex()),
+        data.ifindex()
+    );
+    assert_eq!(Some(0), data.dropped());
+    assert!(data.timestamps().software.is_some());
 }
 
 /*